@@ -0,0 +1,148 @@
+use crate::app::error::AppError;
+use crate::db::archive::{bind_value, quote_ident};
+use crate::domains::diary::analytics::rebuild_diary_analytics;
+use crate::domains::goals::analytics::recompute_goal_analytics;
+use crate::domains::habits::analytics::recompute_habit_analytics;
+use crate::domains::jobs::analytics::recompute_job_analytics;
+use crate::utils::time::LogicalDay;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+
+// Derived per-record figures. Nothing in these tables is user data: every row can be
+// thrown away and rebuilt from the records and their history, so they are left out of
+// archives and sync.
+pub const CACHE_TABLES: &[&str] = &[
+    "diary_analytics_cache",
+    "habit_analytics_cache",
+    "goal_analytics_cache",
+    "job_analytics_cache",
+];
+
+// A cached row is served while it is for the current logical day and younger than the
+// configured TTL; anything else is recomputed.
+pub fn is_fresh(
+    computed_for_date: &str,
+    computed_at: i64,
+    day: &LogicalDay,
+    ttl_secs: i64,
+) -> bool {
+    computed_for_date == day.today_string() && day.now().timestamp() - computed_at < ttl_secs
+}
+
+// Writes a cache row whose field names match the table's columns, replacing any older
+// row for the same record.
+pub async fn save_cache_row<T: Serialize>(
+    pool: &SqlitePool,
+    table: &str,
+    row: &T,
+) -> Result<(), AppError> {
+    let Ok(Value::Object(fields)) = serde_json::to_value(row) else {
+        return Err(AppError::Internal(format!(
+            "Could not serialize a cache row for {}",
+            table
+        )));
+    };
+
+    let columns: Vec<String> = fields.keys().map(|column| quote_ident(column)).collect();
+    let sql = format!(
+        "INSERT OR REPLACE INTO {} ({}) VALUES ({})",
+        quote_ident(table),
+        columns.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+    let mut query = sqlx::query(&sql);
+    for (column, value) in &fields {
+        query = bind_value(query, value, table, column)?;
+    }
+    query.execute(pool).await?;
+    Ok(())
+}
+
+// Drops every cached row; each one is recomputed the next time it is read.
+pub async fn clear_analytics_caches(pool: &SqlitePool) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    for table in CACHE_TABLES {
+        sqlx::query(&format!("DELETE FROM {}", table))
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheRebuildReport {
+    pub diary_entries: usize,
+    pub habits: usize,
+    pub goals: usize,
+    pub job_applications: usize,
+}
+
+// Empties the caches and recomputes a row for every record.
+pub async fn rebuild_analytics_caches(
+    pool: &SqlitePool,
+    day: &LogicalDay,
+) -> Result<CacheRebuildReport, AppError> {
+    clear_analytics_caches(pool).await?;
+    let mut report = CacheRebuildReport {
+        diary_entries: rebuild_diary_analytics(pool, day).await?,
+        ..Default::default()
+    };
+
+    let habit_ids: Vec<String> =
+        sqlx::query_scalar("SELECT habit_id FROM habits WHERE is_deleted = 0")
+            .fetch_all(pool)
+            .await?;
+    for habit_id in &habit_ids {
+        recompute_habit_analytics(pool, habit_id, day).await?;
+    }
+    report.habits = habit_ids.len();
+
+    let goal_ids: Vec<String> =
+        sqlx::query_scalar("SELECT goal_id FROM goals WHERE is_deleted = 0")
+            .fetch_all(pool)
+            .await?;
+    for goal_id in &goal_ids {
+        recompute_goal_analytics(pool, goal_id, day).await?;
+    }
+    report.goals = goal_ids.len();
+
+    let job_ids: Vec<String> =
+        sqlx::query_scalar("SELECT job_application_id FROM job_applications WHERE is_deleted = 0")
+            .fetch_all(pool)
+            .await?;
+    for job_id in &job_ids {
+        recompute_job_analytics(pool, job_id, day).await?;
+    }
+    report.job_applications = job_ids.len();
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::time::FixedClock;
+    use chrono::DateTime;
+
+    fn day_at(at: &str) -> LogicalDay {
+        let at = DateTime::parse_from_rfc3339(at).unwrap().to_utc();
+        LogicalDay::new(Some("UTC"), 0, &FixedClock(at))
+    }
+
+    #[test]
+    fn a_row_is_fresh_within_its_day_and_ttl() {
+        let day = day_at("2026-03-10T12:00:00Z");
+        let computed_at = day.now().timestamp() - 600;
+        assert!(is_fresh("2026-03-10", computed_at, &day, 3600));
+        assert!(!is_fresh("2026-03-10", computed_at, &day, 600));
+    }
+
+    #[test]
+    fn a_row_from_another_day_is_stale_even_within_the_ttl() {
+        let day = day_at("2026-03-11T00:05:00Z");
+        let computed_at = day.now().timestamp() - 600;
+        assert!(!is_fresh("2026-03-10", computed_at, &day, 3600));
+    }
+}
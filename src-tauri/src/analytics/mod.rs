@@ -1,3 +1,4 @@
-﻿pub mod health_scores;
+﻿pub mod cache;
+pub mod health_scores;
 pub mod streaks;
 pub mod trends;
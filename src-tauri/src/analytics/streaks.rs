@@ -1 +1,87 @@
-﻿// Streaks analytics logic
+﻿use chrono::NaiveDate;
+use std::collections::BTreeSet;
+
+// The run of consecutive days ending on `today`, or ending yesterday while today is
+// still open, as (first day, length).
+pub fn streak_ending(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> Option<(NaiveDate, i32)> {
+    let mut day = if days.contains(&today) {
+        Some(today)
+    } else {
+        today.pred_opt()
+    };
+    let mut run: Option<(NaiveDate, i32)> = None;
+    while let Some(date) = day.filter(|d| days.contains(d)) {
+        run = Some((date, run.map_or(1, |(_, length)| length + 1)));
+        day = date.pred_opt();
+    }
+    run
+}
+
+// The length of the longest run of consecutive days.
+pub fn longest_streak(days: &BTreeSet<NaiveDate>) -> i32 {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days {
+        current = match previous {
+            Some(previous) if previous.succ_opt() == Some(day) => current + 1,
+            _ => 1,
+        };
+        longest = longest.max(current);
+        previous = Some(day);
+    }
+    longest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::time::{FixedClock, LogicalDay, DATE_FORMAT};
+    use chrono::DateTime;
+
+    fn today(day_start_hour: u32, at: &str) -> NaiveDate {
+        let at = DateTime::parse_from_rfc3339(at).unwrap().to_utc();
+        LogicalDay::new(Some("Europe/Berlin"), day_start_hour, &FixedClock(at)).today()
+    }
+
+    fn days(dates: &[&str]) -> BTreeSet<NaiveDate> {
+        dates
+            .iter()
+            .map(|d| NaiveDate::parse_from_str(d, DATE_FORMAT).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn an_unlogged_today_does_not_break_the_streak() {
+        let logged = days(&["2026-03-08", "2026-03-09"]);
+        // Just after midnight on the 10th the streak is still open.
+        let (_, length) = streak_ending(&logged, today(0, "2026-03-09T23:30:00Z")).unwrap();
+        assert_eq!(length, 2);
+        // A whole missed day ends it.
+        assert_eq!(
+            streak_ending(&logged, today(0, "2026-03-10T23:30:00Z")),
+            None
+        );
+    }
+
+    #[test]
+    fn the_day_start_hour_keeps_late_nights_on_the_previous_day() {
+        let logged = days(&["2026-03-08", "2026-03-09", "2026-03-10"]);
+        // 03:30 on the 11th in Berlin, with days starting at 04:00: still the 10th.
+        let at = "2026-03-11T02:30:00Z";
+        assert_eq!(
+            today(4, at),
+            days(&["2026-03-10"]).into_iter().next().unwrap()
+        );
+        assert_eq!(
+            streak_ending(&logged, today(4, at)).map(|(_, n)| n),
+            Some(3)
+        );
+        // With days starting at midnight the 11th is open but unlogged; the run holds.
+        assert_eq!(
+            streak_ending(&logged, today(0, at)).map(|(_, n)| n),
+            Some(3)
+        );
+        assert_eq!(longest_streak(&logged), 3);
+    }
+}
@@ -1 +1,19 @@
-﻿// Trends analytics logic
+﻿use chrono::{Duration, NaiveDate};
+use std::collections::BTreeMap;
+
+// Mean daily value over the `window` days ending on `day`; days without a value count
+// as zero.
+pub fn rolling_average(values: &BTreeMap<NaiveDate, i64>, day: NaiveDate, window: i64) -> f64 {
+    let start = day - Duration::days(window - 1);
+    let total: i64 = values.range(start..=day).map(|(_, value)| value).sum();
+    total as f64 / window as f64
+}
+
+// Share of `total` that `part` makes up, or 0 when there is nothing to divide.
+pub fn ratio(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
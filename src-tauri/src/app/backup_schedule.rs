@@ -0,0 +1,56 @@
+use crate::app::state::SharedState;
+use crate::db::backup::{create_backup, list_backups, prune_backups, BackupReason};
+use chrono::Utc;
+use std::time::Duration;
+
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+pub fn spawn_backup_schedule(state: SharedState) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(BACKUP_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let config = state.config();
+            if config.backup.interval_hours == 0 {
+                continue;
+            }
+            let Ok(unlocked) = state.unlocked_vault().await else {
+                continue;
+            };
+            let backup_dir = config.backup_dir(&unlocked.vault.vault_id);
+            let last_backup_at = match list_backups(&backup_dir) {
+                Ok(backups) => backups.first().map(|b| b.created_at),
+                Err(e) => {
+                    log::warn!("Could not list backups: {}", e);
+                    continue;
+                }
+            };
+            let due = last_backup_at.map_or(true, |at| {
+                Utc::now().timestamp() - at >= i64::from(config.backup.interval_hours) * 3600
+            });
+            if !due {
+                continue;
+            }
+
+            if let Err(e) = create_backup(
+                &unlocked.pools.reader,
+                &backup_dir,
+                &unlocked.key,
+                BackupReason::Scheduled,
+            )
+            .await
+            {
+                log::error!("Scheduled backup failed: {}", e);
+                continue;
+            }
+            if let Err(e) = prune_backups(
+                &backup_dir,
+                config.backup.keep_daily,
+                config.backup.keep_weekly,
+            ) {
+                log::warn!("Could not prune old backups: {}", e);
+            }
+        }
+    });
+}
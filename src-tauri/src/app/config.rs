@@ -1,11 +1,236 @@
-﻿pub struct Config {
-    pub database_url: String,
+﻿use crate::app::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+// Years the diary can start in; the yearly pages are dated YYYY-MM-DD.
+pub const DIARY_START_YEARS: RangeInclusive<i32> = 1..=9999;
+
+// Layered: built-in defaults, then `config.toml` in the app config dir, then
+// `NOCTURNE_*` environment variables. Relative paths resolve against the app data dir.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub database_path: PathBuf,
+    pub log_level: String,
+    pub diary_start_year: i32,
+    pub day_start_hour: u32,    // local hour at which a new day begins, 0-23
+    pub auto_lock_minutes: u32, // 0 disables auto-lock
+    pub allow_plaintext_database: bool,
+    pub sync_folder: Option<PathBuf>, // shared folder for device sync; None disables it
+    pub trash_retention_days: u32,    // deleted records are purged after this; 0 keeps them
+    pub cache: CacheConfig,
+    pub backup: BackupConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    pub dashboard_ttl_secs: i64,
+    pub analytics_ttl_secs: i64, // per-record habit, goal, job and diary figures
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    pub directory: PathBuf,
+    pub interval_hours: u32, // 0 disables scheduled backups
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            database_url: "sqlite:nocturne.db".to_string(),
+            database_path: PathBuf::from("nocturne.db"),
+            log_level: "info".to_string(),
+            diary_start_year: 2026,
+            day_start_hour: 0,
+            auto_lock_minutes: 15,
+            allow_plaintext_database: false,
+            sync_folder: None,
+            trash_retention_days: 30,
+            cache: CacheConfig::default(),
+            backup: BackupConfig::default(),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            dashboard_ttl_secs: 3600,
+            analytics_ttl_secs: 3600,
         }
     }
 }
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("backups"),
+            interval_hours: 24,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(config_dir: &Path, data_dir: &Path) -> Result<Config, AppError> {
+        let path = config_dir.join(CONFIG_FILE_NAME);
+        let mut config = if path.exists() {
+            let raw = fs::read_to_string(&path)?;
+            toml::from_str(&raw).map_err(|e| {
+                AppError::Validation(format!("Invalid config file {}: {}", path.display(), e))
+            })?
+        } else {
+            Config::default()
+        };
+
+        config.apply_env_overrides()?;
+        config.validate()?;
+        if config.database_path.is_relative() {
+            config.database_path = data_dir.join(&config.database_path);
+        }
+        if config.backup.directory.is_relative() {
+            config.backup.directory = data_dir.join(&config.backup.directory);
+        }
+
+        Ok(config)
+    }
+
+    // Values the rest of the app relies on being in range, checked once at load.
+    pub fn validate(&self) -> Result<(), AppError> {
+        validate_diary_start_year(self.diary_start_year)?;
+        validate_day_start_hour(self.day_start_hour)
+    }
+
+    // Each vault keeps its backups in its own subdirectory.
+    pub fn backup_dir(&self, vault_id: &str) -> PathBuf {
+        self.backup.directory.join(vault_id)
+    }
+
+    pub fn log_level_filter(&self) -> log::LevelFilter {
+        log::LevelFilter::from_str(&self.log_level).unwrap_or(log::LevelFilter::Info)
+    }
+
+    fn apply_env_overrides(&mut self) -> Result<(), AppError> {
+        if let Ok(value) = env::var("NOCTURNE_DATABASE_PATH") {
+            self.database_path = PathBuf::from(value);
+        }
+        if let Ok(value) = env::var("NOCTURNE_LOG_LEVEL") {
+            self.log_level = value;
+        }
+        if let Some(value) = parse_env("NOCTURNE_DIARY_START_YEAR")? {
+            self.diary_start_year = value;
+        }
+        if let Some(value) = parse_env("NOCTURNE_DAY_START_HOUR")? {
+            self.day_start_hour = value;
+        }
+        if let Some(value) = parse_env("NOCTURNE_AUTO_LOCK_MINUTES")? {
+            self.auto_lock_minutes = value;
+        }
+        if let Ok(value) = env::var("NOCTURNE_ALLOW_PLAINTEXT_DB") {
+            self.allow_plaintext_database = value == "1" || value.eq_ignore_ascii_case("true");
+        }
+        if let Ok(value) = env::var("NOCTURNE_SYNC_FOLDER") {
+            self.sync_folder = Some(PathBuf::from(value));
+        }
+        if let Some(value) = parse_env("NOCTURNE_TRASH_RETENTION_DAYS")? {
+            self.trash_retention_days = value;
+        }
+        if let Some(value) = parse_env("NOCTURNE_DASHBOARD_CACHE_TTL_SECS")? {
+            self.cache.dashboard_ttl_secs = value;
+        }
+        if let Some(value) = parse_env("NOCTURNE_ANALYTICS_CACHE_TTL_SECS")? {
+            self.cache.analytics_ttl_secs = value;
+        }
+        if let Ok(value) = env::var("NOCTURNE_BACKUP_DIR") {
+            self.backup.directory = PathBuf::from(value);
+        }
+        if let Some(value) = parse_env("NOCTURNE_BACKUP_INTERVAL_HOURS")? {
+            self.backup.interval_hours = value;
+        }
+        Ok(())
+    }
+}
+
+// Writes one top-level key back to `config.toml`, leaving the rest of the file
+// (and anything only set through the environment) untouched.
+pub fn persist_setting(
+    config_dir: &Path,
+    key: &str,
+    value: impl Into<toml::Value>,
+) -> Result<(), AppError> {
+    let path = config_dir.join(CONFIG_FILE_NAME);
+    let mut table = if path.exists() {
+        fs::read_to_string(&path)?
+            .parse::<toml::Table>()
+            .map_err(|e| {
+                AppError::Validation(format!("Invalid config file {}: {}", path.display(), e))
+            })?
+    } else {
+        toml::Table::new()
+    };
+    table.insert(key.to_string(), value.into());
+
+    fs::create_dir_all(config_dir)?;
+    fs::write(&path, table.to_string())?;
+    Ok(())
+}
+
+// Drops one top-level key from `config.toml` so it falls back to its default.
+pub fn remove_setting(config_dir: &Path, key: &str) -> Result<(), AppError> {
+    let path = config_dir.join(CONFIG_FILE_NAME);
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut table = fs::read_to_string(&path)?
+        .parse::<toml::Table>()
+        .map_err(|e| {
+            AppError::Validation(format!("Invalid config file {}: {}", path.display(), e))
+        })?;
+    if table.remove(key).is_some() {
+        fs::write(&path, table.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn validate_diary_start_year(year: i32) -> Result<(), AppError> {
+    if DIARY_START_YEARS.contains(&year) {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!(
+            "diary_start_year must be between {} and {}, not {}.",
+            DIARY_START_YEARS.start(),
+            DIARY_START_YEARS.end(),
+            year
+        )))
+    }
+}
+
+pub fn validate_day_start_hour(hour: u32) -> Result<(), AppError> {
+    if hour <= 23 {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!(
+            "day_start_hour must be a whole hour between 0 and 23, not {}.",
+            hour
+        )))
+    }
+}
+
+fn parse_env<T: FromStr>(name: &str) -> Result<Option<T>, AppError> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| AppError::Validation(format!("Invalid value for {}: {}", name, value))),
+        Err(_) => Ok(None),
+    }
+}
@@ -0,0 +1,46 @@
+use crate::app::error::AppError;
+use crate::utils::time::LogicalDay;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+const DEVICE_ID_FILE: &str = "device_id";
+
+// Who is making a change, from where and on which logical day, built per command and
+// handed to the repositories so every write fills in the same audit columns and dates.
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub profile_id: Option<String>,
+    pub device_id: String,
+    pub session_id: String,
+    pub app_version: &'static str,
+    pub day: LogicalDay,
+}
+
+impl RequestContext {
+    pub fn profile_id(&self) -> Option<&str> {
+        self.profile_id.as_deref()
+    }
+
+    // Timestamp for `created_at` / `updated_at`, from the same instant as `day`.
+    pub fn timestamp(&self) -> i64 {
+        self.day.now().timestamp()
+    }
+}
+
+// Generated on first launch and kept next to the config, so it is stable across
+// launches and vaults but never leaves this machine with a copied config.
+pub fn load_or_create_device_id(config_dir: &Path) -> Result<String, AppError> {
+    let path = config_dir.join(DEVICE_ID_FILE);
+    if let Ok(existing) = fs::read_to_string(&path) {
+        let existing = existing.trim();
+        if Uuid::parse_str(existing).is_ok() {
+            return Ok(existing.to_string());
+        }
+        log::warn!("Device ID file is invalid; generating a new one");
+    }
+
+    let device_id = Uuid::new_v4().to_string();
+    fs::write(&path, &device_id)?;
+    Ok(device_id)
+}
@@ -12,17 +12,201 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    #[error("Validation error: {}", .0.iter().map(|e| format!("{}: {}", e.field, e.message)).collect::<Vec<_>>().join("; "))]
+    InvalidFields(Vec<FieldError>),
+
     #[error("Io error: {0}")]
     Io(#[from] std::io::Error),
 
     #[error("Internal error: {0}")]
     Internal(String),
 
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+
+    #[error("Vault is read-only: {0}")]
+    ReadOnly(String),
+
+    #[error("Vault is locked")]
+    Locked,
+
     #[error("Unauthorized")]
     Unauthorized,
 
-    #[error("Not found: {0}")]
-    NotFound(String),
+    #[error("Not found: {entity} {}", .entity_id.as_deref().unwrap_or_default())]
+    NotFound {
+        entity: &'static str,
+        entity_id: Option<String>,
+    },
+}
+
+impl AppError {
+    pub fn not_found(entity: &'static str, entity_id: impl Into<String>) -> Self {
+        AppError::NotFound {
+            entity,
+            entity_id: Some(entity_id.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+// Collects every field problem in an input so the form can show them all at once.
+#[derive(Debug, Default)]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn into_result(self) -> Result<(), AppError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::InvalidFields(self.0))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Validation,
+    NotFound,
+    Conflict,
+    Unauthorized,
+    Internal,
+}
+
+// What the frontend receives. `code` is stable and meant for matching; `message` is
+// safe to show. `detail` carries the underlying error and is only sent in debug
+// builds, so raw database or IO errors never reach a release UI.
+#[derive(Debug, Serialize)]
+pub struct ErrorPayload {
+    pub code: &'static str,
+    pub kind: ErrorKind,
+    pub field: Option<String>,
+    pub entity_id: Option<String>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl AppError {
+    pub fn payload(&self) -> ErrorPayload {
+        let (code, kind, message) = match self {
+            AppError::Database(sqlx::Error::RowNotFound) => (
+                "record_not_found",
+                ErrorKind::NotFound,
+                "The requested record does not exist.".to_string(),
+            ),
+            AppError::Database(sqlx::Error::Database(e)) if e.is_unique_violation() => (
+                "duplicate_record",
+                ErrorKind::Conflict,
+                "A record with the same value already exists.".to_string(),
+            ),
+            AppError::Database(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => (
+                "related_record_missing",
+                ErrorKind::Conflict,
+                "A related record is missing.".to_string(),
+            ),
+            AppError::Database(sqlx::Error::PoolTimedOut) => (
+                "vault_busy",
+                ErrorKind::Conflict,
+                "The vault is busy. Try again in a moment.".to_string(),
+            ),
+            AppError::Database(_) => (
+                "database_error",
+                ErrorKind::Internal,
+                "The vault could not be read or updated.".to_string(),
+            ),
+            AppError::Migration(_) => (
+                "migration_failed",
+                ErrorKind::Internal,
+                "The vault's data could not be upgraded.".to_string(),
+            ),
+            AppError::Validation(message) => {
+                ("invalid_input", ErrorKind::Validation, message.clone())
+            }
+            AppError::InvalidFields(errors) => (
+                "invalid_fields",
+                ErrorKind::Validation,
+                errors
+                    .first()
+                    .map(|e| e.message.clone())
+                    .unwrap_or_default(),
+            ),
+            AppError::Io(_) => (
+                "io_error",
+                ErrorKind::Internal,
+                "A file could not be read or written.".to_string(),
+            ),
+            AppError::Internal(_) => (
+                "internal_error",
+                ErrorKind::Internal,
+                "Something went wrong.".to_string(),
+            ),
+            AppError::Encryption(message) => {
+                ("encryption_failed", ErrorKind::Internal, message.clone())
+            }
+            AppError::ReadOnly(_) => (
+                "vault_read_only",
+                ErrorKind::Conflict,
+                "The vault is read-only because its last upgrade failed and was rolled back."
+                    .to_string(),
+            ),
+            AppError::Locked => (
+                "vault_locked",
+                ErrorKind::Unauthorized,
+                "The vault is locked.".to_string(),
+            ),
+            AppError::Unauthorized => (
+                "invalid_passphrase",
+                ErrorKind::Unauthorized,
+                "The passphrase is incorrect.".to_string(),
+            ),
+            AppError::NotFound { entity, .. } => (
+                "not_found",
+                ErrorKind::NotFound,
+                format!("{} not found.", entity),
+            ),
+        };
+
+        let (field, fields) = match self {
+            AppError::InvalidFields(errors) => {
+                (errors.first().map(|e| e.field.clone()), errors.clone())
+            }
+            _ => (None, Vec::new()),
+        };
+        let entity_id = match self {
+            AppError::NotFound { entity_id, .. } => entity_id.clone(),
+            _ => None,
+        };
+
+        ErrorPayload {
+            code,
+            kind,
+            field,
+            entity_id,
+            message,
+            fields,
+            detail: cfg!(debug_assertions).then(|| self.to_string()),
+        }
+    }
 }
 
 impl Serialize for AppError {
@@ -30,6 +214,10 @@ impl Serialize for AppError {
     where
         S: Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        let payload = self.payload();
+        if payload.kind == ErrorKind::Internal {
+            log::error!("{}: {}", payload.code, self);
+        }
+        payload.serialize(serializer)
     }
 }
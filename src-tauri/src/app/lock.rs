@@ -0,0 +1,25 @@
+use crate::app::state::SharedState;
+use std::time::Duration;
+
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+pub fn spawn_idle_lock(state: SharedState) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let timeout_minutes = state.config().auto_lock_minutes;
+            if state.is_locked() || timeout_minutes == 0 {
+                continue;
+            }
+            if state.idle_seconds() >= i64::from(timeout_minutes) * 60 {
+                log::info!(
+                    "Locking vault after {} minutes of inactivity",
+                    timeout_minutes
+                );
+                state.lock_vault().await;
+            }
+        }
+    });
+}
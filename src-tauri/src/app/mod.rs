@@ -1,3 +1,6 @@
-﻿pub mod config;
+﻿pub mod backup_schedule;
+pub mod config;
+pub mod context;
 pub mod error;
+pub mod lock;
 pub mod state;
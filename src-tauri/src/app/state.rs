@@ -1,15 +1,278 @@
-﻿use sqlx::SqlitePool;
+﻿use crate::app::config::Config;
+use crate::app::context::RequestContext;
+use crate::app::error::AppError;
+use crate::db::attachments::{attachments_dir, collect_attachment_garbage};
+use crate::db::connection::DbPools;
+use crate::db::encryption::DataKey;
+use crate::db::slugs::backfill_slugs;
+use crate::db::trash::purge_expired_trash;
+use crate::db::vault::{OpenedVault, Vault, VaultRegistry};
+use crate::domains::habits::repository::backfill_created_dates;
+use crate::domains::profile::model::Profile;
+use crate::domains::profile::repository::fetch_last_selected_profile;
+use crate::utils::time::{LogicalDay, SimulatedClock};
+use arc_swap::{ArcSwap, ArcSwapOption};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use uuid::Uuid;
 
 pub struct AppState {
-    pub db: SqlitePool,
-    pub config: AppConfig,
+    // None until the vault has been unlocked with the passphrase. Commands clone the
+    // pool handles out of here, so nothing is held locked while their queries run.
+    pub db: ArcSwapOption<DbPools>,
+    pub config: ArcSwap<Config>,
+    pub config_dir: PathBuf,
+    pub device_id: String,
+    // New on every launch.
+    pub session_id: String,
+    pub encryption_status: EncryptionStatus,
+    pub last_activity_at: AtomicI64,
+    // Set while the vault is open in read-only recovery mode after a failed upgrade.
+    pub recovery_reason: ArcSwapOption<String>,
+    // The profile records are stamped with. Restored from the vault on unlock.
+    pub active_profile: ArcSwapOption<Profile>,
+    // Real time unless a debug build has moved it with `set_simulated_time`.
+    pub clock: SimulatedClock,
+    // Only taken by unlock, lock, vault switching and rekeying.
+    pub session: Mutex<VaultSession>,
 }
 
-#[derive(Clone, Default)]
-pub struct AppConfig {
-    pub encryption_key: Option<String>,
+pub struct VaultSession {
+    pub encryption_key: Option<DataKey>,
+    pub active_vault: Vault,
+    pub vaults: VaultRegistry,
 }
 
-pub type SharedState = Arc<Mutex<AppState>>;
+pub struct UnlockedVault {
+    pub pools: DbPools,
+    pub vault: Vault,
+    pub key: DataKey,
+}
+
+impl AppState {
+    pub fn new(
+        config: Config,
+        config_dir: PathBuf,
+        device_id: String,
+        encryption_status: EncryptionStatus,
+        vaults: VaultRegistry,
+    ) -> Self {
+        Self {
+            db: ArcSwapOption::empty(),
+            config: ArcSwap::from_pointee(config),
+            config_dir,
+            device_id,
+            session_id: Uuid::new_v4().to_string(),
+            encryption_status,
+            last_activity_at: AtomicI64::new(0),
+            recovery_reason: ArcSwapOption::empty(),
+            active_profile: ArcSwapOption::empty(),
+            clock: SimulatedClock::default(),
+            session: Mutex::new(VaultSession {
+                encryption_key: None,
+                active_vault: vaults.initial_vault(),
+                vaults,
+            }),
+        }
+    }
+
+    // Every command goes through here, so a locked vault refuses them all and
+    // any successful access counts as activity for the idle timer.
+    pub fn pools(&self) -> Result<DbPools, AppError> {
+        let pools = self.db.load_full().ok_or(AppError::Locked)?;
+        self.touch();
+        Ok(DbPools::clone(&pools))
+    }
+
+    pub fn reader(&self) -> Result<SqlitePool, AppError> {
+        Ok(self.pools()?.reader)
+    }
+
+    pub fn writer(&self) -> Result<SqlitePool, AppError> {
+        let pools = self.pools()?;
+        if let Some(reason) = self.recovery_reason.load_full() {
+            return Err(AppError::ReadOnly(format!(
+                "the last upgrade failed and was rolled back ({})",
+                reason
+            )));
+        }
+        Ok(pools.writer)
+    }
+
+    // The pools, vault and data key taken together under the session lock, so they
+    // can't straddle a vault switch. Doesn't count as activity for the idle timer.
+    pub async fn unlocked_vault(&self) -> Result<UnlockedVault, AppError> {
+        let session = self.session.lock().await;
+        match (self.db.load_full(), session.encryption_key.clone()) {
+            (Some(pools), Some(key)) => Ok(UnlockedVault {
+                pools: DbPools::clone(&pools),
+                vault: session.active_vault.clone(),
+                key,
+            }),
+            _ => Err(AppError::Locked),
+        }
+    }
+
+    pub fn active_profile_id(&self) -> Option<String> {
+        self.active_profile
+            .load()
+            .as_ref()
+            .map(|p| p.profile_id.clone())
+    }
+
+    pub fn request_context(&self) -> RequestContext {
+        RequestContext {
+            profile_id: self.active_profile_id(),
+            device_id: self.device_id.clone(),
+            session_id: self.session_id.clone(),
+            app_version: env!("CARGO_PKG_VERSION"),
+            day: self.logical_day(),
+        }
+    }
+
+    // The active profile's timezone and, if it sets one, its own `day_start_hour`
+    // preference; otherwise the configured day start and the system timezone.
+    pub fn logical_day(&self) -> LogicalDay {
+        let profile = self.active_profile.load();
+        let profile = profile.as_deref();
+        let day_start_hour = profile
+            .and_then(|p| serde_json::from_str::<serde_json::Value>(&p.preferences_json).ok())
+            .and_then(|prefs| prefs.get("day_start_hour")?.as_u64())
+            .map(|hour| hour as u32)
+            .unwrap_or(self.config().day_start_hour);
+
+        LogicalDay::new(
+            profile.and_then(|p| p.timezone.as_deref()),
+            day_start_hour,
+            &self.clock,
+        )
+    }
+
+    pub fn config(&self) -> Arc<Config> {
+        self.config.load_full()
+    }
+
+    pub fn update_config(&self, update: impl Fn(&mut Config)) {
+        self.config.rcu(|current| {
+            let mut config = Config::clone(current);
+            update(&mut config);
+            config
+        });
+    }
+
+    // Fail closed: never write diary data to disk unencrypted without an explicit opt-in.
+    pub fn cipher_available(&self) -> Result<bool, AppError> {
+        match self.encryption_status {
+            EncryptionStatus::Encrypted => Ok(true),
+            EncryptionStatus::PlaintextOptIn => Ok(false),
+            EncryptionStatus::Unavailable => Err(AppError::Encryption(
+                "This build does not support SQLCipher; refusing to open the database unencrypted"
+                    .to_string(),
+            )),
+        }
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.db.load().is_none()
+    }
+
+    pub fn touch(&self) {
+        self.last_activity_at
+            .store(Utc::now().timestamp(), Ordering::Relaxed);
+    }
+
+    pub fn idle_seconds(&self) -> i64 {
+        Utc::now().timestamp() - self.last_activity_at.load(Ordering::Relaxed)
+    }
+
+    pub async fn lock_vault(&self) {
+        let mut session = self.session.lock().await;
+        self.close_vault(&mut session).await;
+    }
+
+    // Closes the pools and drops the data key, which zeroizes it. Commands that already
+    // cloned a pool finish first; `close` waits for their connections to come back.
+    pub async fn close_vault(&self, session: &mut VaultSession) {
+        if let Some(pools) = self.db.swap(None) {
+            pools.close().await;
+        }
+        self.active_profile.store(None);
+        session.encryption_key = None;
+    }
+
+    // A vault stuck before the profiles migration simply has no active profile.
+    pub async fn reload_active_profile(&self, reader: &SqlitePool) {
+        let profile = fetch_last_selected_profile(reader)
+            .await
+            .unwrap_or_else(|e| {
+                log::warn!("Could not restore the active profile: {}", e);
+                None
+            });
+        self.active_profile.store(profile.map(Arc::new));
+    }
+
+    // Hot-swaps the pool over to a freshly opened vault and remembers it as the last one used.
+    pub async fn activate_vault(
+        &self,
+        session: &mut VaultSession,
+        vault: Vault,
+        opened: OpenedVault,
+    ) -> Result<(), AppError> {
+        self.close_vault(session).await;
+        self.reload_active_profile(&opened.pools.reader).await;
+        // A vault opened read-only after a failed migration keeps its trash.
+        if opened.recovery_reason.is_none() {
+            let now = Utc::now().timestamp();
+            let retention_days = self.config().trash_retention_days;
+            if let Err(e) = purge_expired_trash(&opened.pools.writer, retention_days, now).await {
+                log::warn!("Could not purge expired trash: {}", e);
+            }
+            // After the purge, so files only purged records linked to go too.
+            let dir = attachments_dir(&vault.database_path);
+            if let Err(e) = collect_attachment_garbage(&opened.pools.writer, &dir).await {
+                log::warn!("Could not collect unreferenced attachments: {}", e);
+            }
+            let ctx = self.request_context();
+            if let Err(e) = backfill_slugs(&opened.pools.writer, &ctx).await {
+                log::warn!("Could not backfill slugs: {}", e);
+            }
+            if let Err(e) = backfill_created_dates(&opened.pools.writer, &ctx).await {
+                log::warn!("Could not backfill habit created dates: {}", e);
+            }
+        }
+        self.db.store(Some(Arc::new(opened.pools)));
+        self.recovery_reason
+            .store(opened.recovery_reason.map(Arc::new));
+        session.encryption_key = Some(opened.key);
+        self.touch();
+
+        session.vaults.mark_opened(&vault.vault_id);
+        session.active_vault = vault;
+        session.vaults.save(&self.config_dir)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptionStatus {
+    Encrypted,      // SQLCipher is linked; the vault is encrypted at rest
+    Unavailable,    // SQLCipher is missing; opening the vault is refused
+    PlaintextOptIn, // SQLCipher is missing and the user chose to continue unencrypted
+}
+
+impl EncryptionStatus {
+    pub fn from_probe(cipher_version: Option<&str>, allow_plaintext_database: bool) -> Self {
+        match (cipher_version, allow_plaintext_database) {
+            (Some(_), _) => EncryptionStatus::Encrypted,
+            (None, true) => EncryptionStatus::PlaintextOptIn,
+            (None, false) => EncryptionStatus::Unavailable,
+        }
+    }
+}
+
+pub type SharedState = Arc<AppState>;
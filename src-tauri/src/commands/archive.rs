@@ -0,0 +1,68 @@
+use crate::app::config::persist_setting;
+use crate::app::error::AppError;
+use crate::app::state::SharedState;
+use crate::db::archive::{
+    export_archive, import_archive, Archive, DashboardSettings, ImportMode, ManifestEntry,
+    TableImportSummary,
+};
+use crate::db::backup::{create_backup, BackupReason};
+use std::fs;
+use tauri::State;
+
+#[tauri::command]
+pub async fn export_vault(
+    state: State<'_, SharedState>,
+    path: String,
+) -> Result<Vec<ManifestEntry>, AppError> {
+    let pool = &state.reader()?;
+    let settings = DashboardSettings::from(state.config().as_ref());
+    let archive = export_archive(pool, settings).await?;
+
+    let json = serde_json::to_string_pretty(&archive)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize archive: {}", e)))?;
+    fs::write(&path, json)?;
+    Ok(archive.manifest)
+}
+
+#[tauri::command]
+pub async fn import_vault(
+    state: State<'_, SharedState>,
+    path: String,
+    mode: ImportMode,
+) -> Result<Vec<TableImportSummary>, AppError> {
+    state.writer()?;
+    let unlocked = state.unlocked_vault().await?;
+    let archive: Archive = serde_json::from_slice(&fs::read(&path)?)
+        .map_err(|e| AppError::Validation(format!("Archive could not be read: {}", e)))?;
+
+    // A snapshot of the vault as it was, in case the import isn't what the user wanted.
+    let backup_dir = state.config().backup_dir(&unlocked.vault.vault_id);
+    create_backup(
+        &unlocked.pools.reader,
+        &backup_dir,
+        &unlocked.key,
+        BackupReason::PreImport,
+    )
+    .await?;
+
+    let summaries = import_archive(&unlocked.pools.writer, &archive, mode).await?;
+
+    // Merging keeps this device's settings; replacing takes the archived ones too.
+    if let (ImportMode::Replace, Some(settings)) = (mode, archive.settings) {
+        persist_setting(
+            &state.config_dir,
+            "diary_start_year",
+            i64::from(settings.diary_start_year),
+        )?;
+        persist_setting(
+            &state.config_dir,
+            "day_start_hour",
+            i64::from(settings.day_start_hour),
+        )?;
+        state.update_config(|config| {
+            config.diary_start_year = settings.diary_start_year;
+            config.day_start_hour = settings.day_start_hour;
+        });
+    }
+    Ok(summaries)
+}
@@ -0,0 +1,73 @@
+use crate::app::error::AppError;
+use crate::app::state::SharedState;
+use crate::db::attachments::{self, attachments_dir, AttachmentLink};
+use crate::db::relations::EntityRef;
+use std::path::Path;
+use tauri::ipc::Response;
+use tauri::State;
+
+#[tauri::command]
+pub async fn add_attachment(
+    state: State<'_, SharedState>,
+    entity: EntityRef,
+    path: String,
+    role: Option<String>,
+) -> Result<AttachmentLink, AppError> {
+    state.writer()?;
+    let unlocked = state.unlocked_vault().await?;
+    attachments::add_attachment(
+        &unlocked.pools.writer,
+        &attachments_dir(&unlocked.vault.database_path),
+        &unlocked.key,
+        &entity,
+        Path::new(&path),
+        role.as_deref(),
+        &state.request_context(),
+    )
+    .await
+}
+
+// Sent as raw bytes rather than a JSON array of numbers.
+#[tauri::command]
+pub async fn fetch_attachment(
+    state: State<'_, SharedState>,
+    attachment_id: String,
+) -> Result<Response, AppError> {
+    state.reader()?;
+    let unlocked = state.unlocked_vault().await?;
+    let bytes = attachments::read_attachment(
+        &unlocked.pools.reader,
+        &attachments_dir(&unlocked.vault.database_path),
+        &unlocked.key,
+        &attachment_id,
+    )
+    .await?;
+    Ok(Response::new(bytes))
+}
+
+#[tauri::command]
+pub async fn list_attachments(
+    state: State<'_, SharedState>,
+    entity: EntityRef,
+) -> Result<Vec<AttachmentLink>, AppError> {
+    let pool = &state.reader()?;
+    attachments::list_attachments(pool, &entity).await
+}
+
+#[tauri::command]
+pub async fn unlink_attachment(
+    state: State<'_, SharedState>,
+    entity: EntityRef,
+    attachment_id: String,
+    role: Option<String>,
+) -> Result<(), AppError> {
+    let pool = &state.writer()?;
+    attachments::unlink_attachment(
+        pool,
+        &entity,
+        &attachment_id,
+        role.as_deref(),
+        &state.request_context(),
+    )
+    .await
+}
@@ -0,0 +1,74 @@
+use crate::app::error::AppError;
+use crate::app::state::SharedState;
+use crate::db::backup::{
+    self, create_backup as write_backup, prepare_restore, prune_backups, swap_in_restored,
+    BackupInfo, BackupReason,
+};
+use crate::db::connection::establish_connection;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub async fn list_backups(state: State<'_, SharedState>) -> Result<Vec<BackupInfo>, AppError> {
+    let unlocked = state.unlocked_vault().await?;
+    state.touch();
+    backup::list_backups(&state.config().backup_dir(&unlocked.vault.vault_id))
+}
+
+#[tauri::command]
+pub async fn create_backup(state: State<'_, SharedState>) -> Result<BackupInfo, AppError> {
+    let unlocked = state.unlocked_vault().await?;
+    state.touch();
+    let config = state.config();
+    let backup_dir = config.backup_dir(&unlocked.vault.vault_id);
+
+    let info = write_backup(
+        &unlocked.pools.reader,
+        &backup_dir,
+        &unlocked.key,
+        BackupReason::Manual,
+    )
+    .await?;
+    prune_backups(
+        &backup_dir,
+        config.backup.keep_daily,
+        config.backup.keep_weekly,
+    )?;
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn restore_backup(
+    state: State<'_, SharedState>,
+    backup_id: String,
+) -> Result<(), AppError> {
+    // Held throughout so the vault can't be switched, locked or rekeyed mid-restore.
+    let session = state.session.lock().await;
+    let pools = state.pools()?;
+    let key = session.encryption_key.clone().ok_or(AppError::Locked)?;
+    let database_path = session.active_vault.database_path.clone();
+    let backup_dir = state.config().backup_dir(&session.active_vault.vault_id);
+
+    let restore_path = prepare_restore(&backup_dir, &backup_id, &database_path, &key).await?;
+
+    // The current data is kept as a backup of its own, so a restore can be undone.
+    if let Err(e) = write_backup(&pools.reader, &backup_dir, &key, BackupReason::PreRestore).await {
+        let _ = std::fs::remove_file(&restore_path);
+        return Err(e);
+    }
+
+    if let Some(pools) = state.db.swap(None) {
+        pools.close().await;
+    }
+    let result = swap_in_restored(&restore_path, &database_path);
+    if result.is_ok() {
+        // The restored copy was migrated in `prepare_restore`.
+        state.recovery_reason.store(None);
+    }
+
+    // Reopen whichever database is in place now, even if the swap failed.
+    let pools = establish_connection(&database_path, &key).await?;
+    state.reload_active_profile(&pools.reader).await;
+    state.db.store(Some(Arc::new(pools)));
+    result
+}
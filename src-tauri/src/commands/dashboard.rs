@@ -3,7 +3,6 @@ use crate::app::state::SharedState;
 use crate::domains::dashboard::analytics::compute_dashboard;
 use crate::domains::dashboard::model::DashboardSnapshot;
 use crate::domains::dashboard::repository::{get_latest_snapshot, save_snapshot};
-use chrono::Utc;
 use tauri::State;
 
 #[tauri::command]
@@ -11,13 +10,18 @@ pub async fn get_dashboard(
     state: State<'_, SharedState>,
     force_refresh: bool,
 ) -> Result<DashboardSnapshot, AppError> {
-    let state_lock = state.lock().await;
-    let pool = &state_lock.db;
+    let pools = state.pools()?;
+    let active_profile_id = state.active_profile_id();
+    let day = state.logical_day();
 
+    // A snapshot from another profile or from before the day rolled over is stale
+    // whatever its TTL says.
     if !force_refresh {
-        if let Some(snapshot) = get_latest_snapshot(pool).await? {
+        if let Some(snapshot) = get_latest_snapshot(&pools.reader).await?.filter(|s| {
+            s.active_profile_id == active_profile_id && s.dashboard_date == day.today_string()
+        }) {
             // Check if cache is still valid
-            let now = Utc::now().timestamp();
+            let now = day.now().timestamp();
             if let Some(valid_until) = snapshot.cache_valid_until {
                 if now < valid_until {
                     return Ok(snapshot);
@@ -26,11 +30,17 @@ pub async fn get_dashboard(
         }
     }
 
-    // Drop lock before expensive computation if possible,
-    // but here we need the pool which is inside the lock.
-    // For now, we keep the lock as the computation is mostly DB queries.
-    let fresh_snapshot = compute_dashboard(pool).await?;
-    save_snapshot(pool, &fresh_snapshot).await?;
+    let fresh_snapshot = compute_dashboard(
+        &pools.reader,
+        state.config().cache.dashboard_ttl_secs,
+        active_profile_id,
+        &day,
+    )
+    .await?;
+    // A vault opened read-only for recovery still gets its dashboard, just not cached.
+    if let Ok(writer) = state.writer() {
+        save_snapshot(&writer, &fresh_snapshot).await?;
+    }
 
     Ok(fresh_snapshot)
 }
@@ -0,0 +1,72 @@
+use crate::app::error::AppError;
+use crate::app::state::SharedState;
+use crate::utils::time::Clock;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+#[cfg(debug_assertions)]
+use crate::app::error::FieldError;
+#[cfg(debug_assertions)]
+use crate::utils::time::{LogicalDay, DATE_FORMAT};
+#[cfg(debug_assertions)]
+use chrono::{DateTime, NaiveDate, Utc};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClockStatus {
+    pub now: i64,
+    pub logical_date: String,
+    pub timezone: String,
+    pub is_simulated: bool,
+}
+
+#[tauri::command]
+pub async fn get_clock_status(state: State<'_, SharedState>) -> Result<ClockStatus, AppError> {
+    Ok(clock_status(&state))
+}
+
+// Debug builds only; release builds don't register it. `at` is an RFC 3339 timestamp,
+// or a YYYY-MM-DD logical day to jump to, as far into that day as the current one has
+// got; None goes back to real time. The clock keeps running from wherever it was set, and nothing is persisted
+// across launches.
+#[cfg(debug_assertions)]
+#[tauri::command]
+pub async fn set_simulated_time(
+    state: State<'_, SharedState>,
+    at: Option<String>,
+) -> Result<ClockStatus, AppError> {
+    match at {
+        Some(at) => {
+            let target = parse_simulated_time(&at, &state.logical_day())?;
+            log::info!("Simulating time at {}", target);
+            state.clock.travel_to(target);
+        }
+        None => state.clock.reset(),
+    }
+    Ok(clock_status(&state))
+}
+
+fn clock_status(state: &SharedState) -> ClockStatus {
+    let day = state.logical_day();
+    ClockStatus {
+        now: state.clock.now().timestamp(),
+        logical_date: day.today_string(),
+        timezone: day.timezone_label(),
+        is_simulated: state.clock.is_simulated(),
+    }
+}
+
+#[cfg(debug_assertions)]
+fn parse_simulated_time(at: &str, day: &LogicalDay) -> Result<DateTime<Utc>, AppError> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(at) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(at, DATE_FORMAT) {
+        let into_day = day.now() - day.start_of(day.today());
+        return Ok(day.start_of(date) + into_day);
+    }
+
+    Err(AppError::InvalidFields(vec![FieldError {
+        field: "at".to_string(),
+        message: "Expected an RFC 3339 timestamp or a YYYY-MM-DD date.".to_string(),
+    }]))
+}
@@ -1,11 +1,16 @@
-﻿use crate::app::error::AppError;
+﻿use crate::analytics::cache::{is_fresh, save_cache_row};
+use crate::app::error::AppError;
 use crate::app::state::SharedState;
-use crate::domains::diary::analytics::recompute_diary_analytics;
-use crate::domains::diary::model::{CreateDiaryInput, DiaryEntry};
+use crate::domains::diary::analytics::{
+    compute_diary_analytics, fetch_diary_analytics, recompute_diary_analytics,
+};
+use crate::domains::diary::history::{list_diary_revisions, DiaryRevision};
+use crate::domains::diary::model::{CreateDiaryInput, DiaryAnalyticsCache, DiaryEntry};
 use crate::domains::diary::repository::{
     ensure_yearly_entries, fetch_entry, fetch_sub_pages, insert_entry, list_entries, update_entry,
 };
 use crate::domains::diary::validation::{validate_create, validate_update};
+use crate::utils::hashing::ContentHash;
 use tauri::State;
 
 #[tauri::command]
@@ -13,20 +18,19 @@ pub async fn create_diary_entry(
     state: State<'_, SharedState>,
     input: CreateDiaryInput,
 ) -> Result<DiaryEntry, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.writer()?;
 
-    validate_create(&input)?;
-    let id = insert_entry(pool, &input).await?;
-    recompute_diary_analytics(pool, &input.entry_date).await?;
+    let ctx = state.request_context();
+    validate_create(&input, state.config().diary_start_year, ctx.day.today())?;
+    let id = insert_entry(pool, &input, &ctx).await?;
+    recompute_diary_analytics(pool, &input.entry_date, &ctx.day).await?;
     let entry = fetch_entry(pool, &id).await?;
     Ok(entry)
 }
 
 #[tauri::command]
 pub async fn get_diary_entries(state: State<'_, SharedState>) -> Result<Vec<DiaryEntry>, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.reader()?;
     let entries = list_entries(pool).await?;
     Ok(entries)
 }
@@ -36,20 +40,25 @@ pub async fn get_diary_entry(
     state: State<'_, SharedState>,
     id: String,
 ) -> Result<DiaryEntry, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.reader()?;
     let entry = fetch_entry(pool, &id).await?;
     Ok(entry)
 }
 
 #[tauri::command]
 pub async fn setup_diary(state: State<'_, SharedState>) -> Result<(), AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
-    ensure_yearly_entries(pool, 2026).await?;
+    let pool = &state.writer()?;
+    ensure_yearly_entries(
+        pool,
+        state.config().diary_start_year,
+        &state.request_context(),
+    )
+    .await?;
     Ok(())
 }
 
+// The arguments are the keys of the frontend's invoke payload.
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
 pub async fn update_diary_entry(
     state: State<'_, SharedState>,
@@ -63,26 +72,31 @@ pub async fn update_diary_entry(
     stress_level: Option<i32>,
     importance_level: i32,
 ) -> Result<(), AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.writer()?;
 
     // Fetch existing entry to check date
     let entry = fetch_entry(pool, &id).await?;
-    validate_update(&entry.entry_date)?;
+    validate_update(&entry.entry_date, state.logical_day().today())?;
 
-    update_entry(
-        pool,
-        &id,
+    // A save that changes nothing leaves the row and its timestamps alone.
+    let edited = DiaryEntry {
         title,
-        &content_json,
+        content_json,
         word_count,
         mood_label,
         mood_rating,
         energy_level,
         stress_level,
         importance_level,
-    )
-    .await?;
+        ..entry.clone()
+    };
+    if edited.content_hash() == entry.content_hash() {
+        return Ok(());
+    }
+
+    let ctx = state.request_context();
+    update_entry(pool, &edited, &ctx).await?;
+    recompute_diary_analytics(pool, &entry.entry_date, &ctx.day).await?;
     Ok(())
 }
 
@@ -91,8 +105,43 @@ pub async fn get_diary_sub_pages(
     state: State<'_, SharedState>,
     parent_id: String,
 ) -> Result<Vec<DiaryEntry>, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.reader()?;
     let entries = fetch_sub_pages(pool, &parent_id).await?;
     Ok(entries)
 }
+
+#[tauri::command]
+pub async fn get_diary_entry_history(
+    state: State<'_, SharedState>,
+    id: String,
+) -> Result<Vec<DiaryRevision>, AppError> {
+    let pool = &state.reader()?;
+    fetch_entry(pool, &id).await?;
+    let revisions = list_diary_revisions(pool, &id).await?;
+    Ok(revisions)
+}
+
+#[tauri::command]
+pub async fn get_diary_analytics_cache(
+    state: State<'_, SharedState>,
+    id: String,
+) -> Result<DiaryAnalyticsCache, AppError> {
+    let pools = state.pools()?;
+    let day = state.logical_day();
+    let ttl_secs = state.config().cache.analytics_ttl_secs;
+    let writer = state.writer().ok();
+
+    if writer.is_some() {
+        if let Some(analytics) = fetch_diary_analytics(&pools.reader, &id)
+            .await?
+            .filter(|a| is_fresh(&a.computed_for_date, a.computed_at, &day, ttl_secs))
+        {
+            return Ok(analytics);
+        }
+    }
+    let analytics = compute_diary_analytics(&pools.reader, &id, &day).await?;
+    if let Some(writer) = writer {
+        save_cache_row(&writer, "diary_analytics_cache", &analytics).await?;
+    }
+    Ok(analytics)
+}
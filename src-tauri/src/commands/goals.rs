@@ -1,8 +1,15 @@
+use crate::analytics::cache::{is_fresh, save_cache_row};
 use crate::app::error::AppError;
 use crate::app::state::SharedState;
-use crate::domains::goals::model::{CreateGoalInput, Goal};
-use crate::domains::goals::repository::{fetch_goal, insert_goal, list_goals};
-use crate::domains::goals::validation::validate_create_goal;
+use crate::domains::goals::analytics::{
+    compute_goal_analytics, fetch_goal_analytics, recompute_goal_analytics,
+};
+use crate::domains::goals::events::{list_goal_events, GoalProgressEvent};
+use crate::domains::goals::model::{CreateGoalInput, Goal, GoalAnalyticsCache, RenameGoalInput};
+use crate::domains::goals::repository::{
+    fetch_goal, fetch_goal_by_slug, insert_goal, list_goals, rename_goal as rename_goal_record,
+};
+use crate::domains::goals::validation::{validate_create_goal, validate_rename_goal};
 use tauri::State;
 
 #[tauri::command]
@@ -10,19 +17,19 @@ pub async fn create_goal(
     state: State<'_, SharedState>,
     input: CreateGoalInput,
 ) -> Result<Goal, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.writer()?;
 
+    let ctx = state.request_context();
     validate_create_goal(&input)?;
-    let id = insert_goal(pool, &input).await?;
+    let id = insert_goal(pool, &input, &ctx).await?;
+    recompute_goal_analytics(pool, &id, &ctx.day).await?;
     let goal = fetch_goal(pool, &id).await?;
     Ok(goal)
 }
 
 #[tauri::command]
 pub async fn get_goals(state: State<'_, SharedState>) -> Result<Vec<Goal>, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.reader()?;
     let goals = list_goals(pool).await?;
     Ok(goals)
 }
@@ -32,8 +39,71 @@ pub async fn get_goal(
     state: State<'_, SharedState>,
     goal_id: String,
 ) -> Result<Goal, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.reader()?;
     let goal = fetch_goal(pool, &goal_id).await?;
     Ok(goal)
-}
\ No newline at end of file
+}
+
+#[tauri::command]
+pub async fn get_goal_by_slug(
+    state: State<'_, SharedState>,
+    slug: String,
+) -> Result<Goal, AppError> {
+    let pool = &state.reader()?;
+    let goal = fetch_goal_by_slug(pool, &slug).await?;
+    Ok(goal)
+}
+
+#[tauri::command]
+pub async fn rename_goal(
+    state: State<'_, SharedState>,
+    goal_id: String,
+    input: RenameGoalInput,
+) -> Result<Goal, AppError> {
+    let pool = &state.writer()?;
+
+    let ctx = state.request_context();
+    validate_rename_goal(&input)?;
+    rename_goal_record(pool, &goal_id, &input, &ctx).await?;
+    recompute_goal_analytics(pool, &goal_id, &ctx.day).await?;
+    let goal = fetch_goal(pool, &goal_id).await?;
+    Ok(goal)
+}
+
+// Oldest first; each event is the goal as it stood after that change.
+#[tauri::command]
+pub async fn get_goal_timeline(
+    state: State<'_, SharedState>,
+    goal_id: String,
+) -> Result<Vec<GoalProgressEvent>, AppError> {
+    let pool = &state.reader()?;
+    fetch_goal(pool, &goal_id).await?;
+    let events = list_goal_events(pool, &goal_id).await?;
+    Ok(events)
+}
+
+// Same freshness rule as the habit figures.
+#[tauri::command]
+pub async fn get_goal_analytics_cache(
+    state: State<'_, SharedState>,
+    goal_id: String,
+) -> Result<GoalAnalyticsCache, AppError> {
+    let pools = state.pools()?;
+    let day = state.logical_day();
+    let ttl_secs = state.config().cache.analytics_ttl_secs;
+    let writer = state.writer().ok();
+
+    if writer.is_some() {
+        if let Some(analytics) = fetch_goal_analytics(&pools.reader, &goal_id)
+            .await?
+            .filter(|a| is_fresh(&a.computed_for_date, a.computed_at, &day, ttl_secs))
+        {
+            return Ok(analytics);
+        }
+    }
+    let analytics = compute_goal_analytics(&pools.reader, &goal_id, &day).await?;
+    if let Some(writer) = writer {
+        save_cache_row(&writer, "goal_analytics_cache", &analytics).await?;
+    }
+    Ok(analytics)
+}
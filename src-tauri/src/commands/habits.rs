@@ -1,13 +1,16 @@
+use crate::analytics::cache::{is_fresh, save_cache_row};
 use crate::app::error::AppError;
 use crate::app::state::SharedState;
+use crate::domains::habits::analytics::{
+    compute_habit_analytics, current_streak, fetch_habit_analytics, recompute_habit_analytics,
+};
 use crate::domains::habits::habit_log::{CreateHabitLogInput, HabitLog};
-use crate::domains::habits::model::{CreateHabitInput, Habit};
+use crate::domains::habits::model::{CreateHabitInput, Habit, HabitAnalyticsCache};
 use crate::domains::habits::repository::{
     fetch_habit, get_habit_log_for_date, get_habit_logs_for_date_range,
     insert_habit, insert_habit_log, list_habits,
 };
 use crate::domains::habits::validation::validate_create_habit;
-use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
@@ -16,19 +19,19 @@ pub async fn create_habit(
     state: State<'_, SharedState>,
     input: CreateHabitInput,
 ) -> Result<Habit, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.writer()?;
 
+    let ctx = state.request_context();
     validate_create_habit(&input)?;
-    let id = insert_habit(pool, &input).await?;
+    let id = insert_habit(pool, &input, &ctx).await?;
+    recompute_habit_analytics(pool, &id, &ctx.day).await?;
     let habit = fetch_habit(pool, &id).await?;
     Ok(habit)
 }
 
 #[tauri::command]
 pub async fn get_habits(state: State<'_, SharedState>) -> Result<Vec<Habit>, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.reader()?;
     let habits = list_habits(pool).await?;
     Ok(habits)
 }
@@ -38,8 +41,7 @@ pub async fn get_habit(
     state: State<'_, SharedState>,
     habit_id: String,
 ) -> Result<Habit, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.reader()?;
     let habit = fetch_habit(pool, &habit_id).await?;
     Ok(habit)
 }
@@ -47,10 +49,10 @@ pub async fn get_habit(
 #[tauri::command]
 pub async fn get_today_habits(
     state: State<'_, SharedState>,
-    date: String,
+    date: Option<String>,
 ) -> Result<Vec<Habit>, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.reader()?;
+    let date = date.unwrap_or_else(|| state.logical_day().today_string());
     let habits = crate::domains::habits::repository::get_today_habits(pool, &date).await?;
     Ok(habits)
 }
@@ -60,13 +62,19 @@ pub async fn log_habit_completion(
     state: State<'_, SharedState>,
     input: CreateHabitLogInput,
 ) -> Result<HabitLog, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
-
-    let _log_id = insert_habit_log(pool, &input).await?;
-    let log = get_habit_log_for_date(pool, &input.habit_id, &input.log_date)
+    let pool = &state.writer()?;
+
+    let ctx = state.request_context();
+    let log_date = input
+        .log_date
+        .clone()
+        .unwrap_or_else(|| ctx.day.today_string());
+    fetch_habit(pool, &input.habit_id).await?;
+    let _log_id = insert_habit_log(pool, &input, &log_date, &ctx).await?;
+    recompute_habit_analytics(pool, &input.habit_id, &ctx.day).await?;
+    let log = get_habit_log_for_date(pool, &input.habit_id, &log_date)
         .await?
-        .ok_or_else(|| AppError::NotFound("Habit log not found".to_string()))?;
+        .ok_or_else(|| AppError::not_found("Habit log", &input.habit_id))?;
 
     Ok(log)
 }
@@ -93,23 +101,12 @@ pub async fn get_habit_analytics(
     start_date: String,
     end_date: String,
 ) -> Result<HabitAnalytics, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.reader()?;
 
     let logs = get_habit_logs_for_date_range(pool, &habit_id, &start_date, &end_date).await?;
 
     // Calculate streak
-    let mut current_streak = 0;
-    let today = Utc::now().format("%Y-%m-%d").to_string();
-
-    for log in logs.iter().rev() {
-        if log.log_date == today && log.status == "completed" {
-            current_streak += 1;
-            // Decrement date (simplified - would need proper date arithmetic)
-        } else {
-            break;
-        }
-    }
+    let current_streak = current_streak(&logs, state.logical_day().today());
 
     // Calculate completion rate
     let completed_count = logs.iter().filter(|l| l.status == "completed").count();
@@ -146,3 +143,31 @@ pub async fn get_habit_analytics(
         heatmap_data,
     })
 }
+
+// The cached figures, recomputed first once they are stale (see `is_fresh`). A
+// vault opened read-only for recovery may predate the cache tables, so there the
+// figures are computed every time and never stored.
+#[tauri::command]
+pub async fn get_habit_analytics_cache(
+    state: State<'_, SharedState>,
+    habit_id: String,
+) -> Result<HabitAnalyticsCache, AppError> {
+    let pools = state.pools()?;
+    let day = state.logical_day();
+    let ttl_secs = state.config().cache.analytics_ttl_secs;
+    let writer = state.writer().ok();
+
+    if writer.is_some() {
+        if let Some(analytics) = fetch_habit_analytics(&pools.reader, &habit_id)
+            .await?
+            .filter(|a| is_fresh(&a.computed_for_date, a.computed_at, &day, ttl_secs))
+        {
+            return Ok(analytics);
+        }
+    }
+    let analytics = compute_habit_analytics(&pools.reader, &habit_id, &day).await?;
+    if let Some(writer) = writer {
+        save_cache_row(&writer, "habit_analytics_cache", &analytics).await?;
+    }
+    Ok(analytics)
+}
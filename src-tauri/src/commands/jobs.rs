@@ -1,10 +1,18 @@
+use crate::analytics::cache::{is_fresh, save_cache_row};
 use crate::app::error::AppError;
 use crate::app::state::SharedState;
-use crate::domains::jobs::model::{CreateJobInput, JobApplication};
+use crate::domains::jobs::analytics::{
+    compute_job_analytics, fetch_job_analytics, recompute_job_analytics,
+};
+use crate::domains::jobs::events::{list_job_events, JobStatusEvent};
+use crate::domains::jobs::model::{
+    CreateJobInput, JobAnalyticsCache, JobApplication, RenameJobInput,
+};
 use crate::domains::jobs::repository::{
-    fetch_job_application, insert_job_application, list_job_applications,
+    fetch_job_application, fetch_job_by_slug, insert_job_application, list_job_applications,
+    rename_job_application as rename_job_record,
 };
-use crate::domains::jobs::validation::validate_create_job;
+use crate::domains::jobs::validation::{validate_create_job, validate_rename_job};
 use tauri::State;
 
 #[tauri::command]
@@ -12,11 +20,12 @@ pub async fn create_job_application(
     state: State<'_, SharedState>,
     input: CreateJobInput,
 ) -> Result<JobApplication, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.writer()?;
 
+    let ctx = state.request_context();
     validate_create_job(&input)?;
-    let id = insert_job_application(pool, &input).await?;
+    let id = insert_job_application(pool, &input, &ctx).await?;
+    recompute_job_analytics(pool, &id, &ctx.day).await?;
     let job = fetch_job_application(pool, &id).await?;
     Ok(job)
 }
@@ -25,8 +34,7 @@ pub async fn create_job_application(
 pub async fn get_job_applications(
     state: State<'_, SharedState>,
 ) -> Result<Vec<JobApplication>, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.reader()?;
     let jobs = list_job_applications(pool).await?;
     Ok(jobs)
 }
@@ -36,8 +44,69 @@ pub async fn get_job_application(
     state: State<'_, SharedState>,
     job_id: String,
 ) -> Result<JobApplication, AppError> {
-    let state = state.lock().await;
-    let pool = &state.db;
+    let pool = &state.reader()?;
     let job = fetch_job_application(pool, &job_id).await?;
     Ok(job)
-}
\ No newline at end of file
+}
+
+#[tauri::command]
+pub async fn get_job_by_slug(
+    state: State<'_, SharedState>,
+    slug: String,
+) -> Result<JobApplication, AppError> {
+    let pool = &state.reader()?;
+    let job = fetch_job_by_slug(pool, &slug).await?;
+    Ok(job)
+}
+
+#[tauri::command]
+pub async fn rename_job_application(
+    state: State<'_, SharedState>,
+    job_id: String,
+    input: RenameJobInput,
+) -> Result<JobApplication, AppError> {
+    let pool = &state.writer()?;
+
+    let ctx = state.request_context();
+    validate_rename_job(&input)?;
+    rename_job_record(pool, &job_id, &input, &ctx).await?;
+    recompute_job_analytics(pool, &job_id, &ctx.day).await?;
+    let job = fetch_job_application(pool, &job_id).await?;
+    Ok(job)
+}
+
+#[tauri::command]
+pub async fn get_job_timeline(
+    state: State<'_, SharedState>,
+    job_id: String,
+) -> Result<Vec<JobStatusEvent>, AppError> {
+    let pool = &state.reader()?;
+    fetch_job_application(pool, &job_id).await?;
+    let events = list_job_events(pool, &job_id).await?;
+    Ok(events)
+}
+
+#[tauri::command]
+pub async fn get_job_analytics_cache(
+    state: State<'_, SharedState>,
+    job_id: String,
+) -> Result<JobAnalyticsCache, AppError> {
+    let pools = state.pools()?;
+    let day = state.logical_day();
+    let ttl_secs = state.config().cache.analytics_ttl_secs;
+    let writer = state.writer().ok();
+
+    if writer.is_some() {
+        if let Some(analytics) = fetch_job_analytics(&pools.reader, &job_id)
+            .await?
+            .filter(|a| is_fresh(&a.computed_for_date, a.computed_at, &day, ttl_secs))
+        {
+            return Ok(analytics);
+        }
+    }
+    let analytics = compute_job_analytics(&pools.reader, &job_id, &day).await?;
+    if let Some(writer) = writer {
+        save_cache_row(&writer, "job_analytics_cache", &analytics).await?;
+    }
+    Ok(analytics)
+}
@@ -0,0 +1,51 @@
+use crate::app::error::AppError;
+use crate::app::state::SharedState;
+use crate::db::relations::{self, EntityKind, EntityLink, EntityRef};
+use tauri::State;
+
+#[tauri::command]
+pub async fn link_entities(
+    state: State<'_, SharedState>,
+    from: EntityRef,
+    to: EntityRef,
+    relation_type: Option<String>,
+) -> Result<EntityLink, AppError> {
+    let pool = &state.writer()?;
+    relations::link_entities(
+        pool,
+        &from,
+        &to,
+        relation_type.as_deref(),
+        &state.request_context(),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn unlink_entities(
+    state: State<'_, SharedState>,
+    from: EntityRef,
+    to: EntityRef,
+    relation_type: Option<String>,
+) -> Result<(), AppError> {
+    let pool = &state.writer()?;
+    relations::unlink_entities(
+        pool,
+        &from,
+        &to,
+        relation_type.as_deref(),
+        &state.request_context(),
+    )
+    .await
+}
+
+// `kind` narrows the result to one domain, e.g. every diary entry linked to a goal.
+#[tauri::command]
+pub async fn get_links(
+    state: State<'_, SharedState>,
+    entity: EntityRef,
+    kind: Option<EntityKind>,
+) -> Result<Vec<EntityLink>, AppError> {
+    let pool = &state.reader()?;
+    relations::list_links(pool, &entity, kind).await
+}
@@ -0,0 +1,53 @@
+use crate::analytics::cache::{self, CacheRebuildReport};
+use crate::app::error::AppError;
+use crate::app::state::SharedState;
+use crate::db::backup::{create_backup, BackupReason};
+use crate::db::integrity::{self, IntegrityReport, IssueKind};
+use crate::migrations::runner::{schema_status, SchemaStatus};
+use tauri::State;
+
+#[tauri::command]
+pub async fn get_schema_status(state: State<'_, SharedState>) -> Result<SchemaStatus, AppError> {
+    let pool = &state.reader()?;
+    let recovery_reason = state.recovery_reason.load_full().map(|r| r.to_string());
+    schema_status(pool, recovery_reason).await
+}
+
+#[tauri::command]
+pub async fn run_integrity_check(
+    state: State<'_, SharedState>,
+) -> Result<IntegrityReport, AppError> {
+    let pool = &state.reader()?;
+    integrity::run_integrity_check(pool).await
+}
+
+// Repairs every issue of one kind and returns the report as it stands afterwards.
+#[tauri::command]
+pub async fn repair_integrity_issues(
+    state: State<'_, SharedState>,
+    kind: IssueKind,
+) -> Result<IntegrityReport, AppError> {
+    let writer = state.writer()?;
+    let unlocked = state.unlocked_vault().await?;
+
+    let backup_dir = state.config().backup_dir(&unlocked.vault.vault_id);
+    create_backup(
+        &unlocked.pools.reader,
+        &backup_dir,
+        &unlocked.key,
+        BackupReason::PreRepair,
+    )
+    .await?;
+
+    integrity::repair_issues(&writer, kind).await?;
+    integrity::run_integrity_check(&unlocked.pools.reader).await
+}
+
+// The caches only hold derived figures, so this is always safe to run.
+#[tauri::command]
+pub async fn rebuild_analytics_caches(
+    state: State<'_, SharedState>,
+) -> Result<CacheRebuildReport, AppError> {
+    let pool = &state.writer()?;
+    cache::rebuild_analytics_caches(pool, &state.logical_day()).await
+}
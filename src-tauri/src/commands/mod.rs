@@ -1,5 +1,17 @@
-﻿pub mod dashboard;
+﻿pub mod archive;
+pub mod attachments;
+pub mod backups;
+pub mod dashboard;
+pub mod debug;
 pub mod diary;
 pub mod goals;
 pub mod habits;
 pub mod jobs;
+pub mod links;
+pub mod maintenance;
+pub mod profiles;
+pub mod search;
+pub mod security;
+pub mod sync;
+pub mod trash;
+pub mod vaults;
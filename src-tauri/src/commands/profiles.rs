@@ -0,0 +1,89 @@
+use crate::app::error::AppError;
+use crate::app::state::SharedState;
+use crate::domains::profile::analytics::compute_profile_activity;
+use crate::domains::profile::model::{
+    CreateProfileInput, Profile, ProfileActivity, UpdateProfileInput,
+};
+use crate::domains::profile::repository::{
+    fetch_profile, insert_profile, list_profiles, mark_profile_selected, update_profile,
+};
+use crate::domains::profile::validation::{validate_create_profile, validate_update_profile};
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub async fn get_profiles(state: State<'_, SharedState>) -> Result<Vec<Profile>, AppError> {
+    let pool = &state.reader()?;
+    let profiles = list_profiles(pool).await?;
+    Ok(profiles)
+}
+
+#[tauri::command]
+pub async fn get_active_profile(
+    state: State<'_, SharedState>,
+) -> Result<Option<Profile>, AppError> {
+    state.pools()?;
+    Ok(state.active_profile.load_full().map(|p| Profile::clone(&p)))
+}
+
+// The first profile created in a vault becomes the active one.
+#[tauri::command]
+pub async fn create_profile(
+    state: State<'_, SharedState>,
+    input: CreateProfileInput,
+) -> Result<Profile, AppError> {
+    let pool = &state.writer()?;
+
+    validate_create_profile(&input)?;
+    let ctx = state.request_context();
+    let id = insert_profile(pool, &input, &ctx).await?;
+    let first = state.active_profile.load().is_none();
+    if first {
+        mark_profile_selected(pool, &id, &ctx).await?;
+    }
+    let profile = fetch_profile(pool, &id).await?;
+    if first {
+        state.active_profile.store(Some(Arc::new(profile.clone())));
+    }
+    Ok(profile)
+}
+
+#[tauri::command]
+pub async fn update_profile_details(
+    state: State<'_, SharedState>,
+    profile_id: String,
+    input: UpdateProfileInput,
+) -> Result<Profile, AppError> {
+    let pool = &state.writer()?;
+
+    validate_update_profile(&input)?;
+    update_profile(pool, &profile_id, &input, &state.request_context()).await?;
+    let profile = fetch_profile(pool, &profile_id).await?;
+    if state.active_profile_id().as_deref() == Some(profile_id.as_str()) {
+        state.active_profile.store(Some(Arc::new(profile.clone())));
+    }
+    Ok(profile)
+}
+
+#[tauri::command]
+pub async fn select_active_profile(
+    state: State<'_, SharedState>,
+    profile_id: String,
+) -> Result<Profile, AppError> {
+    let pool = &state.writer()?;
+
+    mark_profile_selected(pool, &profile_id, &state.request_context()).await?;
+    let profile = fetch_profile(pool, &profile_id).await?;
+    state.active_profile.store(Some(Arc::new(profile.clone())));
+    Ok(profile)
+}
+
+#[tauri::command]
+pub async fn get_profile_activity(
+    state: State<'_, SharedState>,
+    profile_id: String,
+) -> Result<ProfileActivity, AppError> {
+    let pool = &state.reader()?;
+    fetch_profile(pool, &profile_id).await?;
+    compute_profile_activity(pool, &profile_id).await
+}
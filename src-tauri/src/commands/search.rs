@@ -0,0 +1,13 @@
+use crate::app::error::AppError;
+use crate::app::state::SharedState;
+use crate::db::search::{self, SearchHit, SearchQuery};
+use tauri::State;
+
+#[tauri::command]
+pub async fn search(
+    state: State<'_, SharedState>,
+    query: SearchQuery,
+) -> Result<Vec<SearchHit>, AppError> {
+    let pool = &state.reader()?;
+    search::search(pool, &query).await
+}
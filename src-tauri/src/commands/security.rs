@@ -0,0 +1,120 @@
+use crate::app::config::persist_setting;
+use crate::app::error::AppError;
+use crate::app::state::{EncryptionStatus, SharedState};
+use crate::db::backup::rekey_backups;
+use crate::db::connection::establish_connection;
+use crate::db::vault::{self, open_vault, rekey_vault};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tauri::State;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultStatus {
+    pub vault_id: String,
+    pub vault_name: String,
+    pub is_initialized: bool,
+    pub is_unlocked: bool,
+    pub encryption_status: EncryptionStatus,
+    pub auto_lock_minutes: u32,
+}
+
+#[tauri::command]
+pub async fn get_vault_status(state: State<'_, SharedState>) -> Result<VaultStatus, AppError> {
+    let session = state.session.lock().await;
+    Ok(VaultStatus {
+        vault_id: session.active_vault.vault_id.clone(),
+        vault_name: session.active_vault.name.clone(),
+        is_initialized: vault::is_initialized(&session.active_vault.database_path),
+        is_unlocked: !state.is_locked(),
+        encryption_status: state.encryption_status,
+        auto_lock_minutes: state.config().auto_lock_minutes,
+    })
+}
+
+#[tauri::command]
+pub async fn unlock_vault(
+    state: State<'_, SharedState>,
+    passphrase: String,
+) -> Result<(), AppError> {
+    let mut session = state.session.lock().await;
+    if !state.is_locked() {
+        return Ok(());
+    }
+
+    let cipher_available = state.cipher_available()?;
+    let vault = session.active_vault.clone();
+    let backup_dir = state.config().backup_dir(&vault.vault_id);
+    let opened = open_vault(
+        &vault.database_path,
+        &backup_dir,
+        &passphrase,
+        cipher_available,
+    )
+    .await?;
+    state.activate_vault(&mut session, vault, opened).await
+}
+
+#[tauri::command]
+pub async fn lock_vault(state: State<'_, SharedState>) -> Result<(), AppError> {
+    state.lock_vault().await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_auto_lock_minutes(
+    state: State<'_, SharedState>,
+    minutes: u32,
+) -> Result<(), AppError> {
+    state.pools()?;
+    persist_setting(&state.config_dir, "auto_lock_minutes", i64::from(minutes))?;
+    state.update_config(|config| config.auto_lock_minutes = minutes);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn change_passphrase(
+    state: State<'_, SharedState>,
+    current_passphrase: String,
+    new_passphrase: String,
+) -> Result<(), AppError> {
+    let session = state.session.lock().await;
+    state.pools()?;
+    vault::change_passphrase(
+        &session.active_vault.database_path,
+        &current_passphrase,
+        &new_passphrase,
+    )
+}
+
+#[tauri::command]
+pub async fn rekey_database(
+    state: State<'_, SharedState>,
+    passphrase: String,
+) -> Result<(), AppError> {
+    let mut session = state.session.lock().await;
+    state.pools()?;
+    let current_key = session.encryption_key.clone().ok_or(AppError::Locked)?;
+    let database_path = session.active_vault.database_path.clone();
+
+    // Commands arriving during the rekey see a locked vault rather than waiting on it.
+    if let Some(pools) = state.db.swap(None) {
+        pools.close().await;
+    }
+
+    // Reopen with whichever key is valid on disk, even if the rekey failed.
+    let (result, key) = match rekey_vault(&database_path, &current_key, &passphrase).await {
+        Ok(new_key) => {
+            let backup_dir = state.config().backup_dir(&session.active_vault.vault_id);
+            rekey_backups(&backup_dir, &current_key, &new_key).await;
+            (Ok(()), new_key)
+        }
+        Err(e) => (Err(e), current_key),
+    };
+
+    // Set before reopening, so a failed reopen can't leave the session on the old key.
+    session.encryption_key = Some(key.clone());
+    state.db.store(Some(Arc::new(
+        establish_connection(&database_path, &key).await?,
+    )));
+    result
+}
@@ -0,0 +1,110 @@
+use crate::app::config::{persist_setting, remove_setting};
+use crate::app::error::AppError;
+use crate::app::state::SharedState;
+use crate::db::attachments::{attachments_dir, exchange_attachment_blobs};
+use crate::db::encryption::{key_file_path, KeyFile};
+use crate::db::sync::{
+    self, count_open_conflicts, list_peers, sync_with_folder, ConflictResolution, SyncConflict,
+    SyncPeer, SyncReport,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::State;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub sync_folder: Option<PathBuf>,
+    pub device_id: String,
+    pub open_conflicts: i64,
+    pub peers: Vec<SyncPeer>,
+}
+
+#[tauri::command]
+pub async fn get_sync_status(state: State<'_, SharedState>) -> Result<SyncStatus, AppError> {
+    let pool = &state.reader()?;
+    Ok(SyncStatus {
+        sync_folder: state.config().sync_folder.clone(),
+        device_id: state.device_id.clone(),
+        open_conflicts: count_open_conflicts(pool).await?,
+        peers: list_peers(pool).await?,
+    })
+}
+
+#[tauri::command]
+pub async fn set_sync_folder(
+    state: State<'_, SharedState>,
+    folder: Option<String>,
+) -> Result<(), AppError> {
+    state.pools()?;
+    match folder.map(PathBuf::from) {
+        Some(folder) => {
+            if !folder.is_absolute() || !folder.is_dir() {
+                return Err(AppError::Validation(format!(
+                    "{} is not an existing folder.",
+                    folder.display()
+                )));
+            }
+            persist_setting(
+                &state.config_dir,
+                "sync_folder",
+                folder.to_string_lossy().to_string(),
+            )?;
+            state.update_config(|config| config.sync_folder = Some(folder.clone()));
+        }
+        None => {
+            remove_setting(&state.config_dir, "sync_folder")?;
+            state.update_config(|config| config.sync_folder = None);
+        }
+    }
+    Ok(())
+}
+
+// Change sets live under a directory named after the vault's key, so several vaults
+// can share one sync folder without reading each other's files. Attachment files go
+// in a sibling directory once the rows that link them have been exchanged.
+#[tauri::command]
+pub async fn sync_now(state: State<'_, SharedState>) -> Result<SyncReport, AppError> {
+    state.writer()?;
+    let sync_folder = state
+        .config()
+        .sync_folder
+        .clone()
+        .ok_or_else(|| AppError::Validation("No sync folder is configured.".to_string()))?;
+    let unlocked = state.unlocked_vault().await?;
+    let key_file = KeyFile::load(&key_file_path(&unlocked.vault.database_path))?
+        .ok_or_else(|| AppError::Internal("Vault key file is missing".to_string()))?;
+
+    let mut report = sync_with_folder(
+        &unlocked.pools.writer,
+        &unlocked.key,
+        &sync_folder.join(&key_file.key_id),
+        &state.request_context(),
+    )
+    .await?;
+    (report.attachments_sent, report.attachments_received) = exchange_attachment_blobs(
+        &unlocked.pools.writer,
+        &attachments_dir(&unlocked.vault.database_path),
+        &sync_folder.join(format!("{}.attachments", key_file.key_id)),
+    )
+    .await?;
+    Ok(report)
+}
+
+#[tauri::command]
+pub async fn list_conflicts(
+    state: State<'_, SharedState>,
+    include_resolved: Option<bool>,
+) -> Result<Vec<SyncConflict>, AppError> {
+    let pool = &state.reader()?;
+    sync::list_conflicts(pool, include_resolved.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn resolve_conflict(
+    state: State<'_, SharedState>,
+    conflict_id: String,
+    resolution: ConflictResolution,
+) -> Result<SyncConflict, AppError> {
+    let pool = &state.writer()?;
+    sync::resolve_conflict(pool, &conflict_id, &resolution, &state.request_context()).await
+}
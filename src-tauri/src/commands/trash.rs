@@ -0,0 +1,40 @@
+use crate::app::error::AppError;
+use crate::app::state::SharedState;
+use crate::db::relations::EntityRef;
+use crate::db::trash::{self, TrashItem};
+use tauri::State;
+
+#[tauri::command]
+pub async fn delete_entity(
+    state: State<'_, SharedState>,
+    entity: EntityRef,
+    reason: Option<String>,
+) -> Result<(), AppError> {
+    let pool = &state.writer()?;
+    trash::delete_entity(pool, &entity, reason.as_deref(), &state.request_context()).await
+}
+
+#[tauri::command]
+pub async fn restore_entity(
+    state: State<'_, SharedState>,
+    entity: EntityRef,
+) -> Result<(), AppError> {
+    let pool = &state.writer()?;
+    trash::restore_entity(pool, &entity, &state.request_context()).await
+}
+
+#[tauri::command]
+pub async fn purge_entity(
+    state: State<'_, SharedState>,
+    entity: EntityRef,
+) -> Result<(), AppError> {
+    let pool = &state.writer()?;
+    trash::purge_entity(pool, &entity, &state.request_context()).await
+}
+
+// Each item carries the time it will be purged automatically, if retention is on.
+#[tauri::command]
+pub async fn list_trash(state: State<'_, SharedState>) -> Result<Vec<TrashItem>, AppError> {
+    let pool = &state.reader()?;
+    trash::list_trash(pool, state.config().trash_retention_days).await
+}
@@ -0,0 +1,112 @@
+use crate::app::error::AppError;
+use crate::app::state::SharedState;
+use crate::db::vault::{self, open_vault, Vault};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::State;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultSummary {
+    pub vault_id: String,
+    pub name: String,
+    pub created_at: i64,
+    pub last_opened_at: Option<i64>,
+    pub is_active: bool,
+    pub is_initialized: bool,
+}
+
+impl VaultSummary {
+    fn from_vault(vault: &Vault, active_vault_id: &str) -> Self {
+        Self {
+            vault_id: vault.vault_id.clone(),
+            name: vault.name.clone(),
+            created_at: vault.created_at,
+            last_opened_at: vault.last_opened_at,
+            is_active: vault.vault_id == active_vault_id,
+            is_initialized: vault::is_initialized(&vault.database_path),
+        }
+    }
+}
+
+// Available while locked so the lock screen can offer a vault picker.
+#[tauri::command]
+pub async fn list_vaults(state: State<'_, SharedState>) -> Result<Vec<VaultSummary>, AppError> {
+    let session = state.session.lock().await;
+    let summaries = session
+        .vaults
+        .vaults
+        .iter()
+        .map(|v| VaultSummary::from_vault(v, &session.active_vault.vault_id))
+        .collect();
+    Ok(summaries)
+}
+
+#[tauri::command]
+pub async fn create_vault(
+    state: State<'_, SharedState>,
+    name: String,
+    passphrase: String,
+) -> Result<VaultSummary, AppError> {
+    let mut session = state.session.lock().await;
+    let cipher_available = state.cipher_available()?;
+
+    let vaults_dir = state
+        .config()
+        .database_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join("vaults");
+    std::fs::create_dir_all(&vaults_dir)?;
+
+    let vault = session.vaults.add(&name, &vaults_dir)?;
+    // Creates the key file and runs the migrations on the empty database.
+    let backup_dir = state.config().backup_dir(&vault.vault_id);
+    let opened = match open_vault(
+        &vault.database_path,
+        &backup_dir,
+        &passphrase,
+        cipher_available,
+    )
+    .await
+    {
+        Ok(opened) => opened,
+        Err(e) => {
+            session
+                .vaults
+                .vaults
+                .retain(|v| v.vault_id != vault.vault_id);
+            return Err(e);
+        }
+    };
+
+    state
+        .activate_vault(&mut session, vault.clone(), opened)
+        .await?;
+    Ok(VaultSummary::from_vault(&vault, &vault.vault_id))
+}
+
+#[tauri::command]
+pub async fn switch_vault(
+    state: State<'_, SharedState>,
+    vault_id: String,
+    passphrase: String,
+) -> Result<(), AppError> {
+    let mut session = state.session.lock().await;
+    let cipher_available = state.cipher_available()?;
+    let vault = session
+        .vaults
+        .find(&vault_id)
+        .cloned()
+        .ok_or_else(|| AppError::not_found("Vault", &vault_id))?;
+
+    // The current vault stays open if the new one fails to unlock.
+    let backup_dir = state.config().backup_dir(&vault.vault_id);
+    let opened = open_vault(
+        &vault.database_path,
+        &backup_dir,
+        &passphrase,
+        cipher_available,
+    )
+    .await?;
+    state.activate_vault(&mut session, vault, opened).await
+}
@@ -0,0 +1,938 @@
+use crate::analytics::cache::clear_analytics_caches;
+use crate::app::config::{validate_day_start_hour, validate_diary_start_year, Config};
+use crate::app::error::AppError;
+use crate::db::slugs::{slug_taken, SlugSpec, GOAL_SLUGS, JOB_SLUGS};
+use crate::domains::dashboard::repository::clear_snapshots;
+use crate::migrations::runner::current_version;
+use crate::utils::ids::numbered_slug;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::query::Query;
+use sqlx::sqlite::{SqliteArguments, SqliteRow};
+use sqlx::{Column, Row, Sqlite, SqliteConnection, SqlitePool, TypeInfo, ValueRef};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+pub const ARCHIVE_FORMAT: &str = "nocturne-archive";
+// 2: the manifest has an entry for the settings.
+pub const ARCHIVE_FORMAT_VERSION: u32 = 2;
+
+// 0012 moved the ID-list columns into the relation tables.
+const RELATIONS_SCHEMA_VERSION: i64 = 12;
+
+// The manifest entry that covers `Archive.settings` rather than a table.
+const SETTINGS_ENTRY: &str = "settings";
+
+// BLOB values are written as `{"$hex": "..."}` since JSON has no byte strings.
+const BLOB_KEY: &str = "$hex";
+
+struct TableSpec {
+    name: &'static str,
+    id_column: &'static str,
+    slugs: Option<&'static SlugSpec>,
+    // (column, referenced table) pairs that have to follow a remapped ID.
+    references: &'static [(&'static str, &'static str)],
+}
+
+// Referenced tables come before the tables that point at them.
+const TABLES: &[TableSpec] = &[
+    TableSpec {
+        name: "profiles",
+        id_column: "profile_id",
+        slugs: None,
+        references: &[],
+    },
+    TableSpec {
+        name: "diary_entries",
+        id_column: "diary_entry_id",
+        slugs: None,
+        references: &[
+            ("primary_page_id", "diary_entries"),
+            ("parent_page_id", "diary_entries"),
+            ("root_page_id", "diary_entries"),
+            ("created_by_profile_id", "profiles"),
+            ("last_modified_by_profile_id", "profiles"),
+        ],
+    },
+    TableSpec {
+        name: "habits",
+        id_column: "habit_id",
+        slugs: None,
+        references: &[
+            ("created_by_profile_id", "profiles"),
+            ("last_modified_by_profile_id", "profiles"),
+        ],
+    },
+    TableSpec {
+        name: "habit_logs",
+        id_column: "log_id",
+        slugs: None,
+        references: &[("habit_id", "habits")],
+    },
+    TableSpec {
+        name: "goals",
+        id_column: "goal_id",
+        slugs: Some(&GOAL_SLUGS),
+        references: &[
+            ("created_by_profile_id", "profiles"),
+            ("last_modified_by_profile_id", "profiles"),
+        ],
+    },
+    TableSpec {
+        name: "job_applications",
+        id_column: "job_application_id",
+        slugs: Some(&JOB_SLUGS),
+        references: &[
+            ("created_by_profile_id", "profiles"),
+            ("last_modified_by_profile_id", "profiles"),
+        ],
+    },
+    TableSpec {
+        name: "goal_slug_aliases",
+        id_column: "alias_id",
+        slugs: None,
+        references: &[("goal_id", "goals")],
+    },
+    TableSpec {
+        name: "job_application_slug_aliases",
+        id_column: "alias_id",
+        slugs: None,
+        references: &[("job_application_id", "job_applications")],
+    },
+    TableSpec {
+        name: "goal_progress_events",
+        id_column: "event_id",
+        slugs: None,
+        references: &[("goal_id", "goals"), ("profile_id", "profiles")],
+    },
+    TableSpec {
+        name: "job_status_events",
+        id_column: "event_id",
+        slugs: None,
+        references: &[
+            ("job_application_id", "job_applications"),
+            ("profile_id", "profiles"),
+        ],
+    },
+    TableSpec {
+        name: "diary_edit_history",
+        id_column: "history_id",
+        slugs: None,
+        references: &[
+            ("diary_entry_id", "diary_entries"),
+            ("profile_id", "profiles"),
+        ],
+    },
+    TableSpec {
+        name: "diary_habits",
+        id_column: "link_id",
+        slugs: None,
+        references: &[("diary_entry_id", "diary_entries"), ("habit_id", "habits")],
+    },
+    TableSpec {
+        name: "diary_goals",
+        id_column: "link_id",
+        slugs: None,
+        references: &[("diary_entry_id", "diary_entries"), ("goal_id", "goals")],
+    },
+    TableSpec {
+        name: "diary_jobs",
+        id_column: "link_id",
+        slugs: None,
+        references: &[
+            ("diary_entry_id", "diary_entries"),
+            ("job_application_id", "job_applications"),
+        ],
+    },
+    TableSpec {
+        name: "habit_goals",
+        id_column: "link_id",
+        slugs: None,
+        references: &[("habit_id", "habits"), ("goal_id", "goals")],
+    },
+    TableSpec {
+        name: "job_goals",
+        id_column: "link_id",
+        slugs: None,
+        references: &[
+            ("job_application_id", "job_applications"),
+            ("goal_id", "goals"),
+        ],
+    },
+    TableSpec {
+        name: "job_habits",
+        id_column: "link_id",
+        slugs: None,
+        references: &[
+            ("job_application_id", "job_applications"),
+            ("habit_id", "habits"),
+        ],
+    },
+    TableSpec {
+        name: "goal_dependencies",
+        id_column: "link_id",
+        slugs: None,
+        references: &[("goal_id", "goals"), ("depends_on_goal_id", "goals")],
+    },
+    TableSpec {
+        name: "habit_dependencies",
+        id_column: "link_id",
+        slugs: None,
+        references: &[("habit_id", "habits"), ("depends_on_habit_id", "habits")],
+    },
+    // Only the metadata: the encrypted files stay in the vault's attachment store.
+    TableSpec {
+        name: "attachments",
+        id_column: "attachment_id",
+        slugs: None,
+        references: &[],
+    },
+    TableSpec {
+        name: "attachment_links",
+        id_column: "link_id",
+        slugs: None,
+        references: &[
+            ("attachment_id", "attachments"),
+            ("diary_entry_id", "diary_entries"),
+            ("goal_id", "goals"),
+            ("job_application_id", "job_applications"),
+        ],
+    },
+];
+
+// The ID-list columns from before 0012 and the relation rows their IDs became:
+// (listing table, list column, relation table, relation type, whether the listing
+// record is the relation's first reference or its second).
+const LEGACY_LISTS: &[(&str, &str, &str, &str, bool)] = &[
+    (
+        "diary_entries",
+        "linked_habit_ids",
+        "diary_habits",
+        "linked",
+        true,
+    ),
+    (
+        "diary_entries",
+        "completed_habit_ids",
+        "diary_habits",
+        "completed",
+        true,
+    ),
+    (
+        "diary_entries",
+        "linked_goal_ids",
+        "diary_goals",
+        "linked",
+        true,
+    ),
+    (
+        "diary_entries",
+        "progressed_goal_ids",
+        "diary_goals",
+        "progressed",
+        true,
+    ),
+    (
+        "diary_entries",
+        "linked_job_ids",
+        "diary_jobs",
+        "linked",
+        true,
+    ),
+    (
+        "habits",
+        "prerequisite_habit_ids",
+        "habit_dependencies",
+        "prerequisite",
+        true,
+    ),
+    (
+        "habits",
+        "dependent_habit_ids",
+        "habit_dependencies",
+        "prerequisite",
+        false,
+    ),
+    (
+        "habits",
+        "blocked_by_habit_ids",
+        "habit_dependencies",
+        "blocker",
+        true,
+    ),
+    (
+        "habits",
+        "unlocks_habit_ids",
+        "habit_dependencies",
+        "prerequisite",
+        false,
+    ),
+    ("habits", "linked_goal_ids", "habit_goals", "linked", true),
+    (
+        "habits",
+        "linked_diary_page_ids",
+        "diary_habits",
+        "linked",
+        false,
+    ),
+    ("habits", "linked_job_ids", "job_habits", "linked", false),
+    (
+        "goals",
+        "prerequisite_goal_ids",
+        "goal_dependencies",
+        "prerequisite",
+        true,
+    ),
+    (
+        "goals",
+        "dependent_goal_ids",
+        "goal_dependencies",
+        "prerequisite",
+        false,
+    ),
+    (
+        "goals",
+        "blocked_by_goal_ids",
+        "goal_dependencies",
+        "blocker",
+        true,
+    ),
+    (
+        "goals",
+        "unlocks_goal_ids",
+        "goal_dependencies",
+        "prerequisite",
+        false,
+    ),
+    ("goals", "linked_habit_ids", "habit_goals", "linked", false),
+    (
+        "goals",
+        "linked_diary_page_ids",
+        "diary_goals",
+        "linked",
+        false,
+    ),
+    ("goals", "linked_job_ids", "job_goals", "linked", false),
+    (
+        "job_applications",
+        "linked_goal_ids",
+        "job_goals",
+        "linked",
+        true,
+    ),
+    (
+        "job_applications",
+        "linked_habit_ids",
+        "job_habits",
+        "linked",
+        true,
+    ),
+    (
+        "job_applications",
+        "linked_diary_entry_ids",
+        "diary_jobs",
+        "linked",
+        false,
+    ),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Archive {
+    pub format: String,
+    pub format_version: u32,
+    pub app_version: String,
+    pub schema_version: i64,
+    pub exported_at: i64,
+    pub manifest: Vec<ManifestEntry>,
+    pub tables: Map<String, Value>,
+    #[serde(default)]
+    pub settings: Option<DashboardSettings>,
+}
+
+// The configured values the dashboard is computed from. Dashboard snapshots are not
+// archived: they are rebuilt from the records, and per-profile preferences already
+// travel with the profiles table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DashboardSettings {
+    pub diary_start_year: i32,
+    pub day_start_hour: u32,
+}
+
+impl From<&Config> for DashboardSettings {
+    fn from(config: &Config) -> Self {
+        Self {
+            diary_start_year: config.diary_start_year,
+            day_start_hour: config.day_start_hour,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub table: String,
+    pub row_count: usize,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMode {
+    Merge,   // keep existing data; colliding IDs get fresh ones
+    Replace, // wipe the archived tables first
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TableImportSummary {
+    pub table: String,
+    pub inserted: usize,
+    pub remapped: usize,
+    pub skipped: usize, // already present, or rejected by a uniqueness constraint
+}
+
+pub async fn export_archive(
+    pool: &SqlitePool,
+    settings: DashboardSettings,
+) -> Result<Archive, AppError> {
+    let mut manifest = Vec::new();
+    let mut tables = Map::new();
+
+    for spec in TABLES {
+        let rows = sqlx::query(&format!(
+            "SELECT * FROM {} ORDER BY rowid",
+            quote_ident(spec.name)
+        ))
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(row_to_json)
+        .collect::<Result<Vec<Value>, AppError>>()?;
+
+        manifest.push(ManifestEntry {
+            table: spec.name.to_string(),
+            row_count: rows.len(),
+            sha256: checksum(&rows)?,
+        });
+        tables.insert(spec.name.to_string(), Value::Array(rows));
+    }
+
+    let rows = settings_rows(&settings)?;
+    manifest.push(ManifestEntry {
+        table: SETTINGS_ENTRY.to_string(),
+        row_count: rows.len(),
+        sha256: checksum(&rows)?,
+    });
+
+    Ok(Archive {
+        format: ARCHIVE_FORMAT.to_string(),
+        format_version: ARCHIVE_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version: current_version(pool).await?,
+        exported_at: Utc::now().timestamp(),
+        manifest,
+        tables,
+        settings: Some(settings),
+    })
+}
+
+// Checks the envelope, the manifest checksums and that every column still exists,
+// before anything is written.
+pub async fn validate_archive(pool: &SqlitePool, archive: &Archive) -> Result<(), AppError> {
+    checked_tables(pool, archive).await.map(|_| ())
+}
+
+// The archive's tables as the current schema has them; see `upgrade_tables`.
+async fn checked_tables(
+    pool: &SqlitePool,
+    archive: &Archive,
+) -> Result<Map<String, Value>, AppError> {
+    if archive.format != ARCHIVE_FORMAT {
+        return Err(AppError::Validation(
+            "File is not a Nocturne archive.".to_string(),
+        ));
+    }
+    if archive.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(AppError::Validation(format!(
+            "Archive format {} is newer than this app supports.",
+            archive.format_version
+        )));
+    }
+    if archive.schema_version > current_version(pool).await? {
+        return Err(AppError::Validation(
+            "Archive was exported from a newer version of the app.".to_string(),
+        ));
+    }
+
+    if let Some(settings) = &archive.settings {
+        let entry = archive
+            .manifest
+            .iter()
+            .find(|e| e.table == SETTINGS_ENTRY)
+            .ok_or_else(|| {
+                AppError::Validation("Settings are missing from the archive manifest.".to_string())
+            })?;
+        let rows = settings_rows(settings)?;
+        if rows.len() != entry.row_count || checksum(&rows)? != entry.sha256 {
+            return Err(AppError::Validation(
+                "Checksum mismatch for settings; the archive is damaged.".to_string(),
+            ));
+        }
+        validate_diary_start_year(settings.diary_start_year)?;
+        validate_day_start_hour(settings.day_start_hour)?;
+    }
+
+    for entry in &archive.manifest {
+        if entry.table == SETTINGS_ENTRY {
+            if archive.settings.is_none() {
+                return Err(AppError::Validation(
+                    "Archive manifest lists settings the archive doesn't contain.".to_string(),
+                ));
+            }
+            continue;
+        }
+        let spec = table_spec(&entry.table).ok_or_else(|| {
+            AppError::Validation(format!("Archive contains unknown table {}.", entry.table))
+        })?;
+        let rows = archive_rows(&archive.tables, spec.name)?;
+        if rows.len() != entry.row_count || checksum(rows)? != entry.sha256 {
+            return Err(AppError::Validation(format!(
+                "Checksum mismatch for {}; the archive is damaged.",
+                entry.table
+            )));
+        }
+    }
+
+    for (table, _) in &archive.tables {
+        if !archive.manifest.iter().any(|e| &e.table == table) {
+            return Err(AppError::Validation(format!(
+                "Table {} is missing from the archive manifest.",
+                table
+            )));
+        }
+    }
+
+    let tables = upgrade_tables(pool, archive).await?;
+    for table in tables.keys() {
+        let spec = table_spec(table).ok_or_else(|| {
+            AppError::Validation(format!("Archive contains unknown table {}.", table))
+        })?;
+        let columns = table_columns(pool, spec.name).await?;
+        for row in archive_rows(&tables, spec.name)? {
+            let row = row
+                .as_object()
+                .ok_or_else(|| AppError::Validation(format!("Malformed row in {}.", spec.name)))?;
+            if !row.get(spec.id_column).is_some_and(Value::is_string) {
+                return Err(AppError::Validation(format!(
+                    "Row in {} is missing {}.",
+                    spec.name, spec.id_column
+                )));
+            }
+            if let Some(column) = row.keys().find(|c| !columns.contains(*c)) {
+                return Err(AppError::Validation(format!(
+                    "Unknown column {}.{} in archive.",
+                    spec.name, column
+                )));
+            }
+        }
+    }
+    Ok(tables)
+}
+
+// An archive from an older schema is brought up to the current one the way the
+// migrations since would have: ID lists become relation rows, and then every column
+// the current schema no longer has is dropped.
+async fn upgrade_tables(
+    pool: &SqlitePool,
+    archive: &Archive,
+) -> Result<Map<String, Value>, AppError> {
+    let mut tables = archive.tables.clone();
+    if archive.schema_version >= current_version(pool).await? {
+        return Ok(tables);
+    }
+    if archive.schema_version < RELATIONS_SCHEMA_VERSION {
+        let relations = legacy_relations(&tables, archive.exported_at);
+        tables.extend(relations);
+    }
+
+    for (table, rows) in tables.iter_mut() {
+        let Some(spec) = table_spec(table) else {
+            continue;
+        };
+        let columns = table_columns(pool, spec.name).await?;
+        for row in rows.as_array_mut().into_iter().flatten() {
+            if let Some(row) = row.as_object_mut() {
+                row.retain(|column, _| columns.contains(column));
+            }
+        }
+    }
+    Ok(tables)
+}
+
+// Like 0012, keeps only links whose both ends are in the archive, and names each one
+// after its ends and type.
+fn legacy_relations(tables: &Map<String, Value>, now: i64) -> Map<String, Value> {
+    let ids = |table: &str| -> HashSet<&str> {
+        let Some(spec) = table_spec(table) else {
+            return HashSet::new();
+        };
+        archive_rows(tables, table)
+            .into_iter()
+            .flatten()
+            .filter_map(|row| row.get(spec.id_column)?.as_str())
+            .collect()
+    };
+    let mut relations: Map<String, Value> = LEGACY_LISTS
+        .iter()
+        .map(|list| (list.2.to_string(), Value::Array(Vec::new())))
+        .collect();
+    let mut seen = HashSet::new();
+
+    for &(table, column, relation_table, relation_type, lists_first) in LEGACY_LISTS {
+        let (Some(listing), Some(relation)) = (table_spec(table), table_spec(relation_table))
+        else {
+            continue;
+        };
+        let [(first_column, first_table), (second_column, second_table)] = relation.references
+        else {
+            continue;
+        };
+        let (first_ids, second_ids) = (ids(first_table), ids(second_table));
+
+        for row in archive_rows(tables, table).into_iter().flatten() {
+            let Some(record_id) = row.get(listing.id_column).and_then(Value::as_str) else {
+                continue;
+            };
+            for item_id in id_list(row.get(column)) {
+                let (first, second) = if lists_first {
+                    (record_id, item_id.as_str())
+                } else {
+                    (item_id.as_str(), record_id)
+                };
+                if first == second || !first_ids.contains(first) || !second_ids.contains(second) {
+                    continue;
+                }
+                let link_id = format!("{}:{}:{}", first, second, relation_type);
+                if !seen.insert((relation_table, link_id.clone())) {
+                    continue;
+                }
+                if let Some(Value::Array(rows)) = relations.get_mut(relation_table) {
+                    rows.push(json!({
+                        "link_id": link_id,
+                        *first_column: first,
+                        *second_column: second,
+                        "relation_type": relation_type,
+                        "created_at": now,
+                        "updated_at": now,
+                    }));
+                }
+            }
+        }
+    }
+    relations
+}
+
+// Lists were written either as JSON arrays or comma-separated.
+fn id_list(value: Option<&Value>) -> Vec<String> {
+    let Some(Value::String(list)) = value else {
+        return Vec::new();
+    };
+    let items: Vec<String> = match serde_json::from_str::<Value>(list) {
+        Ok(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => list.split(',').map(str::to_string).collect(),
+    };
+    items
+        .into_iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+// Runs in one transaction on the writer. Foreign keys are checked at commit, so
+// self-referencing diary pages can be inserted in any order.
+pub async fn import_archive(
+    pool: &SqlitePool,
+    archive: &Archive,
+    mode: ImportMode,
+) -> Result<Vec<TableImportSummary>, AppError> {
+    let tables = checked_tables(pool, archive).await?;
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut *tx)
+        .await?;
+
+    if mode == ImportMode::Replace {
+        for spec in TABLES.iter().rev() {
+            if tables.contains_key(spec.name) {
+                sqlx::query(&format!("DELETE FROM {}", quote_ident(spec.name)))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+    }
+
+    let mut id_maps: HashMap<&str, HashMap<String, String>> = HashMap::new();
+    let mut summaries = Vec::new();
+
+    for spec in TABLES {
+        if !tables.contains_key(spec.name) {
+            continue;
+        }
+        let rows = archive_rows(&tables, spec.name)?;
+        let mut summary = TableImportSummary {
+            table: spec.name.to_string(),
+            ..TableImportSummary::default()
+        };
+
+        // First decide every row's ID, so references within the table can follow.
+        let mut planned = Vec::with_capacity(rows.len());
+        let mut id_map = HashMap::new();
+        for row in rows {
+            let row = row.as_object().cloned().unwrap_or_default();
+            let id = row[spec.id_column].as_str().unwrap_or_default().to_string();
+            let existing = fetch_row(&mut tx, spec, &id).await?;
+            let action = match existing {
+                None => RowAction::Insert(id.clone()),
+                Some(existing) if same_values(&row, &existing) => RowAction::Skip,
+                // An attachment's ID is the hash of its file, so the same ID is the same file.
+                Some(_) if spec.name == "attachments" => RowAction::Skip,
+                Some(_) => RowAction::Insert(Uuid::new_v4().to_string()),
+            };
+            if let RowAction::Insert(new_id) = &action {
+                if new_id != &id {
+                    id_map.insert(id, new_id.clone());
+                }
+            }
+            planned.push((row, action));
+        }
+        id_maps.insert(spec.name, id_map);
+
+        for (mut row, action) in planned {
+            let RowAction::Insert(new_id) = action else {
+                summary.skipped += 1;
+                continue;
+            };
+            if new_id != row[spec.id_column] {
+                summary.remapped += 1;
+            }
+            row.insert(spec.id_column.to_string(), Value::String(new_id));
+
+            for (column, table) in spec.references {
+                let remapped = row
+                    .get(*column)
+                    .and_then(Value::as_str)
+                    .and_then(|old| id_maps.get(table).and_then(|m| m.get(old)))
+                    .cloned();
+                if let Some(new_ref) = remapped {
+                    row.insert(column.to_string(), Value::String(new_ref));
+                }
+            }
+            if let Some(slugs) = spec.slugs {
+                dedupe_slug(&mut tx, slugs, &mut row).await?;
+            }
+
+            if insert_row(&mut tx, spec, &row).await? {
+                summary.inserted += 1;
+            } else {
+                summary.skipped += 1;
+            }
+        }
+        summaries.push(summary);
+    }
+
+    tx.commit().await?;
+    clear_analytics_caches(pool).await?;
+    clear_snapshots(pool).await?;
+    Ok(summaries)
+}
+
+enum RowAction {
+    Insert(String),
+    Skip,
+}
+
+async fn fetch_row(
+    conn: &mut SqliteConnection,
+    spec: &TableSpec,
+    id: &str,
+) -> Result<Option<Map<String, Value>>, AppError> {
+    let row = sqlx::query(&format!(
+        "SELECT * FROM {} WHERE {} = ?",
+        quote_ident(spec.name),
+        quote_ident(spec.id_column)
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    match row {
+        Some(row) => match row_to_json(&row)? {
+            Value::Object(object) => Ok(Some(object)),
+            _ => Ok(None),
+        },
+        None => Ok(None),
+    }
+}
+
+// A row that is already present with identical values is the same record,
+// e.g. when an archive is imported back into the vault it came from.
+fn same_values(row: &Map<String, Value>, existing: &Map<String, Value>) -> bool {
+    row.iter()
+        .all(|(column, value)| existing.get(column).unwrap_or(&Value::Null) == value)
+}
+
+async fn dedupe_slug(
+    conn: &mut SqliteConnection,
+    slugs: &SlugSpec,
+    row: &mut Map<String, Value>,
+) -> Result<(), AppError> {
+    let Some(slug) = row
+        .get(slugs.slug_column)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+    else {
+        return Ok(());
+    };
+    let owner_id = row
+        .get(slugs.id_column)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    let mut n = 1;
+    let mut candidate = slug.clone();
+    while slug_taken(conn, slugs, &candidate, &owner_id).await? {
+        n += 1;
+        candidate = numbered_slug(&slug, n);
+    }
+    row.insert(slugs.slug_column.to_string(), Value::String(candidate));
+    Ok(())
+}
+
+// Returns false when a uniqueness constraint (e.g. one log per habit and day) kept the row out.
+async fn insert_row(
+    conn: &mut SqliteConnection,
+    spec: &TableSpec,
+    row: &Map<String, Value>,
+) -> Result<bool, AppError> {
+    let columns: Vec<&String> = row.keys().collect();
+    let sql = format!(
+        "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT DO NOTHING",
+        quote_ident(spec.name),
+        columns
+            .iter()
+            .map(|c| quote_ident(c))
+            .collect::<Vec<_>>()
+            .join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+
+    let mut query = sqlx::query::<Sqlite>(&sql);
+    for column in &columns {
+        query = bind_value(query, &row[*column], spec.name, column)?;
+    }
+
+    Ok(query.execute(&mut *conn).await?.rows_affected() > 0)
+}
+
+// Binds a value in the JSON form produced by `row_to_json`.
+pub fn bind_value<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    value: &Value,
+    table: &str,
+    column: &str,
+) -> Result<Query<'q, Sqlite, SqliteArguments<'q>>, AppError> {
+    let query = match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(value) => query.bind(i64::from(*value)),
+        Value::Number(value) => match value.as_i64() {
+            Some(value) => query.bind(value),
+            None => query.bind(value.as_f64()),
+        },
+        Value::String(value) => query.bind(value.clone()),
+        Value::Object(object) => match object.get(BLOB_KEY).and_then(Value::as_str) {
+            Some(encoded) => query.bind(hex::decode(encoded).map_err(|_| {
+                AppError::Validation(format!("Invalid binary value in {}.{}", table, column))
+            })?),
+            None => {
+                return Err(AppError::Validation(format!(
+                    "Unsupported value in {}.{}",
+                    table, column
+                )))
+            }
+        },
+        Value::Array(_) => {
+            return Err(AppError::Validation(format!(
+                "Unsupported value in {}.{}",
+                table, column
+            )))
+        }
+    };
+    Ok(query)
+}
+
+pub fn row_to_json(row: &SqliteRow) -> Result<Value, AppError> {
+    let mut object = Map::new();
+    for column in row.columns() {
+        let index = column.ordinal();
+        let raw = row.try_get_raw(index)?;
+        // SQLite is dynamically typed, so go by the stored value rather than the declared type.
+        let value = if raw.is_null() {
+            Value::Null
+        } else {
+            match raw.type_info().name() {
+                "INTEGER" => Value::from(row.try_get_unchecked::<i64, _>(index)?),
+                "REAL" => Value::from(row.try_get_unchecked::<f64, _>(index)?),
+                "BLOB" => {
+                    json!({ BLOB_KEY: hex::encode(row.try_get_unchecked::<Vec<u8>, _>(index)?) })
+                }
+                _ => Value::from(row.try_get_unchecked::<String, _>(index)?),
+            }
+        };
+        object.insert(column.name().to_string(), value);
+    }
+    Ok(Value::Object(object))
+}
+
+fn archive_rows<'a>(
+    tables: &'a Map<String, Value>,
+    table: &str,
+) -> Result<&'a Vec<Value>, AppError> {
+    tables
+        .get(table)
+        .and_then(Value::as_array)
+        .ok_or_else(|| AppError::Validation(format!("Archive is missing table {}.", table)))
+}
+
+fn settings_rows(settings: &DashboardSettings) -> Result<Vec<Value>, AppError> {
+    let value = serde_json::to_value(settings)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize settings: {}", e)))?;
+    Ok(vec![value])
+}
+
+fn checksum(rows: &[Value]) -> Result<String, AppError> {
+    let bytes = serde_json::to_vec(rows)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize rows: {}", e)))?;
+    Ok(hex::encode(Sha256::digest(&bytes)))
+}
+
+async fn table_columns(pool: &SqlitePool, table: &str) -> Result<HashSet<String>, AppError> {
+    let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+        .bind(table)
+        .fetch_all(pool)
+        .await?;
+    Ok(columns.into_iter().collect())
+}
+
+fn table_spec(name: &str) -> Option<&'static TableSpec> {
+    TABLES.iter().find(|spec| spec.name == name)
+}
+
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
@@ -0,0 +1,508 @@
+use crate::app::context::RequestContext;
+use crate::app::error::{AppError, FieldError};
+use crate::db::encryption::{open_sealed, seal_bytes, DataKey};
+use crate::db::relations::{ensure_exists, EntityKind, EntityRef};
+use crate::utils::hashing::hash_bytes;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BLOB_MAGIC: &[u8; 8] = b"NOCTATT1";
+// A blob resealed under the new key during a rekey, waiting to replace the original.
+const REKEYED_EXTENSION: &str = "rekeyed";
+// Files are read, hashed and sealed in memory.
+const MAX_ATTACHMENT_BYTES: u64 = 64 * 1024 * 1024;
+
+// The first one is used when no role is given. The others are for jobs only, which
+// have at most one resume and one cover letter at a time.
+const ROLES: &[&str] = &["attachment", "resume", "cover_letter"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachmentLink {
+    pub link_id: String,
+    pub attachment_id: String,
+    pub entity: EntityRef,
+    pub role: String,
+    pub file_name: String,
+    pub mime_type: Option<String>,
+    pub byte_size: i64,
+    pub created_at: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct LinkRow {
+    link_id: String,
+    attachment_id: String,
+    diary_entry_id: Option<String>,
+    goal_id: Option<String>,
+    job_application_id: Option<String>,
+    role: String,
+    file_name: String,
+    mime_type: Option<String>,
+    byte_size: i64,
+    created_at: i64,
+}
+
+impl LinkRow {
+    fn into_link(self) -> AttachmentLink {
+        let entity = match (self.diary_entry_id, self.goal_id, self.job_application_id) {
+            (Some(id), _, _) => EntityRef {
+                kind: EntityKind::Diary,
+                id,
+            },
+            (_, Some(id), _) => EntityRef {
+                kind: EntityKind::Goal,
+                id,
+            },
+            (_, _, id) => EntityRef {
+                kind: EntityKind::Job,
+                id: id.unwrap_or_default(),
+            },
+        };
+        AttachmentLink {
+            link_id: self.link_id,
+            attachment_id: self.attachment_id,
+            entity,
+            role: self.role,
+            file_name: self.file_name,
+            mime_type: self.mime_type,
+            byte_size: self.byte_size,
+            created_at: self.created_at,
+        }
+    }
+}
+
+const LINK_SELECT: &str = "SELECT link.link_id, link.attachment_id, link.diary_entry_id,
+        link.goal_id, link.job_application_id, link.role, link.file_name,
+        attachment.mime_type, attachment.byte_size, link.created_at
+    FROM attachment_links link
+    JOIN attachments attachment ON attachment.attachment_id = link.attachment_id";
+
+// `<vault>.attachments/` beside `<vault>.db`. Blobs are named after their hash and
+// fanned out over subdirectories by its first two characters.
+pub fn attachments_dir(database_path: &Path) -> PathBuf {
+    database_path.with_extension("attachments")
+}
+
+// Copies the file at `source` into the store and attaches it to `entity`. Adding a file
+// that is already stored only adds the link. The blob is written before the rows
+// commit; if they don't, the next garbage collection removes it.
+pub async fn add_attachment(
+    pool: &SqlitePool,
+    dir: &Path,
+    key: &DataKey,
+    entity: &EntityRef,
+    source: &Path,
+    role: Option<&str>,
+    ctx: &RequestContext,
+) -> Result<AttachmentLink, AppError> {
+    let role = checked_role(entity.kind, role)?;
+    let file_name = source
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|_| source.is_file())
+        .ok_or_else(|| AppError::Validation(format!("{} is not a file.", source.display())))?
+        .to_string();
+    if fs::metadata(source)?.len() > MAX_ATTACHMENT_BYTES {
+        return Err(AppError::Validation(format!(
+            "Attachments can be at most {} MB.",
+            MAX_ATTACHMENT_BYTES / 1024 / 1024
+        )));
+    }
+    let bytes = fs::read(source)?;
+    let attachment_id = hash_bytes(&bytes);
+    let column = entity.kind.id_column();
+    let link_id = format!("{}:{}:{}", attachment_id, entity.id, role);
+    let now = ctx.timestamp();
+
+    let mut tx = pool.begin().await?;
+    ensure_exists(&mut tx, entity).await?;
+    write_blob(dir, key, &attachment_id, &bytes)?;
+
+    sqlx::query(
+        "INSERT INTO attachments (attachment_id, byte_size, mime_type, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(attachment_id) DO NOTHING",
+    )
+    .bind(&attachment_id)
+    .bind(bytes.len() as i64)
+    .bind(mime_type(&file_name))
+    .bind(now)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    if role != ROLES[0] {
+        sqlx::query(&format!(
+            "UPDATE attachment_links SET unlinked_at = ?1, updated_at = ?1
+             WHERE {} = ?2 AND role = ?3 AND link_id != ?4 AND unlinked_at IS NULL",
+            column
+        ))
+        .bind(now)
+        .bind(&entity.id)
+        .bind(role)
+        .bind(&link_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query(&format!(
+        "INSERT INTO attachment_links
+            (link_id, attachment_id, {}, role, file_name, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+         ON CONFLICT(link_id) DO UPDATE SET
+            file_name = excluded.file_name, unlinked_at = NULL,
+            updated_at = excluded.updated_at
+         WHERE unlinked_at IS NOT NULL OR file_name != excluded.file_name",
+        column
+    ))
+    .bind(&link_id)
+    .bind(&attachment_id)
+    .bind(&entity.id)
+    .bind(role)
+    .bind(&file_name)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    let row: LinkRow = sqlx::query_as(&format!("{} WHERE link.link_id = ?", LINK_SELECT))
+        .bind(&link_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(row.into_link())
+}
+
+// The decrypted contents, checked against the hash they are stored under.
+pub async fn read_attachment(
+    pool: &SqlitePool,
+    dir: &Path,
+    key: &DataKey,
+    attachment_id: &str,
+) -> Result<Vec<u8>, AppError> {
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM attachments WHERE attachment_id = ?)")
+            .bind(attachment_id)
+            .fetch_one(pool)
+            .await?;
+    if !exists || !is_attachment_id(attachment_id) {
+        return Err(AppError::not_found("Attachment", attachment_id));
+    }
+
+    let path = blob_path(dir, attachment_id);
+    if !path.exists() {
+        return Err(AppError::Validation(
+            "That file hasn't reached this device yet; it comes with the next sync.".to_string(),
+        ));
+    }
+    let contents = fs::read(&path)?;
+    let bytes = contents
+        .strip_prefix(BLOB_MAGIC.as_slice())
+        .map(|sealed| open_sealed(key, sealed, attachment_id.as_bytes()))
+        .transpose()?
+        .filter(|bytes| hash_bytes(bytes) == attachment_id)
+        .ok_or_else(|| AppError::Internal(format!("Attachment {} is corrupt", attachment_id)))?;
+    Ok(bytes)
+}
+
+// Seals a copy of every blob under `new_key` beside the original, ahead of a rekey.
+// Nothing is replaced until `finish_attachment_rekey` runs with the key the database
+// ended up on.
+pub fn stage_attachment_rekey(
+    dir: &Path,
+    current_key: &DataKey,
+    new_key: &DataKey,
+) -> Result<usize, AppError> {
+    let mut staged = 0;
+    for path in stored_files(dir)? {
+        let Some(attachment_id) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .filter(|name| is_attachment_id(name))
+        else {
+            continue;
+        };
+        let Some(bytes) = open_blob(&fs::read(&path)?, current_key, attachment_id) else {
+            log::warn!(
+                "Attachment {} is unreadable and was not rekeyed",
+                attachment_id
+            );
+            continue;
+        };
+        let mut contents = BLOB_MAGIC.to_vec();
+        contents.extend(seal_bytes(new_key, &bytes, attachment_id.as_bytes())?);
+        fs::write(path.with_extension(REKEYED_EXTENSION), contents)?;
+        staged += 1;
+    }
+    Ok(staged)
+}
+
+// Moves the staged blobs that open with `key` into place and deletes the others. Also
+// run on unlock, which completes a rekey that was interrupted after the database
+// switched keys.
+pub fn finish_attachment_rekey(dir: &Path, key: &DataKey) -> Result<usize, AppError> {
+    let mut promoted = 0;
+    for staged in stored_files(dir)? {
+        if staged.extension().and_then(|e| e.to_str()) != Some(REKEYED_EXTENSION) {
+            continue;
+        }
+        let blob = staged.with_extension("");
+        let opens = blob
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|attachment_id| {
+                fs::read(&staged)
+                    .ok()
+                    .and_then(|contents| open_blob(&contents, key, attachment_id))
+                    .is_some()
+            });
+        if opens {
+            fs::rename(&staged, &blob)?;
+            promoted += 1;
+        } else {
+            fs::remove_file(&staged)?;
+        }
+    }
+    Ok(promoted)
+}
+
+pub async fn list_attachments(
+    pool: &SqlitePool,
+    entity: &EntityRef,
+) -> Result<Vec<AttachmentLink>, AppError> {
+    checked_role(entity.kind, None)?;
+    ensure_exists(&mut *pool.acquire().await?, entity).await?;
+
+    let rows: Vec<LinkRow> = sqlx::query_as(&format!(
+        "{} WHERE link.{} = ? AND link.unlinked_at IS NULL ORDER BY link.created_at",
+        LINK_SELECT,
+        entity.kind.id_column()
+    ))
+    .bind(&entity.id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(LinkRow::into_link).collect())
+}
+
+// Detaches the file from `entity` in the given role, or in every role when none is
+// given. The file itself stays until garbage collection finds nothing linking to it.
+pub async fn unlink_attachment(
+    pool: &SqlitePool,
+    entity: &EntityRef,
+    attachment_id: &str,
+    role: Option<&str>,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    let checked = checked_role(entity.kind, role)?;
+    let role = role.map(|_| checked);
+
+    let result = sqlx::query(&format!(
+        "UPDATE attachment_links SET unlinked_at = ?1, updated_at = ?1
+         WHERE {} = ?2 AND attachment_id = ?3 AND (?4 IS NULL OR role = ?4)
+           AND unlinked_at IS NULL",
+        entity.kind.id_column()
+    ))
+    .bind(ctx.timestamp())
+    .bind(&entity.id)
+    .bind(attachment_id)
+    .bind(role)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found(
+            "Attachment link",
+            format!("{}:{}", attachment_id, entity.id),
+        ));
+    }
+    Ok(())
+}
+
+// Deletes the blobs no live link needs, along with files left half-written. Links from
+// records in the trash still count, so a restore gets its files back. The rows stay:
+// another device may link the same file later, and it then arrives with that sync.
+pub async fn collect_attachment_garbage(pool: &SqlitePool, dir: &Path) -> Result<usize, AppError> {
+    if !dir.exists() {
+        return Ok(0);
+    }
+    let live = live_attachment_ids(pool).await?;
+
+    let mut removed = 0;
+    for shard in fs::read_dir(dir)? {
+        let shard = shard?.path();
+        if !shard.is_dir() {
+            continue;
+        }
+        for blob in fs::read_dir(&shard)? {
+            let blob = blob?.path();
+            let needed = blob
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| live.contains(name));
+            if !needed {
+                fs::remove_file(&blob)?;
+                removed += 1;
+            }
+        }
+    }
+
+    if removed > 0 {
+        log::info!("Removed {} unreferenced attachment files", removed);
+    }
+    Ok(removed)
+}
+
+// Copies blobs between the store and `shared_dir` in the sync folder, in whichever
+// direction one is missing. They are sealed already, so they travel as they are.
+// Returns how many went out and how many came in.
+pub async fn exchange_attachment_blobs(
+    pool: &SqlitePool,
+    dir: &Path,
+    shared_dir: &Path,
+) -> Result<(usize, usize), AppError> {
+    let (mut sent, mut received) = (0, 0);
+    for attachment_id in live_attachment_ids(pool).await? {
+        let local = blob_path(dir, &attachment_id);
+        let shared = blob_path(shared_dir, &attachment_id);
+        match (local.exists(), shared.exists()) {
+            (true, false) => {
+                copy_blob(&local, &shared)?;
+                sent += 1;
+            }
+            (false, true) => {
+                copy_blob(&shared, &local)?;
+                received += 1;
+            }
+            _ => {}
+        }
+    }
+    Ok((sent, received))
+}
+
+async fn live_attachment_ids(pool: &SqlitePool) -> Result<HashSet<String>, AppError> {
+    let ids: Vec<String> = sqlx::query_scalar(
+        "SELECT DISTINCT attachment_id FROM attachment_links WHERE unlinked_at IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ids.into_iter().filter(|id| is_attachment_id(id)).collect())
+}
+
+fn checked_role(kind: EntityKind, role: Option<&str>) -> Result<&'static str, AppError> {
+    let allowed = match kind {
+        EntityKind::Job => ROLES,
+        EntityKind::Diary | EntityKind::Goal => &ROLES[..1],
+        EntityKind::Habit => {
+            return Err(AppError::Validation(
+                "Files can't be attached to a habit.".to_string(),
+            ))
+        }
+    };
+    match role {
+        None => Ok(ROLES[0]),
+        Some(role) => allowed
+            .iter()
+            .find(|allowed| **allowed == role)
+            .copied()
+            .ok_or_else(|| {
+                AppError::InvalidFields(vec![FieldError {
+                    field: "role".to_string(),
+                    message: format!("Expected one of: {}.", allowed.join(", ")),
+                }])
+            }),
+    }
+}
+
+// IDs come in from the frontend and name files, so only a bare hash is accepted.
+fn is_attachment_id(attachment_id: &str) -> bool {
+    attachment_id.len() == 64
+        && attachment_id
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+fn blob_path(dir: &Path, attachment_id: &str) -> PathBuf {
+    dir.join(&attachment_id[..2]).join(attachment_id)
+}
+
+// Every file in the store's subdirectories, whatever its name.
+fn stored_files(dir: &Path) -> Result<Vec<PathBuf>, AppError> {
+    let mut files = Vec::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+    for shard in fs::read_dir(dir)? {
+        let shard = shard?.path();
+        if shard.is_dir() {
+            for file in fs::read_dir(&shard)? {
+                files.push(file?.path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn open_blob(contents: &[u8], key: &DataKey, attachment_id: &str) -> Option<Vec<u8>> {
+    let sealed = contents.strip_prefix(BLOB_MAGIC.as_slice())?;
+    open_sealed(key, sealed, attachment_id.as_bytes()).ok()
+}
+
+// The hash is the associated data, so a blob can't be passed off under another name.
+fn write_blob(
+    dir: &Path,
+    key: &DataKey,
+    attachment_id: &str,
+    bytes: &[u8],
+) -> Result<(), AppError> {
+    let path = blob_path(dir, attachment_id);
+    if path.exists() {
+        return Ok(());
+    }
+    let mut contents = BLOB_MAGIC.to_vec();
+    contents.extend(seal_bytes(key, bytes, attachment_id.as_bytes())?);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let partial_path = path.with_extension("partial");
+    fs::write(&partial_path, contents)?;
+    fs::rename(&partial_path, &path)?;
+    Ok(())
+}
+
+// Renamed into place, so neither side ever sees a half-copied blob under its real name.
+fn copy_blob(from: &Path, to: &Path) -> Result<(), AppError> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let partial_path = to.with_extension("partial");
+    fs::copy(from, &partial_path)?;
+    fs::rename(&partial_path, to)?;
+    Ok(())
+}
+
+fn mime_type(file_name: &str) -> Option<&'static str> {
+    let extension = Path::new(file_name)
+        .extension()?
+        .to_str()?
+        .to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "pdf" => "application/pdf",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "heic" => "image/heic",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        _ => return None,
+    })
+}
@@ -0,0 +1,298 @@
+use crate::app::error::AppError;
+use crate::db::cipher::{remove_database_files, sibling_path};
+use crate::db::connection::{establish_single_connection, rekey_database};
+use crate::db::encryption::{open_sealed, seal_bytes, DataKey};
+use crate::migrations::runner::run_migrations;
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BACKUP_MAGIC: &[u8; 8] = b"NOCTBAK1";
+const BACKUP_EXTENSION: &str = "nbak";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupReason {
+    Scheduled,
+    Manual,
+    PreRestore,
+    PreImport,
+    PreMigration,
+    PreRepair,
+}
+
+impl BackupReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BackupReason::Scheduled => "scheduled",
+            BackupReason::Manual => "manual",
+            BackupReason::PreRestore => "pre_restore",
+            BackupReason::PreImport => "pre_import",
+            BackupReason::PreMigration => "pre_migration",
+            BackupReason::PreRepair => "pre_repair",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "scheduled" => Some(BackupReason::Scheduled),
+            "manual" => Some(BackupReason::Manual),
+            "pre_restore" => Some(BackupReason::PreRestore),
+            "pre_import" => Some(BackupReason::PreImport),
+            "pre_migration" => Some(BackupReason::PreMigration),
+            "pre_repair" => Some(BackupReason::PreRepair),
+            _ => None,
+        }
+    }
+}
+
+// Backups are identified by their file stem, e.g. `20261018T093000123Z-manual`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub backup_id: String,
+    pub reason: BackupReason,
+    pub created_at: i64,
+    pub size_bytes: u64,
+}
+
+impl BackupInfo {
+    fn from_path(path: &Path) -> Option<Self> {
+        if path.extension()? != BACKUP_EXTENSION {
+            return None;
+        }
+        let backup_id = path.file_stem()?.to_str()?.to_string();
+        let (timestamp, reason) = backup_id.split_once('-')?;
+        let created_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+            .ok()?
+            .and_utc()
+            .timestamp();
+
+        Some(BackupInfo {
+            reason: BackupReason::parse(reason)?,
+            created_at,
+            size_bytes: fs::metadata(path).ok()?.len(),
+            backup_id,
+        })
+    }
+}
+
+// `VACUUM INTO` writes a consistent copy without blocking writers, and under SQLCipher
+// the copy keeps the database key. The file is then sealed with the data key as well,
+// so backups stay encrypted even for a plaintext opt-in vault.
+pub async fn create_backup(
+    pool: &SqlitePool,
+    backup_dir: &Path,
+    key: &DataKey,
+    reason: BackupReason,
+) -> Result<BackupInfo, AppError> {
+    fs::create_dir_all(backup_dir)?;
+    let backup_id = format!(
+        "{}-{}",
+        Utc::now().format(TIMESTAMP_FORMAT),
+        reason.as_str()
+    );
+    let path = backup_path(backup_dir, &backup_id);
+    let snapshot_path = sibling_path(&path, ".snapshot");
+    remove_database_files(&snapshot_path)?;
+
+    sqlx::query("VACUUM INTO ?")
+        .bind(snapshot_path.to_string_lossy().to_string())
+        .execute(pool)
+        .await?;
+    let result = seal_snapshot(&snapshot_path, &path, key);
+    remove_database_files(&snapshot_path)?;
+    result?;
+
+    log::info!("Created {} backup {}", reason.as_str(), backup_id);
+    BackupInfo::from_path(&path)
+        .ok_or_else(|| AppError::Internal(format!("Backup {} was not written", backup_id)))
+}
+
+// Newest first.
+pub fn list_backups(backup_dir: &Path) -> Result<Vec<BackupInfo>, AppError> {
+    if !backup_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups: Vec<BackupInfo> = fs::read_dir(backup_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| BackupInfo::from_path(&entry.path()))
+        .collect();
+    backups.sort_by(|a, b| b.backup_id.cmp(&a.backup_id));
+    Ok(backups)
+}
+
+// Keeps the newest backup of each of the last `keep_daily` days and of each of the
+// last `keep_weekly` ISO weeks. The newest backup is never removed.
+pub fn prune_backups(
+    backup_dir: &Path,
+    keep_daily: usize,
+    keep_weekly: usize,
+) -> Result<Vec<BackupInfo>, AppError> {
+    let backups = list_backups(backup_dir)?;
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut removed = Vec::new();
+
+    for (index, backup) in backups.into_iter().enumerate() {
+        let created = DateTime::from_timestamp(backup.created_at, 0).unwrap_or_default();
+        let day = created.date_naive();
+        let week = (created.iso_week().year(), created.iso_week().week());
+
+        let mut keep = index == 0;
+        if days.len() < keep_daily && days.insert(day) {
+            keep = true;
+        }
+        if weeks.len() < keep_weekly && weeks.insert(week) {
+            keep = true;
+        }
+
+        if !keep {
+            fs::remove_file(backup_path(backup_dir, &backup.backup_id))?;
+            removed.push(backup);
+        }
+    }
+
+    if !removed.is_empty() {
+        log::info!("Pruned {} old backups", removed.len());
+    }
+    Ok(removed)
+}
+
+// Decrypts a backup next to the live database, checks it and brings its schema up to
+// date. Returns the path of the prepared copy, ready for `swap_in_restored`.
+pub async fn prepare_restore(
+    backup_dir: &Path,
+    backup_id: &str,
+    database_path: &Path,
+    key: &DataKey,
+) -> Result<PathBuf, AppError> {
+    let restore_path = unpack_backup(backup_dir, backup_id, database_path, key).await?;
+    if let Err(e) = migrate_database(&restore_path, key).await {
+        remove_database_files(&restore_path)?;
+        return Err(e);
+    }
+    Ok(restore_path)
+}
+
+// Like `prepare_restore`, but leaves the schema exactly as it was in the backup.
+pub async fn unpack_backup(
+    backup_dir: &Path,
+    backup_id: &str,
+    database_path: &Path,
+    key: &DataKey,
+) -> Result<PathBuf, AppError> {
+    // Only IDs from the listing are accepted, never an arbitrary path.
+    if !list_backups(backup_dir)?
+        .iter()
+        .any(|b| b.backup_id == backup_id)
+    {
+        return Err(AppError::not_found("Backup", backup_id));
+    }
+    let path = backup_path(backup_dir, backup_id);
+
+    let restore_path = sibling_path(database_path, ".restoring");
+    remove_database_files(&restore_path)?;
+    fs::write(&restore_path, unseal_backup(&fs::read(&path)?, key)?)?;
+
+    if let Err(e) = check_integrity(&restore_path, key).await {
+        remove_database_files(&restore_path)?;
+        return Err(e);
+    }
+    Ok(restore_path)
+}
+
+// The live pools must be closed before the files are swapped.
+pub fn swap_in_restored(restore_path: &Path, database_path: &Path) -> Result<(), AppError> {
+    remove_database_files(&sibling_path(database_path, "-wal"))?;
+    remove_database_files(&sibling_path(database_path, "-shm"))?;
+    fs::rename(restore_path, database_path)?;
+    Ok(())
+}
+
+// Re-encrypts existing backups after the vault's data key was replaced, so they can
+// still be restored. Failures are logged per file rather than aborting the rest.
+pub async fn rekey_backups(backup_dir: &Path, current_key: &DataKey, new_key: &DataKey) {
+    let backups = match list_backups(backup_dir) {
+        Ok(backups) => backups,
+        Err(e) => {
+            log::warn!("Could not list backups to rekey: {}", e);
+            return;
+        }
+    };
+    for backup in backups {
+        if let Err(e) = rekey_backup(backup_dir, &backup.backup_id, current_key, new_key).await {
+            log::warn!("Could not rekey backup {}: {}", backup.backup_id, e);
+        }
+    }
+}
+
+async fn rekey_backup(
+    backup_dir: &Path,
+    backup_id: &str,
+    current_key: &DataKey,
+    new_key: &DataKey,
+) -> Result<(), AppError> {
+    let path = backup_path(backup_dir, backup_id);
+    let snapshot_path = sibling_path(&path, ".snapshot");
+    fs::write(
+        &snapshot_path,
+        unseal_backup(&fs::read(&path)?, current_key)?,
+    )?;
+
+    let result = match rekey_database(&snapshot_path, current_key, new_key).await {
+        Ok(()) => seal_snapshot(&snapshot_path, &path, new_key),
+        Err(e) => Err(e),
+    };
+    remove_database_files(&snapshot_path)?;
+    result
+}
+
+async fn check_integrity(database_path: &Path, key: &DataKey) -> Result<(), AppError> {
+    let pool = establish_single_connection(database_path, key).await?;
+    let integrity: Result<String, sqlx::Error> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_one(&pool)
+        .await;
+    pool.close().await;
+
+    let integrity = integrity?;
+    if integrity != "ok" {
+        return Err(AppError::Validation(format!(
+            "Backup failed the integrity check: {}",
+            integrity
+        )));
+    }
+    Ok(())
+}
+
+async fn migrate_database(database_path: &Path, key: &DataKey) -> Result<(), AppError> {
+    let pool = establish_single_connection(database_path, key).await?;
+    let result = run_migrations(&pool).await;
+    pool.close().await;
+    result
+}
+
+fn seal_snapshot(snapshot_path: &Path, path: &Path, key: &DataKey) -> Result<(), AppError> {
+    let mut contents = BACKUP_MAGIC.to_vec();
+    contents.extend(seal_bytes(key, &fs::read(snapshot_path)?, BACKUP_MAGIC)?);
+
+    // Write then rename so a half-written file never shows up as a backup.
+    let tmp_path = sibling_path(path, ".tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+fn unseal_backup(contents: &[u8], key: &DataKey) -> Result<Vec<u8>, AppError> {
+    let sealed = contents
+        .strip_prefix(BACKUP_MAGIC.as_slice())
+        .ok_or_else(|| AppError::Validation("File is not a Nocturne backup".to_string()))?;
+    open_sealed(key, sealed, BACKUP_MAGIC)
+}
+
+fn backup_path(backup_dir: &Path, backup_id: &str) -> PathBuf {
+    backup_dir.join(format!("{}.{}", backup_id, BACKUP_EXTENSION))
+}
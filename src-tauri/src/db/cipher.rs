@@ -0,0 +1,101 @@
+use crate::app::error::AppError;
+use crate::db::connection::key_opens_database;
+use crate::db::encryption::DataKey;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{ConnectOptions, Connection};
+use std::ffi::OsString;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+const PLAINTEXT_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+// Stock SQLite silently ignores `PRAGMA key`; only SQLCipher answers `cipher_version`.
+pub async fn probe_cipher() -> Result<Option<String>, AppError> {
+    let mut conn = SqliteConnectOptions::new()
+        .in_memory(true)
+        .connect()
+        .await?;
+    let version: Option<String> = sqlx::query_scalar("PRAGMA cipher_version")
+        .fetch_optional(&mut conn)
+        .await?;
+    conn.close().await?;
+    Ok(version.filter(|v| !v.is_empty()))
+}
+
+// SQLCipher files start with a random salt, plain SQLite files with a fixed magic string.
+pub fn is_plaintext_database(database_path: &Path) -> Result<bool, AppError> {
+    if !database_path.exists() {
+        return Ok(false);
+    }
+    let mut header = [0u8; 16];
+    let mut file = fs::File::open(database_path)?;
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header == PLAINTEXT_HEADER),
+        // Zero-length files are created by SQLite before the first write.
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// One-time conversion of a plaintext database into an encrypted copy keyed with `key`.
+// The plaintext file is only removed once the encrypted copy opens with the key.
+pub async fn encrypt_plaintext_database(
+    database_path: &Path,
+    key: &DataKey,
+) -> Result<(), AppError> {
+    let encrypted_path = sibling_path(database_path, ".encrypting");
+    let backup_path = sibling_path(database_path, ".plaintext");
+    remove_database_files(&encrypted_path)?;
+
+    let mut conn = SqliteConnectOptions::new()
+        .filename(database_path)
+        .connect()
+        .await?;
+    sqlx::query(&format!(
+        "ATTACH DATABASE ? AS encrypted KEY {};",
+        key.pragma_value().as_str()
+    ))
+    .bind(encrypted_path.to_string_lossy().to_string())
+    .execute(&mut conn)
+    .await?;
+    sqlx::query("SELECT sqlcipher_export('encrypted')")
+        .execute(&mut conn)
+        .await?;
+    sqlx::query("DETACH DATABASE encrypted")
+        .execute(&mut conn)
+        .await?;
+    conn.close().await?;
+
+    if !key_opens_database(&encrypted_path, key).await {
+        remove_database_files(&encrypted_path)?;
+        return Err(AppError::Encryption(
+            "Encrypted copy of the database could not be verified".to_string(),
+        ));
+    }
+
+    fs::rename(database_path, &backup_path)?;
+    if let Err(e) = fs::rename(&encrypted_path, database_path) {
+        fs::rename(&backup_path, database_path)?;
+        return Err(e.into());
+    }
+    remove_database_files(&backup_path)?;
+    remove_database_files(&sibling_path(database_path, "-wal"))?;
+    remove_database_files(&sibling_path(database_path, "-shm"))?;
+
+    log::info!("Converted plaintext database to SQLCipher");
+    Ok(())
+}
+
+pub fn sibling_path(database_path: &Path, suffix: &str) -> PathBuf {
+    let mut path: OsString = database_path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+pub fn remove_database_files(path: &Path) -> Result<(), AppError> {
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
@@ -1,27 +1,129 @@
 ﻿use crate::app::error::AppError;
-use crate::db::encryption::get_encryption_key;
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
-use sqlx::SqlitePool;
-use std::str::FromStr;
+use crate::db::encryption::{DataKey, LEGACY_PLACEHOLDER_KEY};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
+use sqlx::{ConnectOptions, Connection, SqlitePool};
+use std::path::Path;
+use std::time::Duration;
 
-pub async fn establish_connection(database_url: &str) -> Result<SqlitePool, AppError> {
-    let key = get_encryption_key();
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_READERS: u32 = 4;
 
-    let options = SqliteConnectOptions::from_str(database_url)?.create_if_missing(true);
+// All writes go through the single writer connection, so they queue in the pool
+// instead of racing for the database lock; WAL lets the readers run alongside it.
+#[derive(Clone)]
+pub struct DbPools {
+    pub reader: SqlitePool,
+    pub writer: SqlitePool,
+}
 
-    let pool = SqlitePoolOptions::new()
-        .max_connections(5)
-        .after_connect(move |conn, _meta| {
-            let key = key.clone();
-            Box::pin(async move {
-                sqlx::query(&format!("PRAGMA key = '{}';", key))
-                    .execute(conn)
-                    .await?;
-                Ok(())
-            })
-        })
-        .connect_with(options)
+impl DbPools {
+    // Readers first, so the writer is the last connection and checkpoints the WAL.
+    pub async fn close(&self) {
+        self.reader.close().await;
+        self.writer.close().await;
+    }
+}
+
+pub async fn establish_connection(
+    database_path: &Path,
+    key: &DataKey,
+) -> Result<DbPools, AppError> {
+    let options = connect_options(database_path, key)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(BUSY_TIMEOUT)
+        .foreign_keys(true);
+
+    // The writer connects first so the file exists and is already in WAL mode
+    // by the time the read-only connections open it.
+    let writer = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(
+            options
+                .clone()
+                .create_if_missing(true)
+                .journal_mode(SqliteJournalMode::Wal),
+        )
+        .await?;
+    let reader = SqlitePoolOptions::new()
+        .max_connections(MAX_READERS)
+        .connect_with(options.read_only(true))
         .await?;
 
+    Ok(DbPools { reader, writer })
+}
+
+// One connection outside the live pools, for checking and migrating a database
+// file (such as a backup) before it is swapped in.
+pub async fn establish_single_connection(
+    database_path: &Path,
+    key: &DataKey,
+) -> Result<SqlitePool, AppError> {
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(connect_options(database_path, key).busy_timeout(BUSY_TIMEOUT))
+        .await?;
     Ok(pool)
 }
+
+// Re-encrypts every page under `new_key`. Pooled connections still hold the old key,
+// so the caller must close the pools before calling this and reopen them afterwards.
+pub async fn rekey_database(
+    database_path: &Path,
+    current_key: &DataKey,
+    new_key: &DataKey,
+) -> Result<(), AppError> {
+    rekey_with(connect_options(database_path, current_key), new_key).await
+}
+
+// Moves a database written with the old shared placeholder key onto `new_key`.
+pub async fn rekey_legacy_database(
+    database_path: &Path,
+    new_key: &DataKey,
+) -> Result<(), AppError> {
+    rekey_with(legacy_connect_options(database_path), new_key).await
+}
+
+pub async fn key_opens_database(database_path: &Path, key: &DataKey) -> bool {
+    can_read_schema(connect_options(database_path, key)).await
+}
+
+pub async fn legacy_key_opens_database(database_path: &Path) -> bool {
+    can_read_schema(legacy_connect_options(database_path)).await
+}
+
+fn connect_options(database_path: &Path, key: &DataKey) -> SqliteConnectOptions {
+    SqliteConnectOptions::new()
+        .filename(database_path)
+        .pragma("key", key.pragma_value().to_string())
+}
+
+fn legacy_connect_options(database_path: &Path) -> SqliteConnectOptions {
+    SqliteConnectOptions::new()
+        .filename(database_path)
+        .pragma("key", format!("'{}'", LEGACY_PLACEHOLDER_KEY))
+}
+
+async fn rekey_with(options: SqliteConnectOptions, new_key: &DataKey) -> Result<(), AppError> {
+    let mut conn = options.connect().await?;
+    sqlx::query(&format!(
+        "PRAGMA rekey = {};",
+        new_key.pragma_value().as_str()
+    ))
+    .execute(&mut conn)
+    .await?;
+    conn.close().await?;
+    Ok(())
+}
+
+// SQLCipher only reports a wrong key once a page is actually read.
+async fn can_read_schema(options: SqliteConnectOptions) -> bool {
+    let Ok(mut conn) = options.connect().await else {
+        return false;
+    };
+    let readable = sqlx::query("SELECT count(*) FROM sqlite_master")
+        .fetch_one(&mut conn)
+        .await
+        .is_ok();
+    let _ = conn.close().await;
+    readable
+}
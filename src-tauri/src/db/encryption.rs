@@ -1,3 +1,291 @@
-﻿pub fn get_encryption_key() -> String {
-    "nocturne-secret-key-placeholder".to_string()
+﻿use crate::app::error::AppError;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+const KEY_FILE_VERSION: u32 = 1;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+// Key every install shared before the key file existed.
+pub const LEGACY_PLACEHOLDER_KEY: &str = "nocturne-secret-key-placeholder";
+
+// Random 256-bit key that encrypts the database pages. It never leaves memory
+// unwrapped; on disk it only exists sealed under the passphrase-derived key.
+#[derive(Clone)]
+pub struct DataKey(Zeroizing<[u8; KEY_LEN]>);
+
+impl DataKey {
+    fn generate() -> Self {
+        let mut bytes = Zeroizing::new([0u8; KEY_LEN]);
+        OsRng.fill_bytes(bytes.as_mut());
+        Self(bytes)
+    }
+
+    fn from_slice(bytes: &[u8]) -> Result<Self, AppError> {
+        let bytes: [u8; KEY_LEN] = bytes.try_into().map_err(|_| {
+            AppError::Internal("Unwrapped data key has the wrong length".to_string())
+        })?;
+        Ok(Self(Zeroizing::new(bytes)))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; KEY_LEN] {
+        &self.0
+    }
+
+    // Raw key literal so SQLCipher uses the key as-is instead of running its own KDF.
+    pub fn pragma_value(&self) -> Zeroizing<String> {
+        Zeroizing::new(format!("\"x'{}'\"", hex::encode_upper(self.0.as_ref())))
+    }
+}
+
+impl fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DataKey(..)")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub algorithm: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            algorithm: "argon2id".to_string(),
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+// On-disk record next to the database: the KDF salt and parameters plus the
+// data key wrapped with the passphrase-derived key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyFile {
+    pub version: u32,
+    pub key_id: String,
+    pub kdf: KdfParams,
+    pub salt: String,
+    pub nonce: String,
+    pub wrapped_key: String,
+    pub created_at: i64,
+    pub passphrase_changed_at: Option<i64>,
+}
+
+impl KeyFile {
+    pub fn create(passphrase: &str) -> Result<(KeyFile, DataKey), AppError> {
+        validate_passphrase(passphrase)?;
+        let data_key = DataKey::generate();
+        let key_file = Self::wrap(
+            Uuid::new_v4().to_string(),
+            &data_key,
+            passphrase,
+            Utc::now().timestamp(),
+        )?;
+        Ok((key_file, data_key))
+    }
+
+    pub fn load(path: &Path) -> Result<Option<KeyFile>, AppError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let raw = fs::read_to_string(path)?;
+        let key_file: KeyFile = serde_json::from_str(&raw)
+            .map_err(|e| AppError::Internal(format!("Key file is corrupt: {}", e)))?;
+        if key_file.version != KEY_FILE_VERSION {
+            return Err(AppError::Internal(format!(
+                "Unsupported key file version {}",
+                key_file.version
+            )));
+        }
+        Ok(Some(key_file))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize key file: {}", e)))?;
+        // Write then rename so a crash never leaves a half-written key file behind.
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+        fs::write(&tmp_path, json)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn unlock(&self, passphrase: &str) -> Result<DataKey, AppError> {
+        let salt = decode_hex(&self.salt)?;
+        let nonce = decode_hex(&self.nonce)?;
+        let wrapped = decode_hex(&self.wrapped_key)?;
+        if nonce.len() != NONCE_LEN {
+            return Err(AppError::Internal("Key file nonce is corrupt".to_string()));
+        }
+
+        let wrapping_key = derive_wrapping_key(passphrase, &salt, &self.kdf)?;
+        let cipher = XChaCha20Poly1305::new(wrapping_key.as_ref().into());
+        // A failed tag check means the passphrase is wrong (or the file was tampered with).
+        let unwrapped = cipher
+            .decrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &wrapped,
+                    aad: self.key_id.as_bytes(),
+                },
+            )
+            .map_err(|_| AppError::Unauthorized)?;
+        let unwrapped = Zeroizing::new(unwrapped);
+
+        DataKey::from_slice(&unwrapped)
+    }
+
+    // Re-wraps the same data key, so the database itself is untouched.
+    pub fn change_passphrase(&self, current: &str, new: &str) -> Result<KeyFile, AppError> {
+        let data_key = self.unlock(current)?;
+        validate_passphrase(new)?;
+        let mut key_file = Self::wrap(self.key_id.clone(), &data_key, new, self.created_at)?;
+        key_file.passphrase_changed_at = Some(Utc::now().timestamp());
+        Ok(key_file)
+    }
+
+    fn wrap(
+        key_id: String,
+        data_key: &DataKey,
+        passphrase: &str,
+        created_at: i64,
+    ) -> Result<KeyFile, AppError> {
+        let kdf = KdfParams::default();
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+
+        let wrapping_key = derive_wrapping_key(passphrase, &salt, &kdf)?;
+        let cipher = XChaCha20Poly1305::new(wrapping_key.as_ref().into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let wrapped = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: data_key.as_bytes(),
+                    aad: key_id.as_bytes(),
+                },
+            )
+            .map_err(|_| AppError::Internal("Failed to wrap data key".to_string()))?;
+
+        Ok(KeyFile {
+            version: KEY_FILE_VERSION,
+            key_id,
+            kdf,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            wrapped_key: hex::encode(wrapped),
+            created_at,
+            passphrase_changed_at: None,
+        })
+    }
+}
+
+// Encrypts a blob under the data key. The output is the random nonce followed by the ciphertext.
+pub fn seal_bytes(key: &DataKey, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, AppError> {
+    let cipher = XChaCha20Poly1305::new(key.as_bytes().into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| AppError::Encryption("Failed to encrypt data".to_string()))?;
+
+    let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+pub fn open_sealed(key: &DataKey, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, AppError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(AppError::Encryption(
+            "Encrypted data is truncated".to_string(),
+        ));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(key.as_bytes().into());
+    cipher
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| {
+            AppError::Encryption("Data could not be decrypted with this vault's key".to_string())
+        })
+}
+
+pub fn key_file_path(database_path: &Path) -> PathBuf {
+    database_path.with_extension("key")
+}
+
+// Written before `PRAGMA rekey` and promoted once the rekey succeeded.
+pub fn pending_key_file_path(database_path: &Path) -> PathBuf {
+    database_path.with_extension("key.pending")
+}
+
+pub fn validate_passphrase(passphrase: &str) -> Result<(), AppError> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(AppError::Validation(format!(
+            "Passphrase must be at least {} characters.",
+            MIN_PASSPHRASE_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn derive_wrapping_key(
+    passphrase: &str,
+    salt: &[u8],
+    kdf: &KdfParams,
+) -> Result<Zeroizing<[u8; KEY_LEN]>, AppError> {
+    if kdf.algorithm != "argon2id" {
+        return Err(AppError::Internal(format!(
+            "Unsupported key derivation algorithm: {}",
+            kdf.algorithm
+        )));
+    }
+
+    let params = Params::new(
+        kdf.memory_kib,
+        kdf.iterations,
+        kdf.parallelism,
+        Some(KEY_LEN),
+    )
+    .map_err(|e| AppError::Internal(format!("Invalid key derivation parameters: {}", e)))?;
+    let mut output = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, output.as_mut())
+        .map_err(|e| AppError::Internal(format!("Key derivation failed: {}", e)))?;
+
+    Ok(output)
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, AppError> {
+    hex::decode(value).map_err(|_| AppError::Internal("Key file is corrupt".to_string()))
 }
@@ -0,0 +1,454 @@
+use crate::app::error::AppError;
+use crate::domains::diary::model::DiaryEntry;
+use crate::domains::goals::model::Goal;
+use crate::domains::habits::model::Habit;
+use crate::domains::jobs::model::JobApplication;
+use crate::utils::hashing::{
+    fill_missing_hashes, find_stale_hashes, store_content_hash, ContentHash,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    SqliteCorruption,      // `PRAGMA integrity_check`
+    ForeignKeyViolation,   // `PRAGMA foreign_key_check`, minus the cases below
+    OrphanHabitLog,        // habit_logs row whose habit is gone
+    OrphanSubPage,         // parent_page_id points nowhere
+    DanglingPageReference, // primary_page_id or root_page_id points nowhere
+    TreeCounterMismatch,   // has_children / children_count / descendant_count out of date
+    DuplicatePrimaryPage,  // more than one primary page for an entry_date
+    StaleContentHash,      // content_hash missing or not matching the row
+}
+
+impl IssueKind {
+    // None for problems that need a backup restore rather than an in-place fix.
+    pub fn repair_description(&self) -> Option<&'static str> {
+        match self {
+            IssueKind::SqliteCorruption | IssueKind::ForeignKeyViolation => None,
+            IssueKind::OrphanHabitLog => Some("Delete the logs of habits that no longer exist"),
+            IssueKind::OrphanSubPage => {
+                Some("Move orphaned sub-pages under their day's primary page, or to the top level")
+            }
+            IssueKind::DanglingPageReference => Some("Clear the broken page references"),
+            IssueKind::TreeCounterMismatch => Some("Recount children and descendants"),
+            IssueKind::DuplicatePrimaryPage => {
+                Some("Keep the oldest primary page per day and nest the others under it")
+            }
+            IssueKind::StaleContentHash => Some("Recompute content hashes"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    pub kind: IssueKind,
+    pub table: String,
+    pub entity_id: Option<String>,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepairAction {
+    pub kind: IssueKind,
+    pub description: String,
+    pub issue_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub checked_at: i64,
+    pub is_healthy: bool,
+    pub issues: Vec<IntegrityIssue>,
+    pub repairs: Vec<RepairAction>,
+}
+
+// Actual child and descendant counts per page, ignoring deleted pages. UNION (not
+// UNION ALL) keeps a parent_page_id cycle from recursing forever.
+const TREE_COUNTS: &str = "
+    WITH RECURSIVE tree(ancestor_id, descendant_id) AS (
+        SELECT parent_page_id, diary_entry_id FROM diary_entries
+        WHERE parent_page_id IS NOT NULL AND is_deleted = 0
+        UNION
+        SELECT tree.ancestor_id, child.diary_entry_id
+        FROM tree JOIN diary_entries child ON child.parent_page_id = tree.descendant_id
+        WHERE child.is_deleted = 0
+    ),
+    actual AS (
+        SELECT entry.diary_entry_id,
+            (SELECT COUNT(*) FROM diary_entries child
+             WHERE child.parent_page_id = entry.diary_entry_id AND child.is_deleted = 0) AS children_count,
+            (SELECT COUNT(*) FROM tree WHERE tree.ancestor_id = entry.diary_entry_id) AS descendant_count
+        FROM diary_entries entry
+    )";
+
+pub async fn run_integrity_check(pool: &SqlitePool) -> Result<IntegrityReport, AppError> {
+    let mut issues = Vec::new();
+
+    let messages: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(pool)
+        .await?;
+    issues.extend(
+        messages
+            .into_iter()
+            .filter(|m| m != "ok")
+            .map(|detail| IntegrityIssue {
+                kind: IssueKind::SqliteCorruption,
+                table: String::new(),
+                entity_id: None,
+                detail,
+            }),
+    );
+
+    // Violations that the application-level checks below describe more precisely are left out.
+    let violations: Vec<(String, Option<i64>, String)> =
+        sqlx::query_as("SELECT \"table\", rowid, parent FROM pragma_foreign_key_check")
+            .fetch_all(pool)
+            .await?;
+    issues.extend(
+        violations
+            .into_iter()
+            .filter(|(table, _, parent)| {
+                !matches!(
+                    (table.as_str(), parent.as_str()),
+                    ("habit_logs", "habits") | ("diary_entries", "diary_entries")
+                )
+            })
+            .map(|(table, rowid, parent)| IntegrityIssue {
+                kind: IssueKind::ForeignKeyViolation,
+                detail: format!(
+                    "Row {} references a missing {} row",
+                    rowid.unwrap_or(0),
+                    parent
+                ),
+                table,
+                entity_id: rowid.map(|r| r.to_string()),
+            }),
+    );
+
+    let orphan_logs: Vec<(String, String)> = sqlx::query_as(
+        "SELECT log_id, habit_id FROM habit_logs
+         WHERE NOT EXISTS (SELECT 1 FROM habits WHERE habits.habit_id = habit_logs.habit_id)",
+    )
+    .fetch_all(pool)
+    .await?;
+    issues.extend(
+        orphan_logs
+            .into_iter()
+            .map(|(log_id, habit_id)| IntegrityIssue {
+                kind: IssueKind::OrphanHabitLog,
+                table: "habit_logs".to_string(),
+                entity_id: Some(log_id),
+                detail: format!("Log belongs to missing habit {}", habit_id),
+            }),
+    );
+
+    let orphan_pages: Vec<(String, String)> = sqlx::query_as(
+        "SELECT diary_entry_id, parent_page_id FROM diary_entries page
+         WHERE parent_page_id IS NOT NULL
+           AND NOT EXISTS (SELECT 1 FROM diary_entries parent
+                           WHERE parent.diary_entry_id = page.parent_page_id)",
+    )
+    .fetch_all(pool)
+    .await?;
+    issues.extend(
+        orphan_pages
+            .into_iter()
+            .map(|(id, parent_id)| IntegrityIssue {
+                kind: IssueKind::OrphanSubPage,
+                table: "diary_entries".to_string(),
+                entity_id: Some(id),
+                detail: format!("Parent page {} does not exist", parent_id),
+            }),
+    );
+
+    let dangling: Vec<(String, Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT diary_entry_id, primary_page_id, root_page_id FROM diary_entries page
+         WHERE (primary_page_id IS NOT NULL AND NOT EXISTS (
+                    SELECT 1 FROM diary_entries p WHERE p.diary_entry_id = page.primary_page_id))
+            OR (root_page_id IS NOT NULL AND NOT EXISTS (
+                    SELECT 1 FROM diary_entries r WHERE r.diary_entry_id = page.root_page_id))",
+    )
+    .fetch_all(pool)
+    .await?;
+    issues.extend(
+        dangling
+            .into_iter()
+            .map(|(id, primary, root)| IntegrityIssue {
+                kind: IssueKind::DanglingPageReference,
+                table: "diary_entries".to_string(),
+                entity_id: Some(id),
+                detail: format!(
+                    "References missing pages (primary {}, root {})",
+                    primary.as_deref().unwrap_or("none"),
+                    root.as_deref().unwrap_or("none")
+                ),
+            }),
+    );
+
+    let counters: Vec<(String, i64, i64, i64, i64)> = sqlx::query_as(&format!(
+        "{}
+         SELECT entry.diary_entry_id, entry.children_count, entry.descendant_count,
+                actual.children_count, actual.descendant_count
+         FROM diary_entries entry JOIN actual USING (diary_entry_id)
+         WHERE entry.children_count != actual.children_count
+            OR entry.descendant_count != actual.descendant_count
+            OR entry.has_children != (actual.children_count > 0)",
+        TREE_COUNTS
+    ))
+    .fetch_all(pool)
+    .await?;
+    issues.extend(counters.into_iter().map(
+        |(id, children, descendants, actual_children, actual_descendants)| IntegrityIssue {
+            kind: IssueKind::TreeCounterMismatch,
+            table: "diary_entries".to_string(),
+            entity_id: Some(id),
+            detail: format!(
+                "Stored {} children / {} descendants, actually {} / {}",
+                children, descendants, actual_children, actual_descendants
+            ),
+        },
+    ));
+
+    let duplicates: Vec<(String, String)> = sqlx::query_as(
+        "SELECT diary_entry_id, entry_date FROM diary_entries page
+         WHERE is_primary_page = 1
+           AND EXISTS (SELECT 1 FROM diary_entries older
+                       WHERE older.entry_date = page.entry_date AND older.is_primary_page = 1
+                         AND (older.created_at, older.rowid) < (page.created_at, page.rowid))",
+    )
+    .fetch_all(pool)
+    .await?;
+    issues.extend(duplicates.into_iter().map(|(id, date)| IntegrityIssue {
+        kind: IssueKind::DuplicatePrimaryPage,
+        table: "diary_entries".to_string(),
+        entity_id: Some(id),
+        detail: format!("Second primary page for {}", date),
+    }));
+
+    issues.extend(stale_hash_issues::<DiaryEntry>(pool).await?);
+    issues.extend(stale_hash_issues::<Habit>(pool).await?);
+    issues.extend(stale_hash_issues::<Goal>(pool).await?);
+    issues.extend(stale_hash_issues::<JobApplication>(pool).await?);
+
+    let mut repairs: Vec<RepairAction> = Vec::new();
+    for issue in &issues {
+        let Some(description) = issue.kind.repair_description() else {
+            continue;
+        };
+        match repairs.iter_mut().find(|r| r.kind == issue.kind) {
+            Some(repair) => repair.issue_count += 1,
+            None => repairs.push(RepairAction {
+                kind: issue.kind,
+                description: description.to_string(),
+                issue_count: 1,
+            }),
+        }
+    }
+
+    Ok(IntegrityReport {
+        checked_at: Utc::now().timestamp(),
+        is_healthy: issues.is_empty(),
+        issues,
+        repairs,
+    })
+}
+
+// Applies the repair for one kind of issue in a single transaction and returns the
+// number of rows changed.
+pub async fn repair_issues(pool: &SqlitePool, kind: IssueKind) -> Result<u64, AppError> {
+    if kind.repair_description().is_none() {
+        return Err(AppError::Validation(
+            "This problem can't be repaired in place; restore a backup instead.".to_string(),
+        ));
+    }
+
+    // Hashes are computed in Rust, row by row, so this one can't be a single statement.
+    if kind == IssueKind::StaleContentHash {
+        let changed = refresh_stale_hash::<DiaryEntry>(pool).await?
+            + refresh_stale_hash::<Habit>(pool).await?
+            + refresh_stale_hash::<Goal>(pool).await?
+            + refresh_stale_hash::<JobApplication>(pool).await?;
+        log::info!("Repaired {:?}: {} rows changed", kind, changed);
+        return Ok(changed);
+    }
+
+    let mut tx = pool.begin().await?;
+    let changed = match kind {
+        IssueKind::OrphanHabitLog => {
+            sqlx::query(
+                "DELETE FROM habit_logs
+                 WHERE NOT EXISTS (SELECT 1 FROM habits WHERE habits.habit_id = habit_logs.habit_id)",
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected()
+        }
+        IssueKind::OrphanSubPage => {
+            let moved = sqlx::query(
+                "UPDATE diary_entries AS page SET
+                    parent_page_id = target.primary_id,
+                    root_page_id = target.primary_id,
+                    primary_page_id = target.primary_id,
+                    page_depth = CASE WHEN target.primary_id IS NULL THEN 0 ELSE 1 END,
+                    updated_at = ?
+                 FROM (SELECT orphan.diary_entry_id AS orphan_id,
+                              (SELECT day.diary_entry_id FROM diary_entries day
+                               WHERE day.entry_date = orphan.entry_date AND day.is_primary_page = 1
+                                 AND day.diary_entry_id != orphan.diary_entry_id
+                               ORDER BY day.created_at, day.rowid LIMIT 1) AS primary_id
+                       FROM diary_entries orphan
+                       WHERE orphan.parent_page_id IS NOT NULL
+                         AND NOT EXISTS (SELECT 1 FROM diary_entries parent
+                                         WHERE parent.diary_entry_id = orphan.parent_page_id)) AS target
+                 WHERE page.diary_entry_id = target.orphan_id",
+            )
+            .bind(Utc::now().timestamp())
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            recount_tree(&mut tx).await?;
+            moved
+        }
+        IssueKind::DanglingPageReference => {
+            let primary = sqlx::query(
+                "UPDATE diary_entries AS page SET primary_page_id = NULL
+                 WHERE primary_page_id IS NOT NULL AND NOT EXISTS (
+                    SELECT 1 FROM diary_entries p WHERE p.diary_entry_id = page.primary_page_id)",
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            let root = sqlx::query(
+                "UPDATE diary_entries AS page SET root_page_id = NULL
+                 WHERE root_page_id IS NOT NULL AND NOT EXISTS (
+                    SELECT 1 FROM diary_entries r WHERE r.diary_entry_id = page.root_page_id)",
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            primary + root
+        }
+        IssueKind::TreeCounterMismatch => recount_tree(&mut tx).await?,
+        IssueKind::DuplicatePrimaryPage => {
+            let demoted = sqlx::query(
+                "UPDATE diary_entries AS page SET
+                    is_primary_page = 0,
+                    parent_page_id = keeper.diary_entry_id,
+                    root_page_id = keeper.diary_entry_id,
+                    primary_page_id = keeper.diary_entry_id,
+                    page_depth = 1,
+                    updated_at = ?
+                 FROM (SELECT entry_date, diary_entry_id FROM (
+                          SELECT entry_date, diary_entry_id,
+                                 ROW_NUMBER() OVER (PARTITION BY entry_date ORDER BY created_at, rowid) AS rank
+                          FROM diary_entries WHERE is_primary_page = 1)
+                       WHERE rank = 1) AS keeper
+                 WHERE page.is_primary_page = 1
+                   AND page.entry_date = keeper.entry_date
+                   AND page.diary_entry_id != keeper.diary_entry_id",
+            )
+            .bind(Utc::now().timestamp())
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            reroot_tree(&mut tx).await?;
+            recount_tree(&mut tx).await?;
+            demoted
+        }
+        IssueKind::SqliteCorruption
+        | IssueKind::ForeignKeyViolation
+        | IssueKind::StaleContentHash => 0,
+    };
+    tx.commit().await?;
+
+    log::info!("Repaired {:?}: {} rows changed", kind, changed);
+    Ok(changed)
+}
+
+// Brings each page's child and descendant counters back in line with the live tree.
+pub async fn recount_tree(tx: &mut Transaction<'_, Sqlite>) -> Result<u64, AppError> {
+    let result = sqlx::query(&format!(
+        "{}
+         UPDATE diary_entries SET
+            children_count = actual.children_count,
+            descendant_count = actual.descendant_count,
+            has_children = actual.children_count > 0
+         FROM actual
+         WHERE actual.diary_entry_id = diary_entries.diary_entry_id
+           AND (diary_entries.children_count != actual.children_count
+                OR diary_entries.descendant_count != actual.descendant_count
+                OR diary_entries.has_children != (actual.children_count > 0))",
+        TREE_COUNTS
+    ))
+    .execute(&mut **tx)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// Points every sub-page at the primary page its branch hangs from and sets its depth
+// below it, e.g. after a primary page was demoted under another one.
+pub async fn reroot_tree(tx: &mut Transaction<'_, Sqlite>) -> Result<u64, AppError> {
+    let result = sqlx::query(
+        "WITH RECURSIVE placed(diary_entry_id, primary_id, depth) AS (
+            SELECT diary_entry_id, diary_entry_id, 0 FROM diary_entries WHERE is_primary_page = 1
+            UNION ALL
+            SELECT child.diary_entry_id, placed.primary_id, placed.depth + 1
+            FROM placed JOIN diary_entries child
+                ON child.parent_page_id = placed.diary_entry_id AND child.is_primary_page = 0
+         )
+         UPDATE diary_entries SET
+            root_page_id = placed.primary_id,
+            primary_page_id = placed.primary_id,
+            page_depth = placed.depth,
+            updated_at = ?
+         FROM placed
+         WHERE placed.diary_entry_id = diary_entries.diary_entry_id
+           AND placed.depth > 0
+           AND (diary_entries.root_page_id IS NOT placed.primary_id
+                OR diary_entries.primary_page_id IS NOT placed.primary_id
+                OR diary_entries.page_depth != placed.depth)",
+    )
+    .bind(Utc::now().timestamp())
+    .execute(&mut **tx)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// Run on unlock, so rows written before hashing existed get one.
+pub async fn backfill_content_hashes(pool: &SqlitePool) -> Result<usize, AppError> {
+    Ok(fill_missing_hashes::<DiaryEntry>(pool).await?
+        + fill_missing_hashes::<Habit>(pool).await?
+        + fill_missing_hashes::<Goal>(pool).await?
+        + fill_missing_hashes::<JobApplication>(pool).await?)
+}
+
+async fn stale_hash_issues<T>(pool: &SqlitePool) -> Result<Vec<IntegrityIssue>, AppError>
+where
+    T: ContentHash + for<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> + Send + Unpin,
+{
+    Ok(find_stale_hashes::<T>(pool)
+        .await?
+        .into_iter()
+        .map(|id| IntegrityIssue {
+            kind: IssueKind::StaleContentHash,
+            table: T::TABLE.to_string(),
+            entity_id: Some(id),
+            detail: "Stored content hash is missing or out of date".to_string(),
+        })
+        .collect())
+}
+
+async fn refresh_stale_hash<T>(pool: &SqlitePool) -> Result<u64, AppError>
+where
+    T: ContentHash + for<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow> + Send + Unpin,
+{
+    let ids = find_stale_hashes::<T>(pool).await?;
+    let mut conn = pool.acquire().await?;
+    for id in &ids {
+        store_content_hash::<T>(&mut conn, id).await?;
+    }
+    Ok(ids.len() as u64)
+}
@@ -0,0 +1,14 @@
+-- 0008_profiles.sql
+
+CREATE TABLE profiles (
+    profile_id TEXT PRIMARY KEY NOT NULL,
+    display_name TEXT NOT NULL,
+    avatar TEXT, -- emoji or image path
+    timezone TEXT, -- IANA name, e.g. Europe/Berlin
+    preferences_json TEXT NOT NULL DEFAULT '{}',
+    last_selected_at INTEGER,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_profiles_last_selected_at ON profiles(last_selected_at);
@@ -0,0 +1,22 @@
+-- 0009_slug_aliases.sql
+-- Old slugs keep resolving after a rename, so links into the app don't break.
+
+CREATE TABLE goal_slug_aliases (
+    alias_id TEXT PRIMARY KEY NOT NULL,
+    alias_slug TEXT UNIQUE NOT NULL,
+    goal_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (goal_id) REFERENCES goals(goal_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_goal_slug_aliases_goal_id ON goal_slug_aliases(goal_id);
+
+CREATE TABLE job_application_slug_aliases (
+    alias_id TEXT PRIMARY KEY NOT NULL,
+    alias_slug TEXT UNIQUE NOT NULL,
+    job_application_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (job_application_id) REFERENCES job_applications(job_application_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_job_application_slug_aliases_job_id ON job_application_slug_aliases(job_application_id);
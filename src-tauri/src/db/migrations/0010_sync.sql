@@ -0,0 +1,52 @@
+-- 0010_sync.sql
+-- Bookkeeping for folder sync. It describes this device's view of the vault, so none
+-- of it is exported in archives or sent to other devices.
+
+CREATE TABLE sync_meta (
+    meta_key TEXT PRIMARY KEY NOT NULL,
+    meta_value TEXT NOT NULL
+);
+
+-- Each synced row as it was last exported or applied; local edits are found by
+-- comparing against it.
+CREATE TABLE sync_shadow (
+    table_name TEXT NOT NULL,
+    record_id TEXT NOT NULL,
+    row_json TEXT NOT NULL,
+    PRIMARY KEY (table_name, record_id)
+);
+
+-- The HLC of the last write to each field, and the version its writer had seen.
+CREATE TABLE sync_field_clocks (
+    table_name TEXT NOT NULL,
+    record_id TEXT NOT NULL,
+    field_name TEXT NOT NULL,
+    hlc TEXT NOT NULL,
+    base_hlc TEXT,
+    PRIMARY KEY (table_name, record_id, field_name)
+);
+
+-- The last change set applied from each other device.
+CREATE TABLE sync_peers (
+    device_id TEXT PRIMARY KEY NOT NULL,
+    last_change_set TEXT NOT NULL,
+    last_synced_at INTEGER NOT NULL
+);
+
+CREATE TABLE sync_conflicts (
+    conflict_id TEXT PRIMARY KEY NOT NULL,
+    table_name TEXT NOT NULL,
+    record_id TEXT NOT NULL,
+    field_name TEXT NOT NULL,
+    local_value TEXT, -- JSON
+    remote_value TEXT, -- JSON
+    local_hlc TEXT NOT NULL,
+    remote_hlc TEXT NOT NULL,
+    remote_device_id TEXT NOT NULL,
+    detected_at INTEGER NOT NULL,
+    resolved_at INTEGER,
+    resolution TEXT -- keep_local, keep_remote, custom, superseded
+);
+
+CREATE INDEX idx_sync_conflicts_record ON sync_conflicts(table_name, record_id);
+CREATE INDEX idx_sync_conflicts_resolved_at ON sync_conflicts(resolved_at);
@@ -0,0 +1,63 @@
+-- 0011_history.sql
+-- Append-only history. Each event is a snapshot of the record right after a change,
+-- so a timeline is read by comparing consecutive rows. Rows are never updated.
+
+CREATE TABLE goal_progress_events (
+    event_id TEXT PRIMARY KEY NOT NULL,
+    goal_id TEXT NOT NULL,
+    event_type TEXT NOT NULL, -- created, renamed
+    goal_title TEXT NOT NULL,
+    goal_status TEXT NOT NULL,
+    current_value REAL NOT NULL,
+    progress_percentage REAL NOT NULL,
+    event_date TEXT NOT NULL,
+    recorded_at INTEGER NOT NULL,
+    profile_id TEXT,
+    device_id TEXT NOT NULL,
+    session_id TEXT NOT NULL,
+    FOREIGN KEY (goal_id) REFERENCES goals(goal_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_goal_progress_events_goal_id ON goal_progress_events(goal_id, recorded_at);
+CREATE INDEX idx_goal_progress_events_event_date ON goal_progress_events(event_date);
+
+CREATE TABLE job_status_events (
+    event_id TEXT PRIMARY KEY NOT NULL,
+    job_application_id TEXT NOT NULL,
+    event_type TEXT NOT NULL, -- created, renamed
+    job_status TEXT NOT NULL,
+    job_title TEXT NOT NULL,
+    company_name TEXT NOT NULL,
+    event_date TEXT NOT NULL,
+    recorded_at INTEGER NOT NULL,
+    profile_id TEXT,
+    device_id TEXT NOT NULL,
+    session_id TEXT NOT NULL,
+    FOREIGN KEY (job_application_id) REFERENCES job_applications(job_application_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_job_status_events_job_id ON job_status_events(job_application_id, recorded_at);
+CREATE INDEX idx_job_status_events_job_status ON job_status_events(job_status);
+
+CREATE TABLE diary_edit_history (
+    history_id TEXT PRIMARY KEY NOT NULL,
+    diary_entry_id TEXT NOT NULL,
+    event_type TEXT NOT NULL, -- created, edited
+    title TEXT,
+    content_json TEXT NOT NULL,
+    word_count INTEGER NOT NULL,
+    mood_label TEXT,
+    mood_rating INTEGER,
+    energy_level INTEGER,
+    stress_level INTEGER,
+    importance_level INTEGER NOT NULL,
+    event_date TEXT NOT NULL,
+    recorded_at INTEGER NOT NULL,
+    profile_id TEXT,
+    device_id TEXT NOT NULL,
+    session_id TEXT NOT NULL,
+    FOREIGN KEY (diary_entry_id) REFERENCES diary_entries(diary_entry_id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_diary_edit_history_entry_id ON diary_edit_history(diary_entry_id, recorded_at);
+CREATE INDEX idx_diary_edit_history_event_date ON diary_edit_history(event_date);
@@ -0,0 +1,326 @@
+-- 0012_relations.sql
+-- Cross-domain links move out of ID-list columns into join tables. A link's ID is
+-- built from its two ends and its type, so devices that make the same link offline
+-- end up with one row. Unlinking sets unlinked_at instead of deleting, so it syncs.
+
+CREATE TABLE diary_habits (
+    link_id TEXT PRIMARY KEY NOT NULL,
+    diary_entry_id TEXT NOT NULL,
+    habit_id TEXT NOT NULL,
+    relation_type TEXT NOT NULL, -- linked, completed
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    unlinked_at INTEGER,
+    FOREIGN KEY (diary_entry_id) REFERENCES diary_entries(diary_entry_id) ON DELETE CASCADE,
+    FOREIGN KEY (habit_id) REFERENCES habits(habit_id) ON DELETE CASCADE,
+    UNIQUE(diary_entry_id, habit_id, relation_type)
+);
+
+CREATE INDEX idx_diary_habits_habit_id ON diary_habits(habit_id);
+
+CREATE TABLE diary_goals (
+    link_id TEXT PRIMARY KEY NOT NULL,
+    diary_entry_id TEXT NOT NULL,
+    goal_id TEXT NOT NULL,
+    relation_type TEXT NOT NULL, -- linked, progressed
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    unlinked_at INTEGER,
+    FOREIGN KEY (diary_entry_id) REFERENCES diary_entries(diary_entry_id) ON DELETE CASCADE,
+    FOREIGN KEY (goal_id) REFERENCES goals(goal_id) ON DELETE CASCADE,
+    UNIQUE(diary_entry_id, goal_id, relation_type)
+);
+
+CREATE INDEX idx_diary_goals_goal_id ON diary_goals(goal_id);
+
+CREATE TABLE diary_jobs (
+    link_id TEXT PRIMARY KEY NOT NULL,
+    diary_entry_id TEXT NOT NULL,
+    job_application_id TEXT NOT NULL,
+    relation_type TEXT NOT NULL, -- linked
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    unlinked_at INTEGER,
+    FOREIGN KEY (diary_entry_id) REFERENCES diary_entries(diary_entry_id) ON DELETE CASCADE,
+    FOREIGN KEY (job_application_id) REFERENCES job_applications(job_application_id) ON DELETE CASCADE,
+    UNIQUE(diary_entry_id, job_application_id, relation_type)
+);
+
+CREATE INDEX idx_diary_jobs_job_id ON diary_jobs(job_application_id);
+
+CREATE TABLE habit_goals (
+    link_id TEXT PRIMARY KEY NOT NULL,
+    habit_id TEXT NOT NULL,
+    goal_id TEXT NOT NULL,
+    relation_type TEXT NOT NULL, -- linked
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    unlinked_at INTEGER,
+    FOREIGN KEY (habit_id) REFERENCES habits(habit_id) ON DELETE CASCADE,
+    FOREIGN KEY (goal_id) REFERENCES goals(goal_id) ON DELETE CASCADE,
+    UNIQUE(habit_id, goal_id, relation_type)
+);
+
+CREATE INDEX idx_habit_goals_goal_id ON habit_goals(goal_id);
+
+CREATE TABLE job_goals (
+    link_id TEXT PRIMARY KEY NOT NULL,
+    job_application_id TEXT NOT NULL,
+    goal_id TEXT NOT NULL,
+    relation_type TEXT NOT NULL, -- linked
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    unlinked_at INTEGER,
+    FOREIGN KEY (job_application_id) REFERENCES job_applications(job_application_id) ON DELETE CASCADE,
+    FOREIGN KEY (goal_id) REFERENCES goals(goal_id) ON DELETE CASCADE,
+    UNIQUE(job_application_id, goal_id, relation_type)
+);
+
+CREATE INDEX idx_job_goals_goal_id ON job_goals(goal_id);
+
+CREATE TABLE job_habits (
+    link_id TEXT PRIMARY KEY NOT NULL,
+    job_application_id TEXT NOT NULL,
+    habit_id TEXT NOT NULL,
+    relation_type TEXT NOT NULL, -- linked
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    unlinked_at INTEGER,
+    FOREIGN KEY (job_application_id) REFERENCES job_applications(job_application_id) ON DELETE CASCADE,
+    FOREIGN KEY (habit_id) REFERENCES habits(habit_id) ON DELETE CASCADE,
+    UNIQUE(job_application_id, habit_id, relation_type)
+);
+
+CREATE INDEX idx_job_habits_habit_id ON job_habits(habit_id);
+
+-- goal_id depends on depends_on_goal_id: it needs it done first (prerequisite) or is
+-- held up by it (blocker).
+CREATE TABLE goal_dependencies (
+    link_id TEXT PRIMARY KEY NOT NULL,
+    goal_id TEXT NOT NULL,
+    depends_on_goal_id TEXT NOT NULL,
+    relation_type TEXT NOT NULL, -- prerequisite, blocker
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    unlinked_at INTEGER,
+    FOREIGN KEY (goal_id) REFERENCES goals(goal_id) ON DELETE CASCADE,
+    FOREIGN KEY (depends_on_goal_id) REFERENCES goals(goal_id) ON DELETE CASCADE,
+    UNIQUE(goal_id, depends_on_goal_id, relation_type)
+);
+
+CREATE INDEX idx_goal_dependencies_depends_on ON goal_dependencies(depends_on_goal_id);
+
+CREATE TABLE habit_dependencies (
+    link_id TEXT PRIMARY KEY NOT NULL,
+    habit_id TEXT NOT NULL,
+    depends_on_habit_id TEXT NOT NULL,
+    relation_type TEXT NOT NULL, -- prerequisite, blocker
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    unlinked_at INTEGER,
+    FOREIGN KEY (habit_id) REFERENCES habits(habit_id) ON DELETE CASCADE,
+    FOREIGN KEY (depends_on_habit_id) REFERENCES habits(habit_id) ON DELETE CASCADE,
+    UNIQUE(habit_id, depends_on_habit_id, relation_type)
+);
+
+CREATE INDEX idx_habit_dependencies_depends_on ON habit_dependencies(depends_on_habit_id);
+
+-- Existing lists, one row per listed ID. Lists were written either as JSON arrays or
+-- comma-separated.
+CREATE TEMP TABLE legacy_links (
+    source_table TEXT NOT NULL,
+    source_id TEXT NOT NULL,
+    list_column TEXT NOT NULL,
+    item_id TEXT NOT NULL
+);
+
+WITH RECURSIVE
+legacy_lists(source_table, source_id, list_column, list) AS (
+    SELECT 'diary_entries', diary_entry_id, 'linked_habit_ids', linked_habit_ids FROM diary_entries
+    UNION ALL SELECT 'diary_entries', diary_entry_id, 'completed_habit_ids', completed_habit_ids FROM diary_entries
+    UNION ALL SELECT 'diary_entries', diary_entry_id, 'linked_goal_ids', linked_goal_ids FROM diary_entries
+    UNION ALL SELECT 'diary_entries', diary_entry_id, 'progressed_goal_ids', progressed_goal_ids FROM diary_entries
+    UNION ALL SELECT 'diary_entries', diary_entry_id, 'linked_job_ids', linked_job_ids FROM diary_entries
+    UNION ALL SELECT 'habits', habit_id, 'prerequisite_habit_ids', prerequisite_habit_ids FROM habits
+    UNION ALL SELECT 'habits', habit_id, 'dependent_habit_ids', dependent_habit_ids FROM habits
+    UNION ALL SELECT 'habits', habit_id, 'blocked_by_habit_ids', blocked_by_habit_ids FROM habits
+    UNION ALL SELECT 'habits', habit_id, 'unlocks_habit_ids', unlocks_habit_ids FROM habits
+    UNION ALL SELECT 'habits', habit_id, 'linked_goal_ids', linked_goal_ids FROM habits
+    UNION ALL SELECT 'habits', habit_id, 'linked_diary_page_ids', linked_diary_page_ids FROM habits
+    UNION ALL SELECT 'habits', habit_id, 'linked_job_ids', linked_job_ids FROM habits
+    UNION ALL SELECT 'goals', goal_id, 'prerequisite_goal_ids', prerequisite_goal_ids FROM goals
+    UNION ALL SELECT 'goals', goal_id, 'dependent_goal_ids', dependent_goal_ids FROM goals
+    UNION ALL SELECT 'goals', goal_id, 'blocked_by_goal_ids', blocked_by_goal_ids FROM goals
+    UNION ALL SELECT 'goals', goal_id, 'unlocks_goal_ids', unlocks_goal_ids FROM goals
+    UNION ALL SELECT 'goals', goal_id, 'linked_habit_ids', linked_habit_ids FROM goals
+    UNION ALL SELECT 'goals', goal_id, 'linked_diary_page_ids', linked_diary_page_ids FROM goals
+    UNION ALL SELECT 'goals', goal_id, 'linked_job_ids', linked_job_ids FROM goals
+    UNION ALL SELECT 'job_applications', job_application_id, 'linked_goal_ids', linked_goal_ids FROM job_applications
+    UNION ALL SELECT 'job_applications', job_application_id, 'linked_habit_ids', linked_habit_ids FROM job_applications
+    UNION ALL SELECT 'job_applications', job_application_id, 'linked_diary_entry_ids', linked_diary_entry_ids FROM job_applications
+),
+csv_items(source_table, source_id, list_column, item, rest) AS (
+    SELECT source_table, source_id, list_column, '', list || ','
+    FROM legacy_lists
+    WHERE list IS NOT NULL
+      AND NOT (CASE WHEN json_valid(list) THEN json_type(list) = 'array' ELSE 0 END)
+    UNION ALL
+    SELECT source_table, source_id, list_column,
+        trim(substr(rest, 1, instr(rest, ',') - 1)),
+        substr(rest, instr(rest, ',') + 1)
+    FROM csv_items
+    WHERE rest != ''
+)
+INSERT INTO legacy_links (source_table, source_id, list_column, item_id)
+SELECT source_table, source_id, list_column, item FROM csv_items WHERE item != ''
+UNION ALL
+SELECT source_table, source_id, list_column, trim(json_item.value)
+FROM legacy_lists, json_each(
+    CASE WHEN list IS NOT NULL AND json_valid(list) AND json_type(list) = 'array'
+        THEN list ELSE '[]' END
+) AS json_item
+WHERE json_item.type = 'text' AND trim(json_item.value) != '';
+
+-- (relation table, left column, right column, relation type, left ID, right ID),
+-- keeping only links whose both ends still exist.
+CREATE TEMP TABLE legacy_relations AS
+SELECT 'diary_habits' AS relation_table,
+    source_id AS left_id, item_id AS right_id,
+    CASE list_column WHEN 'completed_habit_ids' THEN 'completed' ELSE 'linked' END AS relation_type
+FROM legacy_links
+WHERE source_table = 'diary_entries' AND list_column IN ('linked_habit_ids', 'completed_habit_ids')
+UNION ALL
+SELECT 'diary_habits', item_id, source_id, 'linked'
+FROM legacy_links WHERE source_table = 'habits' AND list_column = 'linked_diary_page_ids'
+UNION ALL
+SELECT 'diary_goals', source_id, item_id,
+    CASE list_column WHEN 'progressed_goal_ids' THEN 'progressed' ELSE 'linked' END
+FROM legacy_links
+WHERE source_table = 'diary_entries' AND list_column IN ('linked_goal_ids', 'progressed_goal_ids')
+UNION ALL
+SELECT 'diary_goals', item_id, source_id, 'linked'
+FROM legacy_links WHERE source_table = 'goals' AND list_column = 'linked_diary_page_ids'
+UNION ALL
+SELECT 'diary_jobs', source_id, item_id, 'linked'
+FROM legacy_links WHERE source_table = 'diary_entries' AND list_column = 'linked_job_ids'
+UNION ALL
+SELECT 'diary_jobs', item_id, source_id, 'linked'
+FROM legacy_links WHERE source_table = 'job_applications' AND list_column = 'linked_diary_entry_ids'
+UNION ALL
+SELECT 'habit_goals', source_id, item_id, 'linked'
+FROM legacy_links WHERE source_table = 'habits' AND list_column = 'linked_goal_ids'
+UNION ALL
+SELECT 'habit_goals', item_id, source_id, 'linked'
+FROM legacy_links WHERE source_table = 'goals' AND list_column = 'linked_habit_ids'
+UNION ALL
+SELECT 'job_goals', source_id, item_id, 'linked'
+FROM legacy_links WHERE source_table = 'job_applications' AND list_column = 'linked_goal_ids'
+UNION ALL
+SELECT 'job_goals', item_id, source_id, 'linked'
+FROM legacy_links WHERE source_table = 'goals' AND list_column = 'linked_job_ids'
+UNION ALL
+SELECT 'job_habits', source_id, item_id, 'linked'
+FROM legacy_links WHERE source_table = 'job_applications' AND list_column = 'linked_habit_ids'
+UNION ALL
+SELECT 'job_habits', item_id, source_id, 'linked'
+FROM legacy_links WHERE source_table = 'habits' AND list_column = 'linked_job_ids'
+UNION ALL
+SELECT CASE source_table WHEN 'goals' THEN 'goal_dependencies' ELSE 'habit_dependencies' END,
+    CASE WHEN list_column LIKE 'prerequisite_%' OR list_column LIKE 'blocked_by_%' THEN source_id ELSE item_id END,
+    CASE WHEN list_column LIKE 'prerequisite_%' OR list_column LIKE 'blocked_by_%' THEN item_id ELSE source_id END,
+    CASE WHEN list_column LIKE 'blocked_by_%' THEN 'blocker' ELSE 'prerequisite' END
+FROM legacy_links
+WHERE source_table IN ('goals', 'habits')
+  AND (list_column LIKE 'prerequisite_%' OR list_column LIKE 'dependent_%'
+       OR list_column LIKE 'blocked_by_%' OR list_column LIKE 'unlocks_%');
+
+INSERT OR IGNORE INTO diary_habits (link_id, diary_entry_id, habit_id, relation_type, created_at, updated_at)
+SELECT left_id || ':' || right_id || ':' || relation_type, left_id, right_id, relation_type, CAST(strftime('%s', 'now') AS INTEGER), CAST(strftime('%s', 'now') AS INTEGER)
+FROM legacy_relations
+WHERE relation_table = 'diary_habits'
+  AND EXISTS (SELECT 1 FROM diary_entries WHERE diary_entry_id = left_id)
+  AND EXISTS (SELECT 1 FROM habits WHERE habit_id = right_id);
+
+INSERT OR IGNORE INTO diary_goals (link_id, diary_entry_id, goal_id, relation_type, created_at, updated_at)
+SELECT left_id || ':' || right_id || ':' || relation_type, left_id, right_id, relation_type, CAST(strftime('%s', 'now') AS INTEGER), CAST(strftime('%s', 'now') AS INTEGER)
+FROM legacy_relations
+WHERE relation_table = 'diary_goals'
+  AND EXISTS (SELECT 1 FROM diary_entries WHERE diary_entry_id = left_id)
+  AND EXISTS (SELECT 1 FROM goals WHERE goal_id = right_id);
+
+INSERT OR IGNORE INTO diary_jobs (link_id, diary_entry_id, job_application_id, relation_type, created_at, updated_at)
+SELECT left_id || ':' || right_id || ':' || relation_type, left_id, right_id, relation_type, CAST(strftime('%s', 'now') AS INTEGER), CAST(strftime('%s', 'now') AS INTEGER)
+FROM legacy_relations
+WHERE relation_table = 'diary_jobs'
+  AND EXISTS (SELECT 1 FROM diary_entries WHERE diary_entry_id = left_id)
+  AND EXISTS (SELECT 1 FROM job_applications WHERE job_application_id = right_id);
+
+INSERT OR IGNORE INTO habit_goals (link_id, habit_id, goal_id, relation_type, created_at, updated_at)
+SELECT left_id || ':' || right_id || ':' || relation_type, left_id, right_id, relation_type, CAST(strftime('%s', 'now') AS INTEGER), CAST(strftime('%s', 'now') AS INTEGER)
+FROM legacy_relations
+WHERE relation_table = 'habit_goals'
+  AND EXISTS (SELECT 1 FROM habits WHERE habit_id = left_id)
+  AND EXISTS (SELECT 1 FROM goals WHERE goal_id = right_id);
+
+INSERT OR IGNORE INTO job_goals (link_id, job_application_id, goal_id, relation_type, created_at, updated_at)
+SELECT left_id || ':' || right_id || ':' || relation_type, left_id, right_id, relation_type, CAST(strftime('%s', 'now') AS INTEGER), CAST(strftime('%s', 'now') AS INTEGER)
+FROM legacy_relations
+WHERE relation_table = 'job_goals'
+  AND EXISTS (SELECT 1 FROM job_applications WHERE job_application_id = left_id)
+  AND EXISTS (SELECT 1 FROM goals WHERE goal_id = right_id);
+
+INSERT OR IGNORE INTO job_habits (link_id, job_application_id, habit_id, relation_type, created_at, updated_at)
+SELECT left_id || ':' || right_id || ':' || relation_type, left_id, right_id, relation_type, CAST(strftime('%s', 'now') AS INTEGER), CAST(strftime('%s', 'now') AS INTEGER)
+FROM legacy_relations
+WHERE relation_table = 'job_habits'
+  AND EXISTS (SELECT 1 FROM job_applications WHERE job_application_id = left_id)
+  AND EXISTS (SELECT 1 FROM habits WHERE habit_id = right_id);
+
+INSERT OR IGNORE INTO goal_dependencies (link_id, goal_id, depends_on_goal_id, relation_type, created_at, updated_at)
+SELECT left_id || ':' || right_id || ':' || relation_type, left_id, right_id, relation_type, CAST(strftime('%s', 'now') AS INTEGER), CAST(strftime('%s', 'now') AS INTEGER)
+FROM legacy_relations
+WHERE relation_table = 'goal_dependencies' AND left_id != right_id
+  AND EXISTS (SELECT 1 FROM goals WHERE goal_id = left_id)
+  AND EXISTS (SELECT 1 FROM goals WHERE goal_id = right_id);
+
+INSERT OR IGNORE INTO habit_dependencies (link_id, habit_id, depends_on_habit_id, relation_type, created_at, updated_at)
+SELECT left_id || ':' || right_id || ':' || relation_type, left_id, right_id, relation_type, CAST(strftime('%s', 'now') AS INTEGER), CAST(strftime('%s', 'now') AS INTEGER)
+FROM legacy_relations
+WHERE relation_table = 'habit_dependencies' AND left_id != right_id
+  AND EXISTS (SELECT 1 FROM habits WHERE habit_id = left_id)
+  AND EXISTS (SELECT 1 FROM habits WHERE habit_id = right_id);
+
+DROP TABLE legacy_relations;
+DROP TABLE legacy_links;
+
+ALTER TABLE diary_entries DROP COLUMN linked_habit_ids;
+ALTER TABLE diary_entries DROP COLUMN completed_habit_ids;
+ALTER TABLE diary_entries DROP COLUMN linked_goal_ids;
+ALTER TABLE diary_entries DROP COLUMN progressed_goal_ids;
+ALTER TABLE diary_entries DROP COLUMN linked_job_ids;
+
+ALTER TABLE habits DROP COLUMN prerequisite_habit_ids;
+ALTER TABLE habits DROP COLUMN dependent_habit_ids;
+ALTER TABLE habits DROP COLUMN blocked_by_habit_ids;
+ALTER TABLE habits DROP COLUMN unlocks_habit_ids;
+ALTER TABLE habits DROP COLUMN linked_goal_ids;
+ALTER TABLE habits DROP COLUMN linked_diary_page_ids;
+ALTER TABLE habits DROP COLUMN linked_job_ids;
+
+ALTER TABLE goals DROP COLUMN prerequisite_goal_ids;
+ALTER TABLE goals DROP COLUMN dependent_goal_ids;
+ALTER TABLE goals DROP COLUMN blocked_by_goal_ids;
+ALTER TABLE goals DROP COLUMN unlocks_goal_ids;
+ALTER TABLE goals DROP COLUMN linked_habit_ids;
+ALTER TABLE goals DROP COLUMN linked_diary_page_ids;
+ALTER TABLE goals DROP COLUMN linked_job_ids;
+
+ALTER TABLE job_applications DROP COLUMN linked_goal_ids;
+ALTER TABLE job_applications DROP COLUMN linked_habit_ids;
+ALTER TABLE job_applications DROP COLUMN linked_diary_entry_ids;
+
+-- Content hashes covered the dropped columns; they are recomputed on the next unlock.
+UPDATE diary_entries SET content_hash = NULL;
+UPDATE habits SET content_hash = NULL;
+UPDATE goals SET content_hash = NULL;
+UPDATE job_applications SET content_hash = NULL;
@@ -0,0 +1,208 @@
+-- 0013_analytics_cache.sql
+-- Derived analytics move out of the entity rows, so refreshing them no longer rewrites
+-- the user's record or its updated_at. Every row here can be rebuilt from the entity
+-- tables and their history; rows start empty and are computed on first read.
+
+CREATE TABLE diary_analytics_cache (
+    diary_entry_id TEXT PRIMARY KEY NOT NULL,
+    computed_for_date TEXT NOT NULL, -- logical day the figures are for
+    computed_at INTEGER NOT NULL,
+    relation_strength_score REAL NOT NULL DEFAULT 0.0,
+    is_counted_for_streak INTEGER NOT NULL DEFAULT 0,
+    daily_streak_index INTEGER,
+    is_streak_breaker INTEGER NOT NULL DEFAULT 0,
+    yearly_day_index INTEGER,
+    is_filled_day INTEGER NOT NULL DEFAULT 0,
+    filled_day_score REAL NOT NULL DEFAULT 0.0,
+    year_completion_percentage REAL NOT NULL DEFAULT 0.0,
+    rolling_7_day_avg_words REAL NOT NULL DEFAULT 0.0,
+    rolling_30_day_avg_words REAL NOT NULL DEFAULT 0.0,
+    longest_streak_so_far INTEGER NOT NULL DEFAULT 0,
+    current_streak_length INTEGER NOT NULL DEFAULT 0,
+    streak_last_updated_at INTEGER,
+    FOREIGN KEY (diary_entry_id) REFERENCES diary_entries(diary_entry_id) ON DELETE CASCADE
+);
+
+CREATE TABLE habit_analytics_cache (
+    habit_id TEXT PRIMARY KEY NOT NULL,
+    computed_for_date TEXT NOT NULL, -- logical day the figures are for
+    computed_at INTEGER NOT NULL,
+    streak_current INTEGER NOT NULL DEFAULT 0,
+    streak_longest INTEGER NOT NULL DEFAULT 0,
+    streak_best_window INTEGER NOT NULL DEFAULT 0,
+    streak_start_date TEXT,
+    streak_last_updated_at INTEGER,
+    streak_break_date TEXT,
+    checkin_last_value REAL,
+    total_completions INTEGER NOT NULL DEFAULT 0,
+    total_failures INTEGER NOT NULL DEFAULT 0,
+    total_skips INTEGER NOT NULL DEFAULT 0,
+    completion_rate_lifetime REAL NOT NULL DEFAULT 0.0,
+    completion_rate_30d REAL NOT NULL DEFAULT 0.0,
+    completion_rate_90d REAL NOT NULL DEFAULT 0.0,
+    completion_rate_365d REAL NOT NULL DEFAULT 0.0,
+    consistency_index REAL NOT NULL DEFAULT 0.0,
+    variance_score REAL NOT NULL DEFAULT 0.0,
+    momentum_score REAL NOT NULL DEFAULT 0.0,
+    habit_strength_score REAL NOT NULL DEFAULT 0.0,
+    habit_entropy_score REAL NOT NULL DEFAULT 0.0,
+    habit_predictability_score REAL NOT NULL DEFAULT 0.0,
+    gamification_points INTEGER NOT NULL DEFAULT 0,
+    gamification_level INTEGER NOT NULL DEFAULT 1,
+    gamification_xp INTEGER NOT NULL DEFAULT 0,
+    gamification_badges TEXT,
+    dependency_strength_score REAL NOT NULL DEFAULT 0.0,
+    is_on_track INTEGER NOT NULL DEFAULT 1,
+    projected_completion_rate REAL NOT NULL DEFAULT 0.0,
+    projected_streak_length INTEGER NOT NULL DEFAULT 0,
+    habit_health_status TEXT,
+    burnout_risk_score REAL NOT NULL DEFAULT 0.0,
+    relapse_risk_score REAL NOT NULL DEFAULT 0.0,
+    success_probability REAL NOT NULL DEFAULT 0.0,
+    last_completed_at INTEGER,
+    last_failed_at INTEGER,
+    last_skipped_at INTEGER,
+    FOREIGN KEY (habit_id) REFERENCES habits(habit_id) ON DELETE CASCADE
+);
+
+CREATE TABLE goal_analytics_cache (
+    goal_id TEXT PRIMARY KEY NOT NULL,
+    computed_for_date TEXT NOT NULL, -- logical day the figures are for
+    computed_at INTEGER NOT NULL,
+    goal_days_remaining INTEGER,
+    goal_days_elapsed INTEGER,
+    goal_overdue_days INTEGER,
+    progress_delta REAL NOT NULL DEFAULT 0.0,
+    progress_velocity REAL NOT NULL DEFAULT 0.0,
+    progress_acceleration REAL NOT NULL DEFAULT 0.0,
+    progress_is_on_track INTEGER NOT NULL DEFAULT 1,
+    progress_confidence_interval REAL,
+    dependency_strength_score REAL NOT NULL DEFAULT 0.0,
+    habit_progress_aggregate REAL NOT NULL DEFAULT 0.0,
+    task_progress_aggregate REAL NOT NULL DEFAULT 0.0,
+    completion_probability REAL NOT NULL DEFAULT 0.0,
+    failure_probability REAL NOT NULL DEFAULT 0.0,
+    burnout_risk_score REAL NOT NULL DEFAULT 0.0,
+    confidence_trend REAL NOT NULL DEFAULT 0.0,
+    consistency_index REAL NOT NULL DEFAULT 0.0,
+    momentum_score REAL NOT NULL DEFAULT 0.0,
+    volatility_score REAL NOT NULL DEFAULT 0.0,
+    effort_to_reward_ratio REAL NOT NULL DEFAULT 0.0,
+    expected_completion_date TEXT,
+    deviation_from_plan REAL NOT NULL DEFAULT 0.0,
+    goal_health_status TEXT,
+    risk_level TEXT,
+    risk_factors TEXT,
+    reflection_sentiment_score REAL NOT NULL DEFAULT 0.0,
+    FOREIGN KEY (goal_id) REFERENCES goals(goal_id) ON DELETE CASCADE
+);
+
+CREATE TABLE job_analytics_cache (
+    job_application_id TEXT PRIMARY KEY NOT NULL,
+    computed_for_date TEXT NOT NULL, -- logical day the figures are for
+    computed_at INTEGER NOT NULL,
+    days_since_applied INTEGER,
+    days_to_first_response INTEGER,
+    response_received_flag INTEGER NOT NULL DEFAULT 0,
+    interview_conversion_rate REAL NOT NULL DEFAULT 0.0,
+    offer_conversion_rate REAL NOT NULL DEFAULT 0.0,
+    rejection_rate REAL NOT NULL DEFAULT 0.0,
+    ghost_rate REAL NOT NULL DEFAULT 0.0,
+    avg_response_time REAL NOT NULL DEFAULT 0.0,
+    pipeline_velocity REAL NOT NULL DEFAULT 0.0,
+    application_success_probability REAL NOT NULL DEFAULT 0.0,
+    job_pipeline_health_status TEXT,
+    job_related_habits_completed INTEGER NOT NULL DEFAULT 0,
+    opportunity_score REAL NOT NULL DEFAULT 0.0,
+    FOREIGN KEY (job_application_id) REFERENCES job_applications(job_application_id) ON DELETE CASCADE
+);
+
+ALTER TABLE diary_entries DROP COLUMN relation_strength_score;
+ALTER TABLE diary_entries DROP COLUMN is_counted_for_streak;
+ALTER TABLE diary_entries DROP COLUMN daily_streak_index;
+ALTER TABLE diary_entries DROP COLUMN is_streak_breaker;
+ALTER TABLE diary_entries DROP COLUMN yearly_day_index;
+ALTER TABLE diary_entries DROP COLUMN is_filled_day;
+ALTER TABLE diary_entries DROP COLUMN filled_day_score;
+ALTER TABLE diary_entries DROP COLUMN year_completion_percentage;
+ALTER TABLE diary_entries DROP COLUMN rolling_7_day_avg_words;
+ALTER TABLE diary_entries DROP COLUMN rolling_30_day_avg_words;
+ALTER TABLE diary_entries DROP COLUMN longest_streak_so_far;
+ALTER TABLE diary_entries DROP COLUMN current_streak_length;
+ALTER TABLE diary_entries DROP COLUMN streak_last_updated_at;
+
+ALTER TABLE habits DROP COLUMN streak_current;
+ALTER TABLE habits DROP COLUMN streak_longest;
+ALTER TABLE habits DROP COLUMN streak_best_window;
+ALTER TABLE habits DROP COLUMN streak_start_date;
+ALTER TABLE habits DROP COLUMN streak_last_updated_at;
+ALTER TABLE habits DROP COLUMN streak_break_date;
+ALTER TABLE habits DROP COLUMN checkin_last_value;
+ALTER TABLE habits DROP COLUMN total_completions;
+ALTER TABLE habits DROP COLUMN total_failures;
+ALTER TABLE habits DROP COLUMN total_skips;
+ALTER TABLE habits DROP COLUMN completion_rate_lifetime;
+ALTER TABLE habits DROP COLUMN completion_rate_30d;
+ALTER TABLE habits DROP COLUMN completion_rate_90d;
+ALTER TABLE habits DROP COLUMN completion_rate_365d;
+ALTER TABLE habits DROP COLUMN consistency_index;
+ALTER TABLE habits DROP COLUMN variance_score;
+ALTER TABLE habits DROP COLUMN momentum_score;
+ALTER TABLE habits DROP COLUMN habit_strength_score;
+ALTER TABLE habits DROP COLUMN habit_entropy_score;
+ALTER TABLE habits DROP COLUMN habit_predictability_score;
+ALTER TABLE habits DROP COLUMN gamification_points;
+ALTER TABLE habits DROP COLUMN gamification_level;
+ALTER TABLE habits DROP COLUMN gamification_xp;
+ALTER TABLE habits DROP COLUMN gamification_badges;
+ALTER TABLE habits DROP COLUMN dependency_strength_score;
+ALTER TABLE habits DROP COLUMN is_on_track;
+ALTER TABLE habits DROP COLUMN projected_completion_rate;
+ALTER TABLE habits DROP COLUMN projected_streak_length;
+ALTER TABLE habits DROP COLUMN habit_health_status;
+ALTER TABLE habits DROP COLUMN burnout_risk_score;
+ALTER TABLE habits DROP COLUMN relapse_risk_score;
+ALTER TABLE habits DROP COLUMN success_probability;
+ALTER TABLE habits DROP COLUMN last_completed_at;
+ALTER TABLE habits DROP COLUMN last_failed_at;
+ALTER TABLE habits DROP COLUMN last_skipped_at;
+
+ALTER TABLE goals DROP COLUMN goal_days_remaining;
+ALTER TABLE goals DROP COLUMN goal_days_elapsed;
+ALTER TABLE goals DROP COLUMN goal_overdue_days;
+ALTER TABLE goals DROP COLUMN progress_delta;
+ALTER TABLE goals DROP COLUMN progress_velocity;
+ALTER TABLE goals DROP COLUMN progress_acceleration;
+ALTER TABLE goals DROP COLUMN progress_is_on_track;
+ALTER TABLE goals DROP COLUMN progress_confidence_interval;
+ALTER TABLE goals DROP COLUMN dependency_strength_score;
+ALTER TABLE goals DROP COLUMN habit_progress_aggregate;
+ALTER TABLE goals DROP COLUMN task_progress_aggregate;
+ALTER TABLE goals DROP COLUMN completion_probability;
+ALTER TABLE goals DROP COLUMN failure_probability;
+ALTER TABLE goals DROP COLUMN burnout_risk_score;
+ALTER TABLE goals DROP COLUMN confidence_trend;
+ALTER TABLE goals DROP COLUMN consistency_index;
+ALTER TABLE goals DROP COLUMN momentum_score;
+ALTER TABLE goals DROP COLUMN volatility_score;
+ALTER TABLE goals DROP COLUMN effort_to_reward_ratio;
+ALTER TABLE goals DROP COLUMN expected_completion_date;
+ALTER TABLE goals DROP COLUMN deviation_from_plan;
+ALTER TABLE goals DROP COLUMN goal_health_status;
+ALTER TABLE goals DROP COLUMN risk_level;
+ALTER TABLE goals DROP COLUMN risk_factors;
+ALTER TABLE goals DROP COLUMN reflection_sentiment_score;
+
+ALTER TABLE job_applications DROP COLUMN days_since_applied;
+ALTER TABLE job_applications DROP COLUMN days_to_first_response;
+ALTER TABLE job_applications DROP COLUMN response_received_flag;
+ALTER TABLE job_applications DROP COLUMN interview_conversion_rate;
+ALTER TABLE job_applications DROP COLUMN offer_conversion_rate;
+ALTER TABLE job_applications DROP COLUMN rejection_rate;
+ALTER TABLE job_applications DROP COLUMN ghost_rate;
+ALTER TABLE job_applications DROP COLUMN avg_response_time;
+ALTER TABLE job_applications DROP COLUMN pipeline_velocity;
+ALTER TABLE job_applications DROP COLUMN application_success_probability;
+ALTER TABLE job_applications DROP COLUMN job_pipeline_health_status;
+ALTER TABLE job_applications DROP COLUMN job_related_habits_completed;
+ALTER TABLE job_applications DROP COLUMN opportunity_score;
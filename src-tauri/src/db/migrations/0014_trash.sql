@@ -0,0 +1,32 @@
+-- 0014_trash.sql
+-- Habits, goals and jobs get the soft-delete columns diary entries already had.
+ALTER TABLE habits ADD COLUMN is_deleted INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE habits ADD COLUMN deleted_at INTEGER;
+ALTER TABLE habits ADD COLUMN deleted_reason TEXT;
+
+ALTER TABLE goals ADD COLUMN is_deleted INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE goals ADD COLUMN deleted_at INTEGER;
+ALTER TABLE goals ADD COLUMN deleted_reason TEXT;
+
+ALTER TABLE job_applications ADD COLUMN is_deleted INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE job_applications ADD COLUMN deleted_at INTEGER;
+ALTER TABLE job_applications ADD COLUMN deleted_reason TEXT;
+
+CREATE INDEX idx_diary_entries_trash ON diary_entries(deleted_at) WHERE is_deleted = 1;
+CREATE INDEX idx_habits_trash ON habits(deleted_at) WHERE is_deleted = 1;
+CREATE INDEX idx_goals_trash ON goals(deleted_at) WHERE is_deleted = 1;
+CREATE INDEX idx_job_applications_trash ON job_applications(deleted_at) WHERE is_deleted = 1;
+
+-- Records removed for good. Sync skips incoming changes for them, so a device that has
+-- not caught up yet can't bring a purged record back.
+CREATE TABLE purged_records (
+    table_name TEXT NOT NULL,
+    record_id TEXT NOT NULL,
+    purged_at INTEGER NOT NULL,
+    PRIMARY KEY (table_name, record_id)
+);
+
+-- The new columns are part of each record's content hash; recomputed on the next unlock.
+UPDATE habits SET content_hash = NULL;
+UPDATE goals SET content_hash = NULL;
+UPDATE job_applications SET content_hash = NULL;
@@ -0,0 +1,46 @@
+-- 0015_attachments.sql
+-- Files attached to diary pages, goals and jobs. An attachment's ID is the SHA-256 of
+-- its contents, so the same file added twice, or on two devices, is one row and one
+-- encrypted blob in the store next to the database.
+
+CREATE TABLE attachments (
+    attachment_id TEXT PRIMARY KEY NOT NULL, -- hex SHA-256 of the plaintext
+    byte_size INTEGER NOT NULL,
+    mime_type TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+-- Exactly one of the record columns is set. Like the relation tables, the link ID is
+-- built from its ends and role, and unlinking sets unlinked_at so the removal syncs.
+CREATE TABLE attachment_links (
+    link_id TEXT PRIMARY KEY NOT NULL,
+    attachment_id TEXT NOT NULL,
+    diary_entry_id TEXT,
+    goal_id TEXT,
+    job_application_id TEXT,
+    role TEXT NOT NULL, -- attachment, resume, cover_letter
+    file_name TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    unlinked_at INTEGER,
+    FOREIGN KEY (attachment_id) REFERENCES attachments(attachment_id) ON DELETE CASCADE,
+    FOREIGN KEY (diary_entry_id) REFERENCES diary_entries(diary_entry_id) ON DELETE CASCADE,
+    FOREIGN KEY (goal_id) REFERENCES goals(goal_id) ON DELETE CASCADE,
+    FOREIGN KEY (job_application_id) REFERENCES job_applications(job_application_id) ON DELETE CASCADE,
+    CHECK ((diary_entry_id IS NOT NULL) + (goal_id IS NOT NULL) + (job_application_id IS NOT NULL) = 1)
+);
+
+CREATE INDEX idx_attachment_links_attachment_id ON attachment_links(attachment_id);
+CREATE INDEX idx_attachment_links_diary_entry_id ON attachment_links(diary_entry_id) WHERE diary_entry_id IS NOT NULL;
+CREATE INDEX idx_attachment_links_goal_id ON attachment_links(goal_id) WHERE goal_id IS NOT NULL;
+CREATE INDEX idx_attachment_links_job_application_id ON attachment_links(job_application_id) WHERE job_application_id IS NOT NULL;
+
+-- These pointed at a file store that never existed, so there is nothing to carry over.
+ALTER TABLE diary_entries DROP COLUMN linked_attachment_ids;
+ALTER TABLE job_applications DROP COLUMN resume_id;
+ALTER TABLE job_applications DROP COLUMN cover_letter_id;
+
+-- Content hashes covered the dropped columns; they are recomputed on the next unlock.
+UPDATE diary_entries SET content_hash = NULL;
+UPDATE job_applications SET content_hash = NULL;
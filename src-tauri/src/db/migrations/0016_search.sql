@@ -0,0 +1,228 @@
+-- 0016_search.sql
+-- Full-text search over every domain in one FTS5 table, so results from different
+-- domains rank against each other. Record IDs are text, so search_documents hands out
+-- the integer rowids the index is keyed on. Triggers keep the index in step with the
+-- records; deleted ones are taken out and put back when restored.
+
+CREATE TABLE search_documents (
+    doc_id INTEGER PRIMARY KEY,
+    entity_kind TEXT NOT NULL, -- diary, habit, goal, job
+    entity_id TEXT NOT NULL,
+    UNIQUE(entity_kind, entity_id)
+);
+
+CREATE VIRTUAL TABLE search_index USING fts5(
+    title,
+    body,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+-- Diary entries
+
+CREATE TRIGGER diary_entries_search_insert AFTER INSERT ON diary_entries
+WHEN new.is_deleted = 0
+BEGIN
+    INSERT OR IGNORE INTO search_documents (entity_kind, entity_id)
+    VALUES ('diary', new.diary_entry_id);
+    INSERT INTO search_index (rowid, title, body)
+    SELECT doc_id, new.title_plaintext, new.content_plaintext FROM search_documents
+    WHERE entity_kind = 'diary' AND entity_id = new.diary_entry_id;
+END;
+
+CREATE TRIGGER diary_entries_search_update
+AFTER UPDATE OF title_plaintext, content_plaintext, is_deleted ON diary_entries
+BEGIN
+    DELETE FROM search_index WHERE rowid = (
+        SELECT doc_id FROM search_documents
+        WHERE entity_kind = 'diary' AND entity_id = old.diary_entry_id
+    );
+    INSERT OR IGNORE INTO search_documents (entity_kind, entity_id)
+    SELECT 'diary', new.diary_entry_id WHERE new.is_deleted = 0;
+    INSERT INTO search_index (rowid, title, body)
+    SELECT doc_id, new.title_plaintext, new.content_plaintext FROM search_documents
+    WHERE entity_kind = 'diary' AND entity_id = new.diary_entry_id AND new.is_deleted = 0;
+END;
+
+CREATE TRIGGER diary_entries_search_delete AFTER DELETE ON diary_entries
+BEGIN
+    DELETE FROM search_index WHERE rowid = (
+        SELECT doc_id FROM search_documents
+        WHERE entity_kind = 'diary' AND entity_id = old.diary_entry_id
+    );
+    DELETE FROM search_documents
+    WHERE entity_kind = 'diary' AND entity_id = old.diary_entry_id;
+END;
+
+-- Habits
+
+CREATE TRIGGER habits_search_insert AFTER INSERT ON habits
+WHEN new.is_deleted = 0
+BEGIN
+    INSERT OR IGNORE INTO search_documents (entity_kind, entity_id)
+    VALUES ('habit', new.habit_id);
+    INSERT INTO search_index (rowid, title, body)
+    SELECT doc_id, new.habit_name, new.habit_description FROM search_documents
+    WHERE entity_kind = 'habit' AND entity_id = new.habit_id;
+END;
+
+CREATE TRIGGER habits_search_update
+AFTER UPDATE OF habit_name, habit_description, is_deleted ON habits
+BEGIN
+    DELETE FROM search_index WHERE rowid = (
+        SELECT doc_id FROM search_documents
+        WHERE entity_kind = 'habit' AND entity_id = old.habit_id
+    );
+    INSERT OR IGNORE INTO search_documents (entity_kind, entity_id)
+    SELECT 'habit', new.habit_id WHERE new.is_deleted = 0;
+    INSERT INTO search_index (rowid, title, body)
+    SELECT doc_id, new.habit_name, new.habit_description FROM search_documents
+    WHERE entity_kind = 'habit' AND entity_id = new.habit_id AND new.is_deleted = 0;
+END;
+
+CREATE TRIGGER habits_search_delete AFTER DELETE ON habits
+BEGIN
+    DELETE FROM search_index WHERE rowid = (
+        SELECT doc_id FROM search_documents
+        WHERE entity_kind = 'habit' AND entity_id = old.habit_id
+    );
+    DELETE FROM search_documents WHERE entity_kind = 'habit' AND entity_id = old.habit_id;
+END;
+
+-- Goals: the description and reflections make up the body.
+
+CREATE TRIGGER goals_search_insert AFTER INSERT ON goals
+WHEN new.is_deleted = 0
+BEGIN
+    INSERT OR IGNORE INTO search_documents (entity_kind, entity_id)
+    VALUES ('goal', new.goal_id);
+    INSERT INTO search_index (rowid, title, body)
+    SELECT doc_id, new.goal_title,
+        COALESCE(new.goal_description, '')
+            || char(10) || COALESCE(new.last_reflection_text, '')
+            || char(10) || COALESCE(new.reflection_history, '')
+    FROM search_documents
+    WHERE entity_kind = 'goal' AND entity_id = new.goal_id;
+END;
+
+CREATE TRIGGER goals_search_update
+AFTER UPDATE OF goal_title, goal_description, last_reflection_text, reflection_history,
+    is_deleted ON goals
+BEGIN
+    DELETE FROM search_index WHERE rowid = (
+        SELECT doc_id FROM search_documents
+        WHERE entity_kind = 'goal' AND entity_id = old.goal_id
+    );
+    INSERT OR IGNORE INTO search_documents (entity_kind, entity_id)
+    SELECT 'goal', new.goal_id WHERE new.is_deleted = 0;
+    INSERT INTO search_index (rowid, title, body)
+    SELECT doc_id, new.goal_title,
+        COALESCE(new.goal_description, '')
+            || char(10) || COALESCE(new.last_reflection_text, '')
+            || char(10) || COALESCE(new.reflection_history, '')
+    FROM search_documents
+    WHERE entity_kind = 'goal' AND entity_id = new.goal_id AND new.is_deleted = 0;
+END;
+
+CREATE TRIGGER goals_search_delete AFTER DELETE ON goals
+BEGIN
+    DELETE FROM search_index WHERE rowid = (
+        SELECT doc_id FROM search_documents
+        WHERE entity_kind = 'goal' AND entity_id = old.goal_id
+    );
+    DELETE FROM search_documents WHERE entity_kind = 'goal' AND entity_id = old.goal_id;
+END;
+
+-- Job applications: the company goes in the title, so a company name ranks like a
+-- job title; the description and every notes field make up the body.
+
+CREATE TRIGGER job_applications_search_insert AFTER INSERT ON job_applications
+WHEN new.is_deleted = 0
+BEGIN
+    INSERT OR IGNORE INTO search_documents (entity_kind, entity_id)
+    VALUES ('job', new.job_application_id);
+    INSERT INTO search_index (rowid, title, body)
+    SELECT doc_id, new.job_title || ' ' || new.company_name,
+        COALESCE(new.job_description_clean, new.job_description_raw, '')
+            || char(10) || COALESCE(new.company_notes, '')
+            || char(10) || COALESCE(new.compensation_notes, '')
+            || char(10) || COALESCE(new.contact_notes, '')
+            || char(10) || COALESCE(new.interview_feedback_notes, '')
+            || char(10) || COALESCE(new.negotiation_notes, '')
+    FROM search_documents
+    WHERE entity_kind = 'job' AND entity_id = new.job_application_id;
+END;
+
+CREATE TRIGGER job_applications_search_update
+AFTER UPDATE OF job_title, company_name, job_description_clean, job_description_raw,
+    company_notes, compensation_notes, contact_notes, interview_feedback_notes,
+    negotiation_notes, is_deleted ON job_applications
+BEGIN
+    DELETE FROM search_index WHERE rowid = (
+        SELECT doc_id FROM search_documents
+        WHERE entity_kind = 'job' AND entity_id = old.job_application_id
+    );
+    INSERT OR IGNORE INTO search_documents (entity_kind, entity_id)
+    SELECT 'job', new.job_application_id WHERE new.is_deleted = 0;
+    INSERT INTO search_index (rowid, title, body)
+    SELECT doc_id, new.job_title || ' ' || new.company_name,
+        COALESCE(new.job_description_clean, new.job_description_raw, '')
+            || char(10) || COALESCE(new.company_notes, '')
+            || char(10) || COALESCE(new.compensation_notes, '')
+            || char(10) || COALESCE(new.contact_notes, '')
+            || char(10) || COALESCE(new.interview_feedback_notes, '')
+            || char(10) || COALESCE(new.negotiation_notes, '')
+    FROM search_documents
+    WHERE entity_kind = 'job' AND entity_id = new.job_application_id AND new.is_deleted = 0;
+END;
+
+CREATE TRIGGER job_applications_search_delete AFTER DELETE ON job_applications
+BEGIN
+    DELETE FROM search_index WHERE rowid = (
+        SELECT doc_id FROM search_documents
+        WHERE entity_kind = 'job' AND entity_id = old.job_application_id
+    );
+    DELETE FROM search_documents
+    WHERE entity_kind = 'job' AND entity_id = old.job_application_id;
+END;
+
+-- Existing records. The diary plaintext columns were never filled, so this fills them
+-- from every "text" value in the editor's block JSON, which indexes the entries through
+-- the update trigger; the next save writes the exact text.
+UPDATE diary_entries SET
+    title_plaintext = NULLIF(trim(title), ''),
+    content_plaintext = (
+        SELECT group_concat(value, ' ') FROM json_tree(diary_entries.content_json)
+        WHERE key = 'text' AND type = 'text'
+    )
+WHERE json_valid(content_json);
+
+UPDATE diary_entries SET title_plaintext = NULLIF(trim(title), '')
+WHERE NOT json_valid(content_json);
+
+INSERT INTO search_documents (entity_kind, entity_id)
+SELECT 'habit', habit_id FROM habits WHERE is_deleted = 0;
+INSERT INTO search_index (rowid, title, body)
+SELECT doc.doc_id, habit_name, habit_description
+FROM habits JOIN search_documents doc ON doc.entity_kind = 'habit' AND doc.entity_id = habit_id;
+
+INSERT INTO search_documents (entity_kind, entity_id)
+SELECT 'goal', goal_id FROM goals WHERE is_deleted = 0;
+INSERT INTO search_index (rowid, title, body)
+SELECT doc.doc_id, goal_title,
+    COALESCE(goal_description, '')
+        || char(10) || COALESCE(last_reflection_text, '')
+        || char(10) || COALESCE(reflection_history, '')
+FROM goals JOIN search_documents doc ON doc.entity_kind = 'goal' AND doc.entity_id = goal_id;
+
+INSERT INTO search_documents (entity_kind, entity_id)
+SELECT 'job', job_application_id FROM job_applications WHERE is_deleted = 0;
+INSERT INTO search_index (rowid, title, body)
+SELECT doc.doc_id, job_title || ' ' || company_name,
+    COALESCE(job_description_clean, job_description_raw, '')
+        || char(10) || COALESCE(company_notes, '')
+        || char(10) || COALESCE(compensation_notes, '')
+        || char(10) || COALESCE(contact_notes, '')
+        || char(10) || COALESCE(interview_feedback_notes, '')
+        || char(10) || COALESCE(negotiation_notes, '')
+FROM job_applications
+JOIN search_documents doc ON doc.entity_kind = 'job' AND doc.entity_id = job_application_id;
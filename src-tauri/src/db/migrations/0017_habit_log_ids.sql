@@ -0,0 +1,10 @@
+-- 0017_habit_log_ids.sql
+-- A habit has one log per day, so a log's ID is now its habit and day. Logs made on
+-- two devices for the same day then sync as one record instead of the second one being
+-- kept out by UNIQUE(habit_id, log_date). Forgetting their sync state makes every log
+-- go out once more under its new ID.
+DELETE FROM sync_shadow WHERE table_name = 'habit_logs';
+DELETE FROM sync_field_clocks WHERE table_name = 'habit_logs';
+
+UPDATE habit_logs SET log_id = habit_id || ':' || log_date
+WHERE log_id != habit_id || ':' || log_date;
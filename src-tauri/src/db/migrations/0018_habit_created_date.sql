@@ -0,0 +1,8 @@
+-- 0018_habit_created_date.sql
+-- The logical day a habit was added, as goals and jobs already have, for search to
+-- date it by. Which day an existing habit was added on depends on the configured
+-- timezone and day-start hour, so those are filled in on unlock rather than here.
+ALTER TABLE habits ADD COLUMN habit_created_date TEXT;
+
+-- Habit content hashes now cover the new column.
+UPDATE habits SET content_hash = NULL;
@@ -1,2 +1,13 @@
-﻿pub mod connection;
+﻿pub mod archive;
+pub mod attachments;
+pub mod backup;
+pub mod cipher;
+pub mod connection;
 pub mod encryption;
+pub mod integrity;
+pub mod relations;
+pub mod search;
+pub mod slugs;
+pub mod sync;
+pub mod trash;
+pub mod vault;
@@ -0,0 +1,411 @@
+use crate::app::context::RequestContext;
+use crate::app::error::{AppError, FieldError};
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Diary,
+    Habit,
+    Goal,
+    Job,
+}
+
+impl EntityKind {
+    pub fn table(self) -> &'static str {
+        match self {
+            EntityKind::Diary => "diary_entries",
+            EntityKind::Habit => "habits",
+            EntityKind::Goal => "goals",
+            EntityKind::Job => "job_applications",
+        }
+    }
+
+    pub fn id_column(self) -> &'static str {
+        match self {
+            EntityKind::Diary => "diary_entry_id",
+            EntityKind::Habit => "habit_id",
+            EntityKind::Goal => "goal_id",
+            EntityKind::Job => "job_application_id",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EntityKind::Diary => "Diary entry",
+            EntityKind::Habit => "Habit",
+            EntityKind::Goal => "Goal",
+            EntityKind::Job => "Job application",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntityRef {
+    pub kind: EntityKind,
+    pub id: String,
+}
+
+// A join table between two kinds of record. Each pair of kinds has one table, so a
+// link reads the same from both ends. Dependency tables join a kind to itself, and
+// there the order matters: `left` depends on `right`.
+struct RelationSpec {
+    table: &'static str,
+    left: EntityKind,
+    left_column: &'static str,
+    right: EntityKind,
+    right_column: &'static str,
+    // The first one is used when a link is made without a type.
+    relation_types: &'static [&'static str],
+}
+
+const RELATIONS: &[RelationSpec] = &[
+    RelationSpec {
+        table: "diary_habits",
+        left: EntityKind::Diary,
+        left_column: "diary_entry_id",
+        right: EntityKind::Habit,
+        right_column: "habit_id",
+        relation_types: &["linked", "completed"],
+    },
+    RelationSpec {
+        table: "diary_goals",
+        left: EntityKind::Diary,
+        left_column: "diary_entry_id",
+        right: EntityKind::Goal,
+        right_column: "goal_id",
+        relation_types: &["linked", "progressed"],
+    },
+    RelationSpec {
+        table: "diary_jobs",
+        left: EntityKind::Diary,
+        left_column: "diary_entry_id",
+        right: EntityKind::Job,
+        right_column: "job_application_id",
+        relation_types: &["linked"],
+    },
+    RelationSpec {
+        table: "habit_goals",
+        left: EntityKind::Habit,
+        left_column: "habit_id",
+        right: EntityKind::Goal,
+        right_column: "goal_id",
+        relation_types: &["linked"],
+    },
+    RelationSpec {
+        table: "job_goals",
+        left: EntityKind::Job,
+        left_column: "job_application_id",
+        right: EntityKind::Goal,
+        right_column: "goal_id",
+        relation_types: &["linked"],
+    },
+    RelationSpec {
+        table: "job_habits",
+        left: EntityKind::Job,
+        left_column: "job_application_id",
+        right: EntityKind::Habit,
+        right_column: "habit_id",
+        relation_types: &["linked"],
+    },
+    RelationSpec {
+        table: "goal_dependencies",
+        left: EntityKind::Goal,
+        left_column: "goal_id",
+        right: EntityKind::Goal,
+        right_column: "depends_on_goal_id",
+        relation_types: &["prerequisite", "blocker"],
+    },
+    RelationSpec {
+        table: "habit_dependencies",
+        left: EntityKind::Habit,
+        left_column: "habit_id",
+        right: EntityKind::Habit,
+        right_column: "depends_on_habit_id",
+        relation_types: &["prerequisite", "blocker"],
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkDirection {
+    Outgoing, // the record asked about is the table's left side, e.g. the dependent goal
+    Incoming,
+}
+
+// One link as seen from a record: the record at the other end and how they relate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityLink {
+    pub link_id: String,
+    pub relation_type: String,
+    pub direction: LinkDirection,
+    pub entity: EntityRef,
+    pub created_at: i64,
+}
+
+// Links `from` to `to`, or restores the link if it was removed. Linking twice is a
+// no-op. A dependency may not close a cycle.
+pub async fn link_entities(
+    pool: &SqlitePool,
+    from: &EntityRef,
+    to: &EntityRef,
+    relation_type: Option<&str>,
+    ctx: &RequestContext,
+) -> Result<EntityLink, AppError> {
+    let (spec, swapped) = relation_between(from.kind, to.kind)?;
+    let relation_type = checked_relation_type(spec, relation_type)?;
+    if from == to {
+        return Err(AppError::Validation(
+            "A record can't be linked to itself.".to_string(),
+        ));
+    }
+    let (left, right) = if swapped { (to, from) } else { (from, to) };
+    let now = ctx.timestamp();
+
+    let mut tx = pool.begin().await?;
+    ensure_exists(&mut tx, from).await?;
+    ensure_exists(&mut tx, to).await?;
+    if spec.left == spec.right && depends_on(&mut tx, spec, &right.id, &left.id).await? {
+        return Err(AppError::Validation(
+            "That dependency would make a cycle.".to_string(),
+        ));
+    }
+
+    sqlx::query(&format!(
+        "INSERT INTO {table} (link_id, {left}, {right}, relation_type, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?)
+         ON CONFLICT({left}, {right}, relation_type) DO UPDATE SET
+            unlinked_at = NULL, updated_at = excluded.updated_at
+         WHERE unlinked_at IS NOT NULL",
+        table = spec.table,
+        left = spec.left_column,
+        right = spec.right_column,
+    ))
+    .bind(link_id(&left.id, &right.id, relation_type))
+    .bind(&left.id)
+    .bind(&right.id)
+    .bind(relation_type)
+    .bind(now)
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    let (link_id, created_at): (String, i64) = sqlx::query_as(&format!(
+        "SELECT link_id, created_at FROM {} WHERE {} = ? AND {} = ? AND relation_type = ?",
+        spec.table, spec.left_column, spec.right_column
+    ))
+    .bind(&left.id)
+    .bind(&right.id)
+    .bind(relation_type)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(EntityLink {
+        link_id,
+        relation_type: relation_type.to_string(),
+        direction: if swapped {
+            LinkDirection::Incoming
+        } else {
+            LinkDirection::Outgoing
+        },
+        entity: to.clone(),
+        created_at,
+    })
+}
+
+// Removes the link between `from` and `to`, or every link between them when no type
+// is given. The row is kept with `unlinked_at` set, so the removal syncs.
+pub async fn unlink_entities(
+    pool: &SqlitePool,
+    from: &EntityRef,
+    to: &EntityRef,
+    relation_type: Option<&str>,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    let (spec, swapped) = relation_between(from.kind, to.kind)?;
+    let relation_type = relation_type
+        .map(|relation_type| checked_relation_type(spec, Some(relation_type)))
+        .transpose()?;
+    let (left, right) = if swapped { (to, from) } else { (from, to) };
+    let now = ctx.timestamp();
+
+    let result = sqlx::query(&format!(
+        "UPDATE {} SET unlinked_at = ?1, updated_at = ?1
+         WHERE {} = ?2 AND {} = ?3 AND (?4 IS NULL OR relation_type = ?4)
+           AND unlinked_at IS NULL",
+        spec.table, spec.left_column, spec.right_column
+    ))
+    .bind(now)
+    .bind(&left.id)
+    .bind(&right.id)
+    .bind(relation_type)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found(
+            "Link",
+            format!("{}:{}", left.id, right.id),
+        ));
+    }
+    Ok(())
+}
+
+// Every live link of `entity`, optionally only those to records of one kind. Links to
+// records in the trash are left out until they are restored.
+pub async fn list_links(
+    pool: &SqlitePool,
+    entity: &EntityRef,
+    kind: Option<EntityKind>,
+) -> Result<Vec<EntityLink>, AppError> {
+    ensure_exists(&mut *pool.acquire().await?, entity).await?;
+
+    let mut links = Vec::new();
+    for spec in RELATIONS {
+        let sides = [
+            (
+                spec.left,
+                spec.left_column,
+                spec.right,
+                spec.right_column,
+                LinkDirection::Outgoing,
+            ),
+            (
+                spec.right,
+                spec.right_column,
+                spec.left,
+                spec.left_column,
+                LinkDirection::Incoming,
+            ),
+        ];
+        for (own_kind, own_column, other_kind, other_column, direction) in sides {
+            if own_kind != entity.kind || kind.is_some_and(|kind| kind != other_kind) {
+                continue;
+            }
+            let rows: Vec<(String, String, String, i64)> = sqlx::query_as(&format!(
+                "SELECT link_id, {other}, relation_type, created_at FROM {table}
+                 WHERE {own} = ? AND unlinked_at IS NULL
+                   AND {other} IN (SELECT {other_id} FROM {other_table} WHERE is_deleted = 0)",
+                other = other_column,
+                table = spec.table,
+                own = own_column,
+                other_id = other_kind.id_column(),
+                other_table = other_kind.table(),
+            ))
+            .bind(&entity.id)
+            .fetch_all(pool)
+            .await?;
+
+            links.extend(
+                rows.into_iter().map(
+                    |(link_id, other_id, relation_type, created_at)| EntityLink {
+                        link_id,
+                        relation_type,
+                        direction,
+                        entity: EntityRef {
+                            kind: other_kind,
+                            id: other_id,
+                        },
+                        created_at,
+                    },
+                ),
+            );
+        }
+    }
+
+    links.sort_by_key(|link| link.created_at);
+    Ok(links)
+}
+
+// Built from the link's ends, so the same link made on two devices is one record.
+fn link_id(left_id: &str, right_id: &str, relation_type: &str) -> String {
+    format!("{}:{}:{}", left_id, right_id, relation_type)
+}
+
+// The join table for two kinds, and whether `from` sits on its right side.
+fn relation_between(
+    from: EntityKind,
+    to: EntityKind,
+) -> Result<(&'static RelationSpec, bool), AppError> {
+    RELATIONS
+        .iter()
+        .find_map(|spec| {
+            if (spec.left, spec.right) == (from, to) {
+                Some((spec, false))
+            } else if (spec.left, spec.right) == (to, from) {
+                Some((spec, true))
+            } else {
+                None
+            }
+        })
+        .ok_or_else(|| {
+            AppError::Validation(format!(
+                "A {} can't be linked to a {}.",
+                from.label().to_lowercase(),
+                to.label().to_lowercase()
+            ))
+        })
+}
+
+fn checked_relation_type<'a>(
+    spec: &RelationSpec,
+    relation_type: Option<&'a str>,
+) -> Result<&'a str, AppError> {
+    match relation_type {
+        None => Ok(spec.relation_types[0]),
+        Some(relation_type) if spec.relation_types.contains(&relation_type) => Ok(relation_type),
+        Some(_) => Err(AppError::InvalidFields(vec![FieldError {
+            field: "relation_type".to_string(),
+            message: format!("Expected one of: {}.", spec.relation_types.join(", ")),
+        }])),
+    }
+}
+
+pub async fn ensure_exists(
+    conn: &mut SqliteConnection,
+    entity: &EntityRef,
+) -> Result<(), AppError> {
+    let exists: bool = sqlx::query_scalar(&format!(
+        "SELECT EXISTS(SELECT 1 FROM {} WHERE {} = ? AND is_deleted = 0)",
+        entity.kind.table(),
+        entity.kind.id_column()
+    ))
+    .bind(&entity.id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if !exists {
+        return Err(AppError::not_found(entity.kind.label(), &entity.id));
+    }
+    Ok(())
+}
+
+// Whether `id` already depends on `target`, directly or through other records.
+async fn depends_on(
+    conn: &mut SqliteConnection,
+    spec: &RelationSpec,
+    id: &str,
+    target: &str,
+) -> Result<bool, AppError> {
+    let found = sqlx::query_scalar(&format!(
+        "WITH RECURSIVE reachable(id) AS (
+            SELECT ?1
+            UNION
+            SELECT dependency.{right} FROM {table} AS dependency
+            JOIN reachable ON dependency.{left} = reachable.id
+            WHERE dependency.unlinked_at IS NULL
+        )
+        SELECT EXISTS(SELECT 1 FROM reachable WHERE id = ?2)",
+        table = spec.table,
+        left = spec.left_column,
+        right = spec.right_column,
+    ))
+    .bind(id)
+    .bind(target)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(found)
+}
@@ -0,0 +1,171 @@
+use crate::app::error::{AppError, FieldErrors};
+use crate::db::relations::{EntityKind, EntityRef};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+// Snippets mark matched terms with these control characters rather than HTML, so the
+// frontend can highlight them without rendering anything from the index as markup.
+pub const HIGHLIGHT_START: &str = "\u{2}";
+pub const HIGHLIGHT_END: &str = "\u{3}";
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SearchQuery {
+    pub query: String,
+    pub kinds: Option<Vec<EntityKind>>, // None searches every domain
+    pub from_date: Option<String>,
+    pub to_date: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub entity: EntityRef,
+    pub title: String,
+    pub snippet: String,
+    pub date: Option<String>,
+    pub rank: f64, // lower is better
+}
+
+// Each word is quoted so the user's punctuation is never read as query syntax, and the
+// last one is a prefix match so results come up while still typing.
+fn match_expression(query: &str) -> Option<String> {
+    let mut terms: Vec<String> = query
+        .split_whitespace()
+        .filter(|term| term.chars().any(char::is_alphanumeric))
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    terms.last_mut()?.push('*');
+    Some(terms.join(" "))
+}
+
+fn validate(query: &SearchQuery) -> Result<(), AppError> {
+    let mut errors = FieldErrors::new();
+    let mut parse = |field: &str, value: &Option<String>| {
+        let date = value
+            .as_deref()
+            .map(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d"))
+            .transpose();
+        date.unwrap_or_else(|_| {
+            errors.add(field, "Invalid date format. Expected YYYY-MM-DD.");
+            None
+        })
+    };
+    let from = parse("from_date", &query.from_date);
+    let to = parse("to_date", &query.to_date);
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            errors.add("to_date", "The end date cannot be before the start date.");
+        }
+    }
+    if query.limit == Some(0) {
+        errors.add("limit", "Limit must be at least 1.");
+    }
+    errors.into_result()
+}
+
+// Titles are weighted above bodies. Every record gets a date to filter on: the entry's
+// day for diary pages, the created date for goals and jobs, and for habits the day they
+// were added.
+pub async fn search(pool: &SqlitePool, query: &SearchQuery) -> Result<Vec<SearchHit>, AppError> {
+    validate(query)?;
+    let Some(expression) = match_expression(&query.query) else {
+        return Ok(Vec::new());
+    };
+    let kinds = query
+        .kinds
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| AppError::Internal(e.to_string()))?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    let rows: Vec<(String, String, String, String, Option<String>, f64)> = sqlx::query_as(
+        "SELECT entity_kind, entity_id, title, snippet, date, rank FROM (
+            SELECT doc.entity_kind, doc.entity_id,
+                CASE doc.entity_kind
+                    WHEN 'diary' THEN COALESCE(diary.title_plaintext, diary.entry_date)
+                    WHEN 'habit' THEN habit.habit_name
+                    WHEN 'goal' THEN goal.goal_title
+                    ELSE job.job_title || ' at ' || job.company_name
+                END AS title,
+                snippet(search_index, -1, ?, ?, '…', 16) AS snippet,
+                CASE doc.entity_kind
+                    WHEN 'diary' THEN diary.entry_date
+                    WHEN 'habit' THEN habit.habit_created_date
+                    WHEN 'goal' THEN goal.goal_created_date
+                    ELSE job.application_created_date
+                END AS date,
+                bm25(search_index, 4.0, 1.0) AS rank
+            FROM search_index
+            JOIN search_documents doc ON doc.doc_id = search_index.rowid
+            LEFT JOIN diary_entries diary
+                ON doc.entity_kind = 'diary' AND diary.diary_entry_id = doc.entity_id
+            LEFT JOIN habits habit
+                ON doc.entity_kind = 'habit' AND habit.habit_id = doc.entity_id
+            LEFT JOIN goals goal
+                ON doc.entity_kind = 'goal' AND goal.goal_id = doc.entity_id
+            LEFT JOIN job_applications job
+                ON doc.entity_kind = 'job' AND job.job_application_id = doc.entity_id
+            WHERE search_index MATCH ?
+        )
+        WHERE (? IS NULL OR entity_kind IN (SELECT value FROM json_each(?)))
+          AND (? IS NULL OR date >= ?)
+          AND (? IS NULL OR date <= ?)
+        ORDER BY rank, date DESC
+        LIMIT ?",
+    )
+    .bind(HIGHLIGHT_START)
+    .bind(HIGHLIGHT_END)
+    .bind(&expression)
+    .bind(&kinds)
+    .bind(&kinds)
+    .bind(&query.from_date)
+    .bind(&query.from_date)
+    .bind(&query.to_date)
+    .bind(&query.to_date)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|(kind, id, title, snippet, date, rank)| {
+            let kind = serde_json::from_value(serde_json::Value::String(kind))
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            Ok(SearchHit {
+                entity: EntityRef { kind, id },
+                title,
+                snippet: snippet.trim().to_string(),
+                date,
+                rank,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terms_are_quoted_and_the_last_is_a_prefix() {
+        assert_eq!(
+            match_expression("he said \"hi\"").as_deref(),
+            Some("\"he\" \"said\" \"\"\"hi\"\"\"*")
+        );
+        assert_eq!(
+            match_expression("  run OR  walk ").as_deref(),
+            Some("\"run\" \"OR\" \"walk\"*")
+        );
+    }
+
+    #[test]
+    fn punctuation_only_terms_are_dropped() {
+        assert_eq!(match_expression("tea - ").as_deref(), Some("\"tea\"*"));
+        assert_eq!(match_expression("* ( \" "), None);
+        assert_eq!(match_expression("   "), None);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::app::context::RequestContext;
+use crate::app::error::AppError;
+use crate::utils::ids::{numbered_slug, slugify};
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+// Where an entity keeps its slug and the slugs it has been renamed away from.
+pub struct SlugSpec {
+    pub table: &'static str,
+    pub id_column: &'static str,
+    pub slug_column: &'static str,
+    pub alias_table: &'static str,
+    pub text_sql: &'static str, // the text the slug is made from, as a column expression
+}
+
+pub const GOAL_SLUGS: SlugSpec = SlugSpec {
+    table: "goals",
+    id_column: "goal_id",
+    slug_column: "goal_slug",
+    alias_table: "goal_slug_aliases",
+    text_sql: "goal_title",
+};
+
+pub const JOB_SLUGS: SlugSpec = SlugSpec {
+    table: "job_applications",
+    id_column: "job_application_id",
+    slug_column: "job_application_slug",
+    alias_table: "job_application_slug_aliases",
+    text_sql: "company_name || ' ' || job_title",
+};
+
+// First free slug for `text`. The owner's own current slug and aliases are free to
+// it, so renaming back to an old title reclaims the old slug. Run this on the
+// connection that writes the slug so the check and the write can't interleave.
+pub async fn unique_slug(
+    conn: &mut SqliteConnection,
+    spec: &SlugSpec,
+    text: &str,
+    owner_id: &str,
+) -> Result<String, AppError> {
+    let base = slugify(text);
+    let mut n = 1;
+    loop {
+        let candidate = numbered_slug(&base, n);
+        if !slug_taken(conn, spec, &candidate, owner_id).await? {
+            return Ok(candidate);
+        }
+        n += 1;
+    }
+}
+
+// A slug is taken if another record uses it or still answers to it as an alias.
+pub async fn slug_taken(
+    conn: &mut SqliteConnection,
+    spec: &SlugSpec,
+    slug: &str,
+    owner_id: &str,
+) -> Result<bool, AppError> {
+    let taken = sqlx::query_scalar(&format!(
+        "SELECT EXISTS (SELECT 1 FROM {table} WHERE {slug} = ?1 AND {id} != ?2)
+            OR EXISTS (SELECT 1 FROM {aliases} WHERE alias_slug = ?1 AND {id} != ?2)",
+        table = spec.table,
+        slug = spec.slug_column,
+        id = spec.id_column,
+        aliases = spec.alias_table,
+    ))
+    .bind(slug)
+    .bind(owner_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(taken)
+}
+
+// Keeps `old_slug` resolving to the owner after a rename. If the new slug was one of
+// the owner's aliases it stops being an alias, since it is the live slug again.
+pub async fn retire_slug(
+    conn: &mut SqliteConnection,
+    spec: &SlugSpec,
+    owner_id: &str,
+    ctx: &RequestContext,
+    old_slug: &str,
+    new_slug: &str,
+) -> Result<(), AppError> {
+    if old_slug == new_slug {
+        return Ok(());
+    }
+
+    sqlx::query(&format!(
+        "DELETE FROM {} WHERE alias_slug = ? AND {} = ?",
+        spec.alias_table, spec.id_column
+    ))
+    .bind(new_slug)
+    .bind(owner_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(&format!(
+        "INSERT INTO {} (alias_id, alias_slug, {}, created_at) VALUES (?, ?, ?, ?)",
+        spec.alias_table, spec.id_column
+    ))
+    .bind(Uuid::new_v4().to_string())
+    .bind(old_slug)
+    .bind(owner_id)
+    .bind(ctx.timestamp())
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// The id of the record answering to `slug`, either as its current slug or as an
+// alias left behind by a rename.
+pub async fn resolve_slug(
+    pool: &SqlitePool,
+    spec: &SlugSpec,
+    slug: &str,
+) -> Result<Option<String>, AppError> {
+    let id = sqlx::query_scalar(&format!(
+        "SELECT {id} FROM {table} WHERE {slug} = ?1
+         UNION ALL
+         SELECT {id} FROM {aliases} WHERE alias_slug = ?1
+         LIMIT 1",
+        id = spec.id_column,
+        table = spec.table,
+        slug = spec.slug_column,
+        aliases = spec.alias_table,
+    ))
+    .bind(slug)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(id)
+}
+
+// Slugs written before `slugify` were the lowercased title with a random suffix. Run on
+// unlock: a record keeps its slug only if it is the one `unique_slug` would give it now,
+// otherwise it gets that one and the old slug stays behind as an alias, so existing
+// links keep working.
+pub async fn backfill_slugs(pool: &SqlitePool, ctx: &RequestContext) -> Result<usize, AppError> {
+    let mut updated = 0;
+    for spec in [&GOAL_SLUGS, &JOB_SLUGS] {
+        let rows: Vec<(String, String, String)> = sqlx::query_as(&format!(
+            "SELECT {id}, {slug}, {text} FROM {table}",
+            id = spec.id_column,
+            slug = spec.slug_column,
+            text = spec.text_sql,
+            table = spec.table,
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        for (id, old_slug, text) in rows {
+            // The bare slug is always the first one tried, so it needs no lookup.
+            if old_slug == slugify(&text) {
+                continue;
+            }
+            let mut tx = pool.begin().await?;
+            let slug = unique_slug(&mut tx, spec, &text, &id).await?;
+            if slug == old_slug {
+                continue;
+            }
+            sqlx::query(&format!(
+                "UPDATE {} SET {} = ? WHERE {} = ?",
+                spec.table, spec.slug_column, spec.id_column
+            ))
+            .bind(&slug)
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
+            retire_slug(&mut tx, spec, &id, ctx, &old_slug, &slug).await?;
+            tx.commit().await?;
+            updated += 1;
+        }
+    }
+    Ok(updated)
+}
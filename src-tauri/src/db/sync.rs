@@ -0,0 +1,1379 @@
+use crate::analytics::cache::clear_analytics_caches;
+use crate::app::context::RequestContext;
+use crate::app::error::AppError;
+use crate::db::archive::{bind_value, quote_ident, row_to_json};
+use crate::db::encryption::{open_sealed, seal_bytes, DataKey};
+use crate::db::slugs::{slug_taken, SlugSpec, GOAL_SLUGS, JOB_SLUGS};
+use crate::db::trash::is_purged;
+use crate::domains::diary::model::DiaryEntry;
+use crate::domains::diary::text::{content_plaintext, title_plaintext};
+use crate::domains::goals::model::Goal;
+use crate::domains::habits::model::Habit;
+use crate::domains::jobs::model::JobApplication;
+use crate::utils::hashing::store_content_hash;
+use crate::utils::hlc::Hlc;
+use crate::utils::ids::numbered_slug;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::{Sqlite, SqliteConnection, SqlitePool};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+pub const CHANGE_SET_FORMAT: &str = "nocturne-changes";
+pub const CHANGE_SET_FORMAT_VERSION: u32 = 1;
+const CHANGE_SET_MAGIC: &[u8; 8] = b"NOCTSYN1";
+const CHANGE_SET_EXTENSION: &str = "nsync";
+const HLC_META_KEY: &str = "hlc";
+
+struct SyncTable {
+    name: &'static str,
+    id_column: &'static str,
+    slugs: Option<&'static SlugSpec>,
+    // Whether the table has the sync_state / conflict_state columns.
+    tracks_sync_state: bool,
+}
+
+// Referenced tables come first, so a change set applies in one pass.
+const SYNC_TABLES: &[SyncTable] = &[
+    SyncTable {
+        name: "profiles",
+        id_column: "profile_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "diary_entries",
+        id_column: "diary_entry_id",
+        slugs: None,
+        tracks_sync_state: true,
+    },
+    SyncTable {
+        name: "habits",
+        id_column: "habit_id",
+        slugs: None,
+        tracks_sync_state: true,
+    },
+    SyncTable {
+        name: "habit_logs",
+        id_column: "log_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "goals",
+        id_column: "goal_id",
+        slugs: Some(&GOAL_SLUGS),
+        tracks_sync_state: true,
+    },
+    SyncTable {
+        name: "job_applications",
+        id_column: "job_application_id",
+        slugs: Some(&JOB_SLUGS),
+        tracks_sync_state: true,
+    },
+    SyncTable {
+        name: "goal_slug_aliases",
+        id_column: "alias_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "job_application_slug_aliases",
+        id_column: "alias_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "goal_progress_events",
+        id_column: "event_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "job_status_events",
+        id_column: "event_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "diary_edit_history",
+        id_column: "history_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "diary_habits",
+        id_column: "link_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "diary_goals",
+        id_column: "link_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "diary_jobs",
+        id_column: "link_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "habit_goals",
+        id_column: "link_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "job_goals",
+        id_column: "link_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "job_habits",
+        id_column: "link_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "goal_dependencies",
+        id_column: "link_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "habit_dependencies",
+        id_column: "link_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "attachments",
+        id_column: "attachment_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+    SyncTable {
+        name: "attachment_links",
+        id_column: "link_id",
+        slugs: None,
+        tracks_sync_state: false,
+    },
+];
+
+// Columns describing this device's copy rather than the record, or recomputed locally.
+const LOCAL_COLUMNS: &[&str] = &[
+    "sync_state",
+    "conflict_state",
+    "conflict_resolved_at",
+    "content_hash",
+    "title_plaintext",
+    "content_plaintext",
+];
+
+// Concurrent edits to these are kept for the user to reconcile. Everything else is
+// settled by last-writer-wins alone.
+const CONFLICT_FIELDS: &[(&str, &str)] = &[
+    ("diary_entries", "title"),
+    ("diary_entries", "content_json"),
+];
+
+// One file in the sync folder: every record this device changed since its last export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeSet {
+    pub format: String,
+    pub format_version: u32,
+    pub device_id: String,
+    pub created_at: i64,
+    pub changes: Vec<RecordChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordChange {
+    pub table: String,
+    pub record_id: String,
+    pub fields: BTreeMap<String, FieldChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub value: Value,
+    pub hlc: String,
+    // The version of the field the writer was editing; None for a field it created.
+    pub base_hlc: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub exported_change_set: Option<String>,
+    pub exported_records: usize,
+    pub applied_change_sets: usize,
+    pub applied_fields: usize,
+    pub conflicts_detected: usize,
+    // Change sets that could not be applied; they are retried on the next sync.
+    pub failed_change_sets: Vec<String>,
+    // Attachment files copied to and from the sync folder.
+    pub attachments_sent: usize,
+    pub attachments_received: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub conflict_id: String,
+    pub table_name: String,
+    pub record_id: String,
+    pub field_name: String,
+    pub local_value: Value,
+    pub remote_value: Value,
+    pub local_hlc: String,
+    pub remote_hlc: String,
+    pub remote_device_id: String,
+    pub detected_at: i64,
+    pub resolved_at: Option<i64>,
+    pub resolution: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ConflictResolution {
+    KeepLocal,
+    KeepRemote,
+    Custom { value: Value },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SyncPeer {
+    pub device_id: String,
+    pub last_change_set: String,
+    pub last_synced_at: i64,
+}
+
+struct FieldClock {
+    hlc: Hlc,
+    base_hlc: Option<Hlc>,
+}
+
+// Exports local changes into `<root>/<device_id>/`, then applies what other devices
+// left in their directories under `root`. Change sets are sealed with the vault's data
+// key, so only devices sharing the vault's key file can read them.
+pub async fn sync_with_folder(
+    pool: &SqlitePool,
+    key: &DataKey,
+    root: &Path,
+    ctx: &RequestContext,
+) -> Result<SyncReport, AppError> {
+    let mut report = SyncReport::default();
+    export_changes(pool, key, root, ctx, &mut report).await?;
+    ingest_changes(pool, key, root, ctx, &mut report).await?;
+    // Incoming changes can touch any record's history; cached figures are rebuilt lazily.
+    if report.applied_change_sets > 0 {
+        clear_analytics_caches(pool).await?;
+    }
+    Ok(report)
+}
+
+async fn export_changes(
+    pool: &SqlitePool,
+    key: &DataKey,
+    root: &Path,
+    ctx: &RequestContext,
+    report: &mut SyncReport,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    let mut last = load_hlc(&mut tx).await?;
+    let changes = capture_local_changes(&mut tx, ctx, &mut last).await?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let change_set = ChangeSet {
+        format: CHANGE_SET_FORMAT.to_string(),
+        format_version: CHANGE_SET_FORMAT_VERSION,
+        device_id: ctx.device_id.clone(),
+        created_at: ctx.timestamp(),
+        changes,
+    };
+    let Some(last) = last else {
+        return Err(AppError::Internal("Change set has no HLC".to_string()));
+    };
+    let name = last.to_string();
+    save_hlc(&mut tx, &last).await?;
+
+    // The file goes out before the shadow rows commit: if writing it fails, the same
+    // changes are captured again next time instead of being lost.
+    write_change_set(key, &root.join(&ctx.device_id), &name, &change_set)?;
+    tx.commit().await?;
+
+    log::info!(
+        "Exported {} changed records as change set {}",
+        change_set.changes.len(),
+        name
+    );
+    report.exported_records = change_set.changes.len();
+    report.exported_change_set = Some(name);
+    Ok(())
+}
+
+// Compares every synced row with its shadow copy; fields that differ get a fresh HLC.
+async fn capture_local_changes(
+    conn: &mut SqliteConnection,
+    ctx: &RequestContext,
+    last: &mut Option<Hlc>,
+) -> Result<Vec<RecordChange>, AppError> {
+    let mut changes = Vec::new();
+
+    for table in SYNC_TABLES {
+        let rows = sqlx::query(&format!("SELECT * FROM {}", quote_ident(table.name)))
+            .fetch_all(&mut *conn)
+            .await?;
+        let mut shadows = load_shadows(conn, table.name).await?;
+        let mut clocks = load_clocks(conn, table.name).await?;
+
+        for row in rows {
+            let row = synced_fields(row_to_json(&row)?);
+            let Some(record_id) = row
+                .get(table.id_column)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            let shadow = shadows.remove(&record_id).unwrap_or_default();
+            let changed: Vec<&String> = row
+                .iter()
+                .filter(|(field, value)| shadow.get(*field) != Some(*value))
+                .map(|(field, _)| field)
+                .collect();
+            if changed.is_empty() {
+                continue;
+            }
+
+            let hlc = Hlc::tick(last.as_ref(), ctx.day.now(), &ctx.device_id);
+            *last = Some(hlc.clone());
+
+            let mut fields = BTreeMap::new();
+            for field in changed {
+                let base_hlc = clocks
+                    .remove(&(record_id.clone(), field.clone()))
+                    .map(|clock| clock.hlc.to_string());
+                save_clock(
+                    conn,
+                    table.name,
+                    &record_id,
+                    field,
+                    &hlc.to_string(),
+                    base_hlc.as_deref(),
+                )
+                .await?;
+                fields.insert(
+                    field.clone(),
+                    FieldChange {
+                        value: row[field].clone(),
+                        hlc: hlc.to_string(),
+                        base_hlc,
+                    },
+                );
+            }
+            save_shadow(conn, table.name, &record_id, &row).await?;
+            if table.tracks_sync_state {
+                mark_synced(conn, table, &record_id).await?;
+            }
+
+            changes.push(RecordChange {
+                table: table.name.to_string(),
+                record_id,
+                fields,
+            });
+        }
+    }
+
+    Ok(changes)
+}
+
+async fn ingest_changes(
+    pool: &SqlitePool,
+    key: &DataKey,
+    root: &Path,
+    ctx: &RequestContext,
+    report: &mut SyncReport,
+) -> Result<(), AppError> {
+    if !root.exists() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(root)? {
+        let peer_dir = entry?.path();
+        let Some(peer_id) = peer_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        if !peer_dir.is_dir() || peer_id == ctx.device_id {
+            continue;
+        }
+
+        let cursor: Option<String> =
+            sqlx::query_scalar("SELECT last_change_set FROM sync_peers WHERE device_id = ?")
+                .bind(&peer_id)
+                .fetch_optional(pool)
+                .await?;
+        for (name, path) in list_change_sets(&peer_dir)? {
+            if cursor.as_ref().is_some_and(|cursor| name <= *cursor) {
+                continue;
+            }
+            // Later change sets may build on this one, so a failure stops this peer
+            // until the next sync.
+            match apply_change_set_file(pool, key, &peer_id, &name, &path, ctx).await {
+                Ok((fields, conflicts)) => {
+                    report.applied_change_sets += 1;
+                    report.applied_fields += fields;
+                    report.conflicts_detected += conflicts;
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Could not apply change set {} from {}: {}",
+                        name,
+                        peer_id,
+                        e
+                    );
+                    report
+                        .failed_change_sets
+                        .push(format!("{}/{}", peer_id, name));
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn apply_change_set_file(
+    pool: &SqlitePool,
+    key: &DataKey,
+    peer_id: &str,
+    name: &str,
+    path: &Path,
+    ctx: &RequestContext,
+) -> Result<(usize, usize), AppError> {
+    let change_set = read_change_set(key, peer_id, path)?;
+    if change_set.format != CHANGE_SET_FORMAT
+        || change_set.format_version > CHANGE_SET_FORMAT_VERSION
+        || change_set.device_id != peer_id
+    {
+        return Err(AppError::Validation(format!(
+            "{} is not a change set this version can read.",
+            name
+        )));
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::query("PRAGMA defer_foreign_keys = ON")
+        .execute(&mut *tx)
+        .await?;
+
+    let mut last = load_hlc(&mut tx).await?;
+    let mut applied_fields = 0;
+    let mut conflicts = 0;
+    let mut touched = Vec::new();
+
+    let mut changes: Vec<&RecordChange> = change_set.changes.iter().collect();
+    changes.sort_by_key(|change| {
+        SYNC_TABLES
+            .iter()
+            .position(|table| table.name == change.table)
+    });
+    for change in changes {
+        let table = sync_table(&change.table).ok_or_else(|| {
+            AppError::Validation(format!(
+                "Change set touches unknown table {}.",
+                change.table
+            ))
+        })?;
+        let (fields, found) =
+            apply_record_change(&mut tx, table, change, peer_id, ctx, &mut last).await?;
+        if fields > 0 {
+            touched.push((table.name, change.record_id.clone()));
+        }
+        applied_fields += fields;
+        conflicts += found;
+    }
+
+    if let Some(last) = &last {
+        save_hlc(&mut tx, last).await?;
+    }
+    sqlx::query(
+        "INSERT INTO sync_peers (device_id, last_change_set, last_synced_at) VALUES (?, ?, ?)
+         ON CONFLICT(device_id) DO UPDATE SET
+            last_change_set = excluded.last_change_set,
+            last_synced_at = excluded.last_synced_at",
+    )
+    .bind(peer_id)
+    .bind(name)
+    .bind(ctx.timestamp())
+    .execute(&mut *tx)
+    .await?;
+    for (table, record_id) in touched {
+        refresh_content_hash(&mut tx, table, &record_id).await?;
+    }
+    tx.commit().await?;
+    Ok((applied_fields, conflicts))
+}
+
+// Per field: the later HLC wins. An edit made without having seen the other side's
+// version is concurrent; for diary content that is recorded as a conflict, with the
+// losing value kept so the user can pick. Returns (fields applied, conflicts found).
+async fn apply_record_change(
+    conn: &mut SqliteConnection,
+    table: &SyncTable,
+    change: &RecordChange,
+    peer_id: &str,
+    ctx: &RequestContext,
+    last: &mut Option<Hlc>,
+) -> Result<(usize, usize), AppError> {
+    let columns = table_columns(conn, table.name).await?;
+    let mut local_row = fetch_row(conn, table, &change.record_id).await?;
+    // Purged here while the other device still had it; it stays gone.
+    if local_row.is_none() && is_purged(conn, table.name, &change.record_id).await? {
+        return Ok((0, 0));
+    }
+    let mut record_id = change.record_id.clone();
+    if local_row.is_none() {
+        if let Some(same_day) = same_day_log(conn, table, change).await? {
+            local_row = fetch_row(conn, table, &same_day).await?;
+            record_id = same_day;
+        }
+    }
+    let clocks = load_record_clocks(conn, table.name, &record_id).await?;
+
+    let mut winners: Map<String, Value> = Map::new();
+    let mut conflicts = 0;
+
+    for (field, incoming) in &change.fields {
+        // Fields from a newer schema, or local-only ones, are left alone.
+        if !columns.contains(field) || LOCAL_COLUMNS.contains(&field.as_str()) {
+            continue;
+        }
+        // A log merged into this device's one for the same day keeps the local ID.
+        if field == table.id_column && record_id != change.record_id {
+            continue;
+        }
+        let remote_hlc = parse_hlc(&incoming.hlc)?;
+        let remote_base = incoming.base_hlc.as_deref().map(parse_hlc).transpose()?;
+        *last = Some(Hlc::observe(last.take(), &remote_hlc));
+
+        let Some(local) = clocks.get(field) else {
+            winners.insert(field.clone(), incoming.value.clone());
+            continue;
+        };
+        if remote_hlc == local.hlc {
+            continue;
+        }
+
+        let remote_saw_local = remote_base.as_ref().is_some_and(|base| *base >= local.hlc);
+        let local_saw_remote = local
+            .base_hlc
+            .as_ref()
+            .is_some_and(|base| *base >= remote_hlc);
+        let concurrent = !remote_saw_local && !local_saw_remote;
+        let remote_wins = remote_hlc > local.hlc;
+
+        let local_value = local_row
+            .as_ref()
+            .and_then(|row| row.get(field))
+            .cloned()
+            .unwrap_or(Value::Null);
+        if concurrent
+            && CONFLICT_FIELDS.contains(&(table.name, field.as_str()))
+            && local_value != incoming.value
+        {
+            let conflict = DetectedConflict {
+                table: table.name,
+                record_id: &record_id,
+                field,
+                local: (&local_value, &local.hlc),
+                remote: (&incoming.value, &remote_hlc),
+                peer_id,
+            };
+            record_conflict(conn, &conflict, ctx).await?;
+            conflicts += 1;
+        } else if remote_wins && remote_saw_local {
+            // The other device edited on top of our version, which settles any
+            // conflict still open on this field.
+            supersede_conflicts(conn, table.name, &record_id, field, ctx).await?;
+        }
+
+        if remote_wins {
+            winners.insert(field.clone(), incoming.value.clone());
+        }
+    }
+
+    if winners.is_empty() {
+        if conflicts > 0 {
+            mark_conflicted(conn, table, &record_id).await?;
+        }
+        return Ok((0, conflicts));
+    }
+
+    let mut written = winners.clone();
+    if let Some(slugs) = table.slugs {
+        dedupe_slug(conn, slugs, &record_id, &mut written).await?;
+    }
+    let applied = match local_row {
+        Some(_) => update_row(conn, table, &record_id, &written).await?,
+        None if references_purged(conn, table, &written).await? => return Ok((0, conflicts)),
+        None => {
+            written.insert(
+                table.id_column.to_string(),
+                Value::String(record_id.clone()),
+            );
+            insert_row(conn, table, &written).await?
+        }
+    };
+    if !applied {
+        // A uniqueness rule kept the row out.
+        return Ok((0, conflicts));
+    }
+    if table.name == "diary_entries" {
+        refresh_diary_plaintext(conn, &record_id).await?;
+    }
+
+    for field in winners.keys() {
+        let incoming = &change.fields[field];
+        save_clock(
+            conn,
+            table.name,
+            &record_id,
+            field,
+            &incoming.hlc,
+            incoming.base_hlc.as_deref(),
+        )
+        .await?;
+    }
+    // Only the applied fields move the shadow, so a local edit made since the last
+    // export still shows up as a change next time. A slug that had to be renumbered
+    // does too, and travels back as a local change.
+    let mut shadow = load_shadow(conn, table.name, &record_id).await?;
+    shadow.extend(winners.clone());
+    save_shadow(conn, table.name, &record_id, &shadow).await?;
+
+    if table.tracks_sync_state {
+        mark_synced(conn, table, &record_id).await?;
+        if conflicts > 0 {
+            mark_conflicted(conn, table, &record_id).await?;
+        }
+    }
+    Ok((winners.len(), conflicts))
+}
+
+// A log made before log IDs came from the day can arrive under another device's random
+// ID for a day already logged here. It is merged into the local log field by field,
+// like any other change to it.
+async fn same_day_log(
+    conn: &mut SqliteConnection,
+    table: &SyncTable,
+    change: &RecordChange,
+) -> Result<Option<String>, AppError> {
+    if table.name != "habit_logs" {
+        return Ok(None);
+    }
+    let field = |name: &str| change.fields.get(name).and_then(|f| f.value.as_str());
+    let (Some(habit_id), Some(log_date)) = (field("habit_id"), field("log_date")) else {
+        return Ok(None);
+    };
+    let log_id =
+        sqlx::query_scalar("SELECT log_id FROM habit_logs WHERE habit_id = ? AND log_date = ?")
+            .bind(habit_id)
+            .bind(log_date)
+            .fetch_optional(&mut *conn)
+            .await?;
+    Ok(log_id)
+}
+
+pub async fn list_conflicts(
+    pool: &SqlitePool,
+    include_resolved: bool,
+) -> Result<Vec<SyncConflict>, AppError> {
+    let rows: Vec<ConflictRow> = sqlx::query_as(
+        "SELECT * FROM sync_conflicts WHERE ? OR resolved_at IS NULL ORDER BY detected_at DESC",
+    )
+    .bind(include_resolved)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter().map(ConflictRow::into_conflict).collect()
+}
+
+// Writes the chosen value as a local edit, which the next sync sends to the other
+// devices; they take it without a new conflict since it was made on top of theirs.
+pub async fn resolve_conflict(
+    pool: &SqlitePool,
+    conflict_id: &str,
+    resolution: &ConflictResolution,
+    ctx: &RequestContext,
+) -> Result<SyncConflict, AppError> {
+    let conflict: ConflictRow =
+        sqlx::query_as("SELECT * FROM sync_conflicts WHERE conflict_id = ?")
+            .bind(conflict_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found("Conflict", conflict_id))?;
+    let conflict = conflict.into_conflict()?;
+    if conflict.resolved_at.is_some() {
+        return Err(AppError::Validation(
+            "This conflict has already been resolved.".to_string(),
+        ));
+    }
+    let table = sync_table(&conflict.table_name).ok_or_else(|| {
+        AppError::Internal(format!("Conflict on unknown table {}", conflict.table_name))
+    })?;
+
+    let (value, resolution_name) = match resolution {
+        ConflictResolution::KeepLocal => (conflict.local_value.clone(), "keep_local"),
+        ConflictResolution::KeepRemote => (conflict.remote_value.clone(), "keep_remote"),
+        ConflictResolution::Custom { value } => (value.clone(), "custom"),
+    };
+
+    let mut tx = pool.begin().await?;
+    let mut fields = Map::new();
+    fields.insert(conflict.field_name.clone(), value);
+    if table_columns(&mut tx, table.name)
+        .await?
+        .contains("updated_at")
+    {
+        fields.insert("updated_at".to_string(), Value::from(ctx.timestamp()));
+    }
+    if !update_row(&mut tx, table, &conflict.record_id, &fields).await? {
+        return Err(AppError::not_found("Record", &conflict.record_id));
+    }
+    if table.name == "diary_entries" {
+        refresh_diary_plaintext(&mut tx, &conflict.record_id).await?;
+    }
+    // Forget the field's shadow value so the next sync sends the decision even when
+    // it keeps the value the row already has.
+    let mut shadow = load_shadow(&mut tx, table.name, &conflict.record_id).await?;
+    shadow.remove(&conflict.field_name);
+    save_shadow(&mut tx, table.name, &conflict.record_id, &shadow).await?;
+
+    sqlx::query(
+        "UPDATE sync_conflicts SET resolved_at = ?, resolution = ?
+         WHERE conflict_id = ?",
+    )
+    .bind(ctx.timestamp())
+    .bind(resolution_name)
+    .bind(conflict_id)
+    .execute(&mut *tx)
+    .await?;
+    settle_conflict_state(&mut tx, table, &conflict.record_id, ctx).await?;
+    refresh_content_hash(&mut tx, table.name, &conflict.record_id).await?;
+    tx.commit().await?;
+
+    let resolved: ConflictRow =
+        sqlx::query_as("SELECT * FROM sync_conflicts WHERE conflict_id = ?")
+            .bind(conflict_id)
+            .fetch_one(pool)
+            .await?;
+    resolved.into_conflict()
+}
+
+pub async fn list_peers(pool: &SqlitePool) -> Result<Vec<SyncPeer>, AppError> {
+    let peers =
+        sqlx::query_as::<_, SyncPeer>("SELECT * FROM sync_peers ORDER BY last_synced_at DESC")
+            .fetch_all(pool)
+            .await?;
+
+    Ok(peers)
+}
+
+pub async fn count_open_conflicts(pool: &SqlitePool) -> Result<i64, AppError> {
+    let count = sqlx::query_scalar("SELECT COUNT(*) FROM sync_conflicts WHERE resolved_at IS NULL")
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
+
+#[derive(sqlx::FromRow)]
+struct ConflictRow {
+    conflict_id: String,
+    table_name: String,
+    record_id: String,
+    field_name: String,
+    local_value: Option<String>,
+    remote_value: Option<String>,
+    local_hlc: String,
+    remote_hlc: String,
+    remote_device_id: String,
+    detected_at: i64,
+    resolved_at: Option<i64>,
+    resolution: Option<String>,
+}
+
+impl ConflictRow {
+    fn into_conflict(self) -> Result<SyncConflict, AppError> {
+        Ok(SyncConflict {
+            local_value: parse_json(self.local_value.as_deref())?,
+            remote_value: parse_json(self.remote_value.as_deref())?,
+            conflict_id: self.conflict_id,
+            table_name: self.table_name,
+            record_id: self.record_id,
+            field_name: self.field_name,
+            local_hlc: self.local_hlc,
+            remote_hlc: self.remote_hlc,
+            remote_device_id: self.remote_device_id,
+            detected_at: self.detected_at,
+            resolved_at: self.resolved_at,
+            resolution: self.resolution,
+        })
+    }
+}
+
+// Both sides of a field edited concurrently on two devices, each with its clock.
+struct DetectedConflict<'a> {
+    table: &'a str,
+    record_id: &'a str,
+    field: &'a str,
+    local: (&'a Value, &'a Hlc),
+    remote: (&'a Value, &'a Hlc),
+    peer_id: &'a str,
+}
+
+async fn record_conflict(
+    conn: &mut SqliteConnection,
+    conflict: &DetectedConflict<'_>,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    let DetectedConflict {
+        table,
+        record_id,
+        field,
+        local: (local_value, local_hlc),
+        remote: (remote_value, remote_hlc),
+        peer_id,
+    } = *conflict;
+    // A newer conflict on the same field replaces the open one; its local side is
+    // still what the user has.
+    supersede_conflicts(conn, table, record_id, field, ctx).await?;
+    sqlx::query(
+        "INSERT INTO sync_conflicts (
+            conflict_id, table_name, record_id, field_name, local_value, remote_value,
+            local_hlc, remote_hlc, remote_device_id, detected_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(table)
+    .bind(record_id)
+    .bind(field)
+    .bind(local_value.to_string())
+    .bind(remote_value.to_string())
+    .bind(local_hlc.to_string())
+    .bind(remote_hlc.to_string())
+    .bind(peer_id)
+    .bind(ctx.timestamp())
+    .execute(&mut *conn)
+    .await?;
+
+    log::info!(
+        "Recorded sync conflict on {}.{} of {}",
+        table,
+        field,
+        record_id
+    );
+    Ok(())
+}
+
+async fn supersede_conflicts(
+    conn: &mut SqliteConnection,
+    table: &str,
+    record_id: &str,
+    field: &str,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    let result = sqlx::query(
+        "UPDATE sync_conflicts SET resolved_at = ?, resolution = 'superseded'
+         WHERE table_name = ? AND record_id = ? AND field_name = ? AND resolved_at IS NULL",
+    )
+    .bind(ctx.timestamp())
+    .bind(table)
+    .bind(record_id)
+    .bind(field)
+    .execute(&mut *conn)
+    .await?;
+
+    if result.rows_affected() > 0 {
+        if let Some(table) = sync_table(table) {
+            settle_conflict_state(conn, table, record_id, ctx).await?;
+        }
+    }
+    Ok(())
+}
+
+// Clears the row's conflict flag once none of its conflicts are open.
+async fn settle_conflict_state(
+    conn: &mut SqliteConnection,
+    table: &SyncTable,
+    record_id: &str,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    if !table.tracks_sync_state {
+        return Ok(());
+    }
+    let open: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM sync_conflicts
+            WHERE table_name = ? AND record_id = ? AND resolved_at IS NULL)",
+    )
+    .bind(table.name)
+    .bind(record_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    if open {
+        return Ok(());
+    }
+
+    sqlx::query(&format!(
+        "UPDATE {} SET conflict_state = 'resolved', conflict_resolved_at = ?
+         WHERE {} = ? AND conflict_state = 'conflicted'",
+        quote_ident(table.name),
+        quote_ident(table.id_column)
+    ))
+    .bind(ctx.timestamp())
+    .bind(record_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+async fn mark_conflicted(
+    conn: &mut SqliteConnection,
+    table: &SyncTable,
+    record_id: &str,
+) -> Result<(), AppError> {
+    if !table.tracks_sync_state {
+        return Ok(());
+    }
+    sqlx::query(&format!(
+        "UPDATE {} SET conflict_state = 'conflicted', conflict_resolved_at = NULL WHERE {} = ?",
+        quote_ident(table.name),
+        quote_ident(table.id_column)
+    ))
+    .bind(record_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+async fn mark_synced(
+    conn: &mut SqliteConnection,
+    table: &SyncTable,
+    record_id: &str,
+) -> Result<(), AppError> {
+    sqlx::query(&format!(
+        "UPDATE {} SET sync_state = 'synced' WHERE {} = ?",
+        quote_ident(table.name),
+        quote_ident(table.id_column)
+    ))
+    .bind(record_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+// Same renumbering as an archive import, except the record keeps its own slug.
+async fn dedupe_slug(
+    conn: &mut SqliteConnection,
+    slugs: &SlugSpec,
+    record_id: &str,
+    fields: &mut Map<String, Value>,
+) -> Result<(), AppError> {
+    let Some(slug) = fields
+        .get(slugs.slug_column)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+    else {
+        return Ok(());
+    };
+
+    let mut n = 1;
+    let mut candidate = slug.clone();
+    while slug_taken(conn, slugs, &candidate, record_id).await? {
+        n += 1;
+        candidate = numbered_slug(&slug, n);
+    }
+    fields.insert(slugs.slug_column.to_string(), Value::String(candidate));
+    Ok(())
+}
+
+// Returns false when a uniqueness constraint kept the row out.
+async fn insert_row(
+    conn: &mut SqliteConnection,
+    table: &SyncTable,
+    fields: &Map<String, Value>,
+) -> Result<bool, AppError> {
+    let columns: Vec<&String> = fields.keys().collect();
+    let sql = format!(
+        "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT DO NOTHING",
+        quote_ident(table.name),
+        columns
+            .iter()
+            .map(|c| quote_ident(c))
+            .collect::<Vec<_>>()
+            .join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+
+    let mut query = sqlx::query::<Sqlite>(&sql);
+    for column in &columns {
+        query = bind_value(query, &fields[*column], table.name, column)?;
+    }
+    Ok(query.execute(&mut *conn).await?.rows_affected() > 0)
+}
+
+// Foreign keys are only checked at commit while a change set is applied, so a row that
+// belongs to a record purged here has to be caught before it is inserted.
+async fn references_purged(
+    conn: &mut SqliteConnection,
+    table: &SyncTable,
+    fields: &Map<String, Value>,
+) -> Result<bool, AppError> {
+    let references: Vec<(String, String)> =
+        sqlx::query_as("SELECT \"from\", \"table\" FROM pragma_foreign_key_list(?)")
+            .bind(table.name)
+            .fetch_all(&mut *conn)
+            .await?;
+    for (column, parent) in references {
+        if let Some(Value::String(parent_id)) = fields.get(&column) {
+            if is_purged(conn, &parent, parent_id).await? {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+async fn update_row(
+    conn: &mut SqliteConnection,
+    table: &SyncTable,
+    record_id: &str,
+    fields: &Map<String, Value>,
+) -> Result<bool, AppError> {
+    let sql = format!(
+        "UPDATE OR IGNORE {} SET {} WHERE {} = ?",
+        quote_ident(table.name),
+        fields
+            .keys()
+            .map(|c| format!("{} = ?", quote_ident(c)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        quote_ident(table.id_column)
+    );
+
+    let mut query = sqlx::query::<Sqlite>(&sql);
+    for (column, value) in fields {
+        query = bind_value(query, value, table.name, column)?;
+    }
+    Ok(query
+        .bind(record_id)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected()
+        > 0)
+}
+
+async fn fetch_row(
+    conn: &mut SqliteConnection,
+    table: &SyncTable,
+    record_id: &str,
+) -> Result<Option<Map<String, Value>>, AppError> {
+    let row = sqlx::query(&format!(
+        "SELECT * FROM {} WHERE {} = ?",
+        quote_ident(table.name),
+        quote_ident(table.id_column)
+    ))
+    .bind(record_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    row.map(|row| row_to_json(&row).map(synced_fields))
+        .transpose()
+}
+
+async fn table_columns(
+    conn: &mut SqliteConnection,
+    table: &str,
+) -> Result<HashSet<String>, AppError> {
+    let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info(?)")
+        .bind(table)
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(columns.into_iter().collect())
+}
+
+async fn load_shadows(
+    conn: &mut SqliteConnection,
+    table: &str,
+) -> Result<HashMap<String, Map<String, Value>>, AppError> {
+    let rows: Vec<(String, String)> =
+        sqlx::query_as("SELECT record_id, row_json FROM sync_shadow WHERE table_name = ?")
+            .bind(table)
+            .fetch_all(&mut *conn)
+            .await?;
+
+    rows.into_iter()
+        .map(|(record_id, row_json)| Ok((record_id, parse_object(&row_json)?)))
+        .collect()
+}
+
+async fn load_shadow(
+    conn: &mut SqliteConnection,
+    table: &str,
+    record_id: &str,
+) -> Result<Map<String, Value>, AppError> {
+    let row_json: Option<String> = sqlx::query_scalar(
+        "SELECT row_json FROM sync_shadow WHERE table_name = ? AND record_id = ?",
+    )
+    .bind(table)
+    .bind(record_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    match row_json {
+        Some(row_json) => parse_object(&row_json),
+        None => Ok(Map::new()),
+    }
+}
+
+async fn save_shadow(
+    conn: &mut SqliteConnection,
+    table: &str,
+    record_id: &str,
+    row: &Map<String, Value>,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO sync_shadow (table_name, record_id, row_json) VALUES (?, ?, ?)
+         ON CONFLICT(table_name, record_id) DO UPDATE SET row_json = excluded.row_json",
+    )
+    .bind(table)
+    .bind(record_id)
+    .bind(Value::Object(row.clone()).to_string())
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+async fn load_clocks(
+    conn: &mut SqliteConnection,
+    table: &str,
+) -> Result<HashMap<(String, String), FieldClock>, AppError> {
+    let rows: Vec<(String, String, String, Option<String>)> = sqlx::query_as(
+        "SELECT record_id, field_name, hlc, base_hlc FROM sync_field_clocks WHERE table_name = ?",
+    )
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    rows.into_iter()
+        .map(|(record_id, field, hlc, base_hlc)| {
+            Ok(((record_id, field), field_clock(&hlc, base_hlc.as_deref())?))
+        })
+        .collect()
+}
+
+async fn load_record_clocks(
+    conn: &mut SqliteConnection,
+    table: &str,
+    record_id: &str,
+) -> Result<HashMap<String, FieldClock>, AppError> {
+    let rows: Vec<(String, String, Option<String>)> = sqlx::query_as(
+        "SELECT field_name, hlc, base_hlc FROM sync_field_clocks
+         WHERE table_name = ? AND record_id = ?",
+    )
+    .bind(table)
+    .bind(record_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    rows.into_iter()
+        .map(|(field, hlc, base_hlc)| Ok((field, field_clock(&hlc, base_hlc.as_deref())?)))
+        .collect()
+}
+
+async fn save_clock(
+    conn: &mut SqliteConnection,
+    table: &str,
+    record_id: &str,
+    field: &str,
+    hlc: &str,
+    base_hlc: Option<&str>,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO sync_field_clocks (table_name, record_id, field_name, hlc, base_hlc)
+         VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(table_name, record_id, field_name) DO UPDATE SET
+            hlc = excluded.hlc, base_hlc = excluded.base_hlc",
+    )
+    .bind(table)
+    .bind(record_id)
+    .bind(field)
+    .bind(hlc)
+    .bind(base_hlc)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+async fn load_hlc(conn: &mut SqliteConnection) -> Result<Option<Hlc>, AppError> {
+    let value: Option<String> =
+        sqlx::query_scalar("SELECT meta_value FROM sync_meta WHERE meta_key = ?")
+            .bind(HLC_META_KEY)
+            .fetch_optional(&mut *conn)
+            .await?;
+    value.as_deref().map(parse_hlc).transpose()
+}
+
+async fn save_hlc(conn: &mut SqliteConnection, hlc: &Hlc) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO sync_meta (meta_key, meta_value) VALUES (?, ?)
+         ON CONFLICT(meta_key) DO UPDATE SET meta_value = excluded.meta_value",
+    )
+    .bind(HLC_META_KEY)
+    .bind(hlc.to_string())
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+// The search text of a diary page is derived from its title and content, so each
+// device works it out from whatever the page holds after a sync or a resolution.
+async fn refresh_diary_plaintext(
+    conn: &mut SqliteConnection,
+    record_id: &str,
+) -> Result<(), AppError> {
+    let (title, content_json): (Option<String>, String) =
+        sqlx::query_as("SELECT title, content_json FROM diary_entries WHERE diary_entry_id = ?")
+            .bind(record_id)
+            .fetch_one(&mut *conn)
+            .await?;
+
+    sqlx::query(
+        "UPDATE diary_entries SET title_plaintext = ?, content_plaintext = ?
+         WHERE diary_entry_id = ?",
+    )
+    .bind(title_plaintext(title.as_deref()))
+    .bind(content_plaintext(&content_json))
+    .bind(record_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+async fn refresh_content_hash(
+    conn: &mut SqliteConnection,
+    table: &str,
+    record_id: &str,
+) -> Result<(), AppError> {
+    match table {
+        "diary_entries" => store_content_hash::<DiaryEntry>(conn, record_id)
+            .await
+            .map(drop),
+        "habits" => store_content_hash::<Habit>(conn, record_id).await.map(drop),
+        "goals" => store_content_hash::<Goal>(conn, record_id).await.map(drop),
+        "job_applications" => store_content_hash::<JobApplication>(conn, record_id)
+            .await
+            .map(drop),
+        _ => Ok(()),
+    }
+}
+
+// Sealed like backups, with the sending device as associated data so a change set
+// can't be passed off as another device's.
+fn write_change_set(
+    key: &DataKey,
+    dir: &Path,
+    name: &str,
+    change_set: &ChangeSet,
+) -> Result<(), AppError> {
+    fs::create_dir_all(dir)?;
+    let json = serde_json::to_vec(change_set)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize change set: {}", e)))?;
+    let mut bytes = CHANGE_SET_MAGIC.to_vec();
+    bytes.extend(seal_bytes(key, &json, change_set.device_id.as_bytes())?);
+
+    // Synced folders may pick up a half-written file, so it only appears once complete.
+    let path = change_set_path(dir, name);
+    let partial_path = path.with_extension("partial");
+    fs::write(&partial_path, bytes)?;
+    fs::rename(&partial_path, &path)?;
+    Ok(())
+}
+
+fn read_change_set(key: &DataKey, peer_id: &str, path: &Path) -> Result<ChangeSet, AppError> {
+    let bytes = fs::read(path)?;
+    let sealed = bytes
+        .strip_prefix(CHANGE_SET_MAGIC.as_slice())
+        .ok_or_else(|| AppError::Validation(format!("{} is not a change set.", path.display())))?;
+    let json = open_sealed(key, sealed, peer_id.as_bytes())?;
+    serde_json::from_slice(&json)
+        .map_err(|e| AppError::Validation(format!("Malformed change set: {}", e)))
+}
+
+// Oldest first; names are HLCs, which sort in the order they were made.
+fn list_change_sets(dir: &Path) -> Result<Vec<(String, PathBuf)>, AppError> {
+    let mut change_sets: Vec<(String, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == CHANGE_SET_EXTENSION)
+        })
+        .filter_map(|path| Some((path.file_stem()?.to_str()?.to_string(), path)))
+        .collect();
+    change_sets.sort();
+    Ok(change_sets)
+}
+
+fn change_set_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.{}", name, CHANGE_SET_EXTENSION))
+}
+
+fn synced_fields(row: Value) -> Map<String, Value> {
+    let mut row = match row {
+        Value::Object(row) => row,
+        _ => Map::new(),
+    };
+    row.retain(|column, _| !LOCAL_COLUMNS.contains(&column.as_str()));
+    row
+}
+
+fn sync_table(name: &str) -> Option<&'static SyncTable> {
+    SYNC_TABLES.iter().find(|table| table.name == name)
+}
+
+fn field_clock(hlc: &str, base_hlc: Option<&str>) -> Result<FieldClock, AppError> {
+    Ok(FieldClock {
+        hlc: parse_hlc(hlc)?,
+        base_hlc: base_hlc.map(parse_hlc).transpose()?,
+    })
+}
+
+fn parse_hlc(value: &str) -> Result<Hlc, AppError> {
+    value.parse().map_err(AppError::Validation)
+}
+
+fn parse_object(json: &str) -> Result<Map<String, Value>, AppError> {
+    match serde_json::from_str(json) {
+        Ok(Value::Object(object)) => Ok(object),
+        _ => Err(AppError::Internal("Malformed sync shadow row".to_string())),
+    }
+}
+
+fn parse_json(json: Option<&str>) -> Result<Value, AppError> {
+    match json {
+        Some(json) => serde_json::from_str(json)
+            .map_err(|e| AppError::Internal(format!("Malformed conflict value: {}", e))),
+        None => Ok(Value::Null),
+    }
+}
@@ -0,0 +1,449 @@
+use crate::app::context::RequestContext;
+use crate::app::error::AppError;
+use crate::db::integrity::recount_tree;
+use crate::db::relations::{EntityKind, EntityRef};
+use crate::domains::diary::analytics::recompute_diary_analytics;
+use crate::domains::diary::history::record_diary_revision;
+use crate::domains::diary::model::DiaryEntry;
+use crate::domains::goals::events::record_goal_event;
+use crate::domains::goals::model::Goal;
+use crate::domains::habits::model::Habit;
+use crate::domains::jobs::events::record_job_event;
+use crate::domains::jobs::model::JobApplication;
+use crate::utils::hashing::store_content_hash;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+
+const KINDS: [EntityKind; 4] = [
+    EntityKind::Diary,
+    EntityKind::Habit,
+    EntityKind::Goal,
+    EntityKind::Job,
+];
+
+const DAY_SECS: i64 = 86_400;
+
+// Bound first, as ?1 to ?5, in every statement that moves records in or out of the
+// trash; the record's ID always follows as ?6.
+const AUDIT_COLUMNS: &str = "last_modified_by_profile_id = ?1, device_id = ?2, session_id = ?3,
+    app_version_last_modified = ?4, updated_at = ?5";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashItem {
+    pub entity: EntityRef,
+    pub title: String,
+    pub deleted_at: i64,
+    pub deleted_reason: Option<String>,
+    pub purge_at: Option<i64>, // None when retention is off
+}
+
+// Moves a record to the trash. A diary page takes its live sub-pages with it: each one
+// is detached from its parent, which is remembered in `restore_parent_id`, and they all
+// share the page's `deleted_at`, which is how a restore tells them apart from pages that
+// were deleted on their own earlier.
+pub async fn delete_entity(
+    pool: &SqlitePool,
+    entity: &EntityRef,
+    reason: Option<&str>,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    let now = ctx.timestamp();
+
+    let mut tx = pool.begin().await?;
+    if trashed_at(&mut tx, entity).await?.is_some() {
+        return Err(AppError::Validation(format!(
+            "That {} is already in the trash.",
+            entity.kind.label().to_lowercase()
+        )));
+    }
+
+    let ids: Vec<String> = sqlx::query_scalar(&match entity.kind {
+        EntityKind::Diary => format!(
+            "WITH RECURSIVE subtree(id) AS (
+                SELECT ?6
+                UNION
+                SELECT child.diary_entry_id FROM diary_entries child
+                JOIN subtree ON child.parent_page_id = subtree.id
+                WHERE child.is_deleted = 0
+            )
+            UPDATE diary_entries SET
+                is_deleted = 1, deleted_at = ?5, deleted_reason = ?7,
+                restore_parent_id = parent_page_id, parent_page_id = NULL, {}
+            WHERE diary_entry_id IN subtree
+            RETURNING diary_entry_id",
+            AUDIT_COLUMNS
+        ),
+        kind => format!(
+            "UPDATE {table} SET is_deleted = 1, deleted_at = ?5, deleted_reason = ?7, {audit}
+            WHERE {id} = ?6
+            RETURNING {id}",
+            table = kind.table(),
+            id = kind.id_column(),
+            audit = AUDIT_COLUMNS
+        ),
+    })
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(ctx.app_version)
+    .bind(now)
+    .bind(&entity.id)
+    .bind(reason)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if entity.kind == EntityKind::Diary {
+        recount_tree(&mut tx).await?;
+    }
+    record_event(&mut tx, entity, "deleted", ctx).await?;
+    store_hashes(&mut tx, entity.kind, &ids).await?;
+    tx.commit().await?;
+
+    after_change(pool, entity.kind, &ids, ctx).await
+}
+
+// Takes a record out of the trash. A diary page brings back the sub-pages that were
+// deleted with it and goes back under its old parent, which must not be in the trash.
+pub async fn restore_entity(
+    pool: &SqlitePool,
+    entity: &EntityRef,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    let now = ctx.timestamp();
+
+    let mut tx = pool.begin().await?;
+    let Some(deleted_at) = trashed_at(&mut tx, entity).await? else {
+        return Err(AppError::Validation(format!(
+            "That {} is not in the trash.",
+            entity.kind.label().to_lowercase()
+        )));
+    };
+
+    if entity.kind == EntityKind::Diary {
+        let parent_is_live: Option<bool> = sqlx::query_scalar(
+            "SELECT parent.is_deleted = 0 FROM diary_entries page
+             JOIN diary_entries parent ON parent.diary_entry_id = page.restore_parent_id
+             WHERE page.diary_entry_id = ?",
+        )
+        .bind(&entity.id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if parent_is_live == Some(false) {
+            return Err(AppError::Validation(
+                "The page this one belongs under is in the trash; restore it first.".to_string(),
+            ));
+        }
+    }
+
+    let ids: Vec<String> = sqlx::query_scalar(&match entity.kind {
+        EntityKind::Diary => format!(
+            "WITH RECURSIVE batch(id) AS (
+                SELECT ?6
+                UNION
+                SELECT child.diary_entry_id FROM diary_entries child
+                JOIN batch ON child.restore_parent_id = batch.id
+                WHERE child.is_deleted = 1 AND child.deleted_at = ?7
+            )
+            UPDATE diary_entries SET
+                is_deleted = 0, deleted_at = NULL, deleted_reason = NULL,
+                parent_page_id = (SELECT parent.diary_entry_id FROM diary_entries parent
+                                  WHERE parent.diary_entry_id = diary_entries.restore_parent_id),
+                restore_parent_id = NULL, {}
+            WHERE diary_entry_id IN batch
+            RETURNING diary_entry_id",
+            AUDIT_COLUMNS
+        ),
+        kind => format!(
+            "UPDATE {table} SET is_deleted = 0, deleted_at = NULL, deleted_reason = NULL, {audit}
+            WHERE {id} = ?6
+            RETURNING {id}",
+            table = kind.table(),
+            id = kind.id_column(),
+            audit = AUDIT_COLUMNS
+        ),
+    })
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(ctx.app_version)
+    .bind(now)
+    .bind(&entity.id)
+    .bind(deleted_at)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if entity.kind == EntityKind::Diary {
+        recount_tree(&mut tx).await?;
+    }
+    record_event(&mut tx, entity, "restored", ctx).await?;
+    store_hashes(&mut tx, entity.kind, &ids).await?;
+    tx.commit().await?;
+
+    after_change(pool, entity.kind, &ids, ctx).await
+}
+
+// Deletes a record from the trash for good, along with everything that hangs off it.
+// Purging a diary page also purges the trashed pages that were under it.
+pub async fn purge_entity(
+    pool: &SqlitePool,
+    entity: &EntityRef,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    let mut tx = pool.begin().await?;
+    if trashed_at(&mut tx, entity).await?.is_none() {
+        return Err(AppError::Validation(
+            "Only records in the trash can be purged.".to_string(),
+        ));
+    }
+    purge(&mut tx, entity, ctx.timestamp()).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// Purges whatever has been in the trash longer than the retention window.
+pub async fn purge_expired_trash(
+    pool: &SqlitePool,
+    retention_days: u32,
+    now: i64,
+) -> Result<usize, AppError> {
+    if retention_days == 0 {
+        return Ok(0);
+    }
+    let cutoff = now - i64::from(retention_days) * DAY_SECS;
+
+    let mut purged = 0;
+    let mut tx = pool.begin().await?;
+    for kind in KINDS {
+        let ids: Vec<String> = sqlx::query_scalar(&format!(
+            "SELECT {} FROM {} WHERE is_deleted = 1 AND deleted_at <= ?",
+            kind.id_column(),
+            kind.table()
+        ))
+        .bind(cutoff)
+        .fetch_all(&mut *tx)
+        .await?;
+        for id in ids {
+            purged += purge(&mut tx, &EntityRef { kind, id }, now).await?;
+        }
+    }
+    tx.commit().await?;
+
+    if purged > 0 {
+        log::info!("Purged {} records from the trash", purged);
+    }
+    Ok(purged)
+}
+
+// Newest first. Sub-pages deleted along with their page are listed under that page
+// rather than on their own.
+pub async fn list_trash(
+    pool: &SqlitePool,
+    retention_days: u32,
+) -> Result<Vec<TrashItem>, AppError> {
+    let mut items = Vec::new();
+    for kind in KINDS {
+        let (title, filter) = match kind {
+            EntityKind::Diary => (
+                "COALESCE(NULLIF(title, ''), entry_date)",
+                "AND NOT EXISTS (SELECT 1 FROM diary_entries parent
+                    WHERE parent.diary_entry_id = diary_entries.restore_parent_id
+                      AND parent.is_deleted = 1
+                      AND parent.deleted_at = diary_entries.deleted_at)",
+            ),
+            EntityKind::Habit => ("habit_name", ""),
+            EntityKind::Goal => ("goal_title", ""),
+            EntityKind::Job => ("job_title || ' at ' || company_name", ""),
+        };
+        let rows: Vec<(String, String, i64, Option<String>)> = sqlx::query_as(&format!(
+            "SELECT {}, {}, deleted_at, deleted_reason FROM {} WHERE is_deleted = 1 {}",
+            kind.id_column(),
+            title,
+            kind.table(),
+            filter
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        items.extend(
+            rows.into_iter()
+                .map(|(id, title, deleted_at, deleted_reason)| TrashItem {
+                    entity: EntityRef { kind, id },
+                    title,
+                    deleted_at,
+                    deleted_reason,
+                    purge_at: (retention_days > 0)
+                        .then(|| deleted_at + i64::from(retention_days) * DAY_SECS),
+                }),
+        );
+    }
+
+    items.sort_by_key(|item| std::cmp::Reverse(item.deleted_at));
+    Ok(items)
+}
+
+pub async fn is_purged(
+    conn: &mut SqliteConnection,
+    table: &str,
+    record_id: &str,
+) -> Result<bool, AppError> {
+    let purged = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM purged_records WHERE table_name = ? AND record_id = ?)",
+    )
+    .bind(table)
+    .bind(record_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(purged)
+}
+
+// Returns how many rows of the record's own table went.
+async fn purge(
+    conn: &mut SqliteConnection,
+    entity: &EntityRef,
+    now: i64,
+) -> Result<usize, AppError> {
+    let ids: Vec<String> = match entity.kind {
+        EntityKind::Diary => {
+            let ids: Vec<String> = sqlx::query_scalar(
+                "WITH RECURSIVE batch(id) AS (
+                    SELECT diary_entry_id FROM diary_entries
+                    WHERE diary_entry_id = ? AND is_deleted = 1
+                    UNION
+                    SELECT child.diary_entry_id FROM diary_entries child
+                    JOIN batch ON child.restore_parent_id = batch.id
+                    WHERE child.is_deleted = 1
+                )
+                SELECT id FROM batch",
+            )
+            .bind(&entity.id)
+            .fetch_all(&mut *conn)
+            .await?;
+            // Pages outside the batch may still point at one of these as their primary
+            // or root page.
+            let batch = serde_json::Value::from(ids.clone()).to_string();
+            for column in ["primary_page_id", "root_page_id"] {
+                sqlx::query(&format!(
+                    "UPDATE diary_entries SET {column} = NULL
+                     WHERE {column} IN (SELECT value FROM json_each(?1))
+                       AND diary_entry_id NOT IN (SELECT value FROM json_each(?1))"
+                ))
+                .bind(&batch)
+                .execute(&mut *conn)
+                .await?;
+            }
+            // Pages in the batch point at each other, so the links go before any row
+            // does.
+            sqlx::query(
+                "UPDATE diary_entries
+                 SET parent_page_id = NULL, root_page_id = NULL, primary_page_id = NULL
+                 WHERE diary_entry_id IN (SELECT value FROM json_each(?))",
+            )
+            .bind(&batch)
+            .execute(&mut *conn)
+            .await?;
+            ids
+        }
+        _ => vec![entity.id.clone()],
+    };
+
+    let table = entity.kind.table();
+    let mut purged = 0;
+    for id in &ids {
+        purged += sqlx::query(&format!(
+            "DELETE FROM {} WHERE {} = ?",
+            table,
+            entity.kind.id_column()
+        ))
+        .bind(id)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected() as usize;
+        sqlx::query(
+            "INSERT OR REPLACE INTO purged_records (table_name, record_id, purged_at)
+             VALUES (?, ?, ?)",
+        )
+        .bind(table)
+        .bind(id)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(purged)
+}
+
+// When the record went to the trash, or None if it is live.
+async fn trashed_at(
+    conn: &mut SqliteConnection,
+    entity: &EntityRef,
+) -> Result<Option<i64>, AppError> {
+    let row: Option<(bool, Option<i64>)> = sqlx::query_as(&format!(
+        "SELECT is_deleted, deleted_at FROM {} WHERE {} = ?",
+        entity.kind.table(),
+        entity.kind.id_column()
+    ))
+    .bind(&entity.id)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    match row {
+        None => Err(AppError::not_found(entity.kind.label(), &entity.id)),
+        Some((false, _)) => Ok(None),
+        Some((true, deleted_at)) => Ok(Some(deleted_at.unwrap_or_default())),
+    }
+}
+
+async fn record_event(
+    conn: &mut SqliteConnection,
+    entity: &EntityRef,
+    event_type: &str,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    match entity.kind {
+        EntityKind::Diary => record_diary_revision(conn, &entity.id, event_type, ctx).await,
+        EntityKind::Goal => record_goal_event(conn, &entity.id, event_type, ctx).await,
+        EntityKind::Job => record_job_event(conn, &entity.id, event_type, ctx).await,
+        EntityKind::Habit => Ok(()),
+    }
+}
+
+// The deletion columns are part of the content hash.
+async fn store_hashes(
+    conn: &mut SqliteConnection,
+    kind: EntityKind,
+    ids: &[String],
+) -> Result<(), AppError> {
+    for id in ids {
+        match kind {
+            EntityKind::Diary => store_content_hash::<DiaryEntry>(conn, id).await.map(drop)?,
+            EntityKind::Habit => store_content_hash::<Habit>(conn, id).await.map(drop)?,
+            EntityKind::Goal => store_content_hash::<Goal>(conn, id).await.map(drop)?,
+            EntityKind::Job => store_content_hash::<JobApplication>(conn, id)
+                .await
+                .map(drop)?,
+        }
+    }
+    Ok(())
+}
+
+// A diary page going or coming back changes the word counts of its day.
+async fn after_change(
+    pool: &SqlitePool,
+    kind: EntityKind,
+    ids: &[String],
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    if kind == EntityKind::Diary {
+        let earliest: Option<String> = sqlx::query_scalar(
+            "SELECT MIN(entry_date) FROM diary_entries
+             WHERE diary_entry_id IN (SELECT value FROM json_each(?))",
+        )
+        .bind(serde_json::Value::from(ids.to_vec()).to_string())
+        .fetch_one(pool)
+        .await?;
+        if let Some(date) = earliest {
+            recompute_diary_analytics(pool, &date, &ctx.day).await?;
+        }
+    }
+    Ok(())
+}
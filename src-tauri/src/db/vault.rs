@@ -0,0 +1,322 @@
+use crate::app::error::AppError;
+use crate::db::attachments::{attachments_dir, finish_attachment_rekey, stage_attachment_rekey};
+use crate::db::backup::{create_backup, swap_in_restored, unpack_backup, BackupReason};
+use crate::db::cipher::{encrypt_plaintext_database, is_plaintext_database};
+use crate::db::connection::{
+    establish_connection, key_opens_database, legacy_key_opens_database, rekey_database,
+    rekey_legacy_database, DbPools,
+};
+use crate::db::encryption::{key_file_path, pending_key_file_path, DataKey, KeyFile};
+use crate::db::integrity::backfill_content_hashes;
+use crate::migrations::runner::{run_migrations, schema_status};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const REGISTRY_FILE_NAME: &str = "vaults.json";
+
+pub struct OpenedVault {
+    pub pools: DbPools,
+    pub key: DataKey,
+    // Set when an upgrade failed and the vault was rolled back to its pre-migration backup.
+    pub recovery_reason: Option<String>,
+}
+
+pub fn is_initialized(database_path: &Path) -> bool {
+    key_file_path(database_path).exists()
+}
+
+pub async fn open_vault(
+    database_path: &Path,
+    backup_dir: &Path,
+    passphrase: &str,
+    cipher_available: bool,
+) -> Result<OpenedVault, AppError> {
+    recover_interrupted_rekey(database_path, passphrase).await?;
+    let is_plaintext = is_plaintext_database(database_path)?;
+
+    let key = match KeyFile::load(&key_file_path(database_path))? {
+        Some(key_file) => key_file.unlock(passphrase)?,
+        None => {
+            // First unlock: create the key file. An encrypted database that already exists
+            // was written with the shared placeholder key and is rekeyed onto the new one.
+            let (key_file, key) = KeyFile::create(passphrase)?;
+            if cipher_available && database_path.exists() && !is_plaintext {
+                commit_rekey(database_path, None, &key_file, &key).await?;
+            } else {
+                key_file.save(&key_file_path(database_path))?;
+            }
+            key
+        }
+    };
+
+    if cipher_available && is_plaintext {
+        encrypt_plaintext_database(database_path, &key).await?;
+    }
+    finish_attachment_rekey(&attachments_dir(database_path), &key)?;
+
+    let pools = establish_connection(database_path, &key).await?;
+    let (pools, recovery_reason) =
+        migrate_or_recover(database_path, backup_dir, &key, pools).await?;
+    if recovery_reason.is_none() {
+        // Not worth refusing the unlock over; the integrity check reports any leftovers.
+        if let Err(e) = backfill_content_hashes(&pools.writer).await {
+            log::warn!("Could not backfill content hashes: {}", e);
+        }
+    }
+
+    Ok(OpenedVault {
+        pools,
+        key,
+        recovery_reason,
+    })
+}
+
+// Pending migrations run only after a backup of the current schema has been taken. If one
+// fails, that backup is swapped back in and the caller opens the vault read-only instead.
+async fn migrate_or_recover(
+    database_path: &Path,
+    backup_dir: &Path,
+    key: &DataKey,
+    pools: DbPools,
+) -> Result<(DbPools, Option<String>), AppError> {
+    let status = schema_status(&pools.writer, None).await?;
+    if status.pending.is_empty() {
+        return Ok((pools, None));
+    }
+    // Nothing to lose in a brand-new database.
+    if status.applied.is_empty() {
+        run_migrations(&pools.writer).await?;
+        return Ok((pools, None));
+    }
+
+    log::info!(
+        "Upgrading schema from version {} to {}",
+        status.current_version,
+        status.latest_version
+    );
+    let backup = create_backup(&pools.reader, backup_dir, key, BackupReason::PreMigration).await?;
+    let Err(e) = run_migrations(&pools.writer).await else {
+        return Ok((pools, None));
+    };
+
+    log::error!(
+        "Migration failed, rolling back to backup {}: {}",
+        backup.backup_id,
+        e
+    );
+    pools.close().await;
+    let restore_path = unpack_backup(backup_dir, &backup.backup_id, database_path, key).await?;
+    swap_in_restored(&restore_path, database_path)?;
+
+    let pools = establish_connection(database_path, key).await?;
+    Ok((pools, Some(e.to_string())))
+}
+
+pub fn change_passphrase(
+    database_path: &Path,
+    current_passphrase: &str,
+    new_passphrase: &str,
+) -> Result<(), AppError> {
+    let key_path = key_file_path(database_path);
+    let key_file = KeyFile::load(&key_path)?.ok_or_else(|| AppError::NotFound {
+        entity: "Vault key file",
+        entity_id: None,
+    })?;
+
+    key_file
+        .change_passphrase(current_passphrase, new_passphrase)?
+        .save(&key_path)
+}
+
+// Replaces the data key itself. The pools must already be closed. Attachment files are
+// sealed with the data key too, so they are resealed alongside the database.
+pub async fn rekey_vault(
+    database_path: &Path,
+    current_key: &DataKey,
+    passphrase: &str,
+) -> Result<DataKey, AppError> {
+    let key_file =
+        KeyFile::load(&key_file_path(database_path))?.ok_or_else(|| AppError::NotFound {
+            entity: "Vault key file",
+            entity_id: None,
+        })?;
+    // Confirms the passphrase before anything is rewritten.
+    key_file.unlock(passphrase)?;
+
+    let (new_key_file, new_key) = KeyFile::create(passphrase)?;
+    let attachments = attachments_dir(database_path);
+    let result = match stage_attachment_rekey(&attachments, current_key, &new_key) {
+        Ok(_) => commit_rekey(database_path, Some(current_key), &new_key_file, &new_key).await,
+        Err(e) => Err(e),
+    };
+    let key = if result.is_ok() {
+        &new_key
+    } else {
+        current_key
+    };
+    // Unlocking finishes the job if this fails.
+    if let Err(e) = finish_attachment_rekey(&attachments, key) {
+        log::warn!("Could not finish rekeying attachments: {}", e);
+    }
+    result?;
+
+    Ok(new_key)
+}
+
+// The new key file is staged as `.key.pending` before the rekey and only promoted
+// afterwards, so an interruption never leaves the database without a usable key.
+async fn commit_rekey(
+    database_path: &Path,
+    current_key: Option<&DataKey>,
+    new_key_file: &KeyFile,
+    new_key: &DataKey,
+) -> Result<(), AppError> {
+    let pending_path = pending_key_file_path(database_path);
+    new_key_file.save(&pending_path)?;
+
+    let result = match current_key {
+        Some(current_key) => rekey_database(database_path, current_key, new_key).await,
+        None => rekey_legacy_database(database_path, new_key).await,
+    };
+    if let Err(e) = result {
+        let _ = fs::remove_file(&pending_path);
+        return Err(e);
+    }
+
+    fs::rename(&pending_path, key_file_path(database_path))?;
+    Ok(())
+}
+
+async fn recover_interrupted_rekey(database_path: &Path, passphrase: &str) -> Result<(), AppError> {
+    let pending_path = pending_key_file_path(database_path);
+    let Some(pending) = KeyFile::load(&pending_path)? else {
+        return Ok(());
+    };
+    let pending_key = pending.unlock(passphrase)?;
+
+    let current_opens = match KeyFile::load(&key_file_path(database_path))? {
+        Some(current) => key_opens_database(database_path, &current.unlock(passphrase)?).await,
+        None => legacy_key_opens_database(database_path).await,
+    };
+
+    if !current_opens && key_opens_database(database_path, &pending_key).await {
+        log::warn!("Completing an interrupted database rekey");
+        fs::rename(&pending_path, key_file_path(database_path))?;
+    } else {
+        fs::remove_file(&pending_path)?;
+    }
+
+    Ok(())
+}
+
+// A separate database file with its own key file, e.g. "Work" and "Personal".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vault {
+    pub vault_id: String,
+    pub name: String,
+    pub database_path: PathBuf,
+    pub created_at: i64,
+    pub last_opened_at: Option<i64>,
+}
+
+// Known vaults, persisted as `vaults.json` in the app config dir.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VaultRegistry {
+    pub vaults: Vec<Vault>,
+    pub last_vault_id: Option<String>,
+}
+
+impl VaultRegistry {
+    // The configured database is always registered, so existing installs
+    // show up as the "Default" vault.
+    pub fn load(config_dir: &Path, default_database_path: &Path) -> Result<Self, AppError> {
+        let path = config_dir.join(REGISTRY_FILE_NAME);
+        let mut registry: VaultRegistry = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)
+                .map_err(|e| AppError::Internal(format!("Vault registry is corrupt: {}", e)))?
+        } else {
+            VaultRegistry::default()
+        };
+
+        if !registry
+            .vaults
+            .iter()
+            .any(|v| v.database_path == default_database_path)
+        {
+            registry.vaults.insert(
+                0,
+                Vault {
+                    vault_id: Uuid::new_v4().to_string(),
+                    name: "Default".to_string(),
+                    database_path: default_database_path.to_path_buf(),
+                    created_at: Utc::now().timestamp(),
+                    last_opened_at: None,
+                },
+            );
+            registry.save(config_dir)?;
+        }
+
+        Ok(registry)
+    }
+
+    pub fn save(&self, config_dir: &Path) -> Result<(), AppError> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            AppError::Internal(format!("Failed to serialize vault registry: {}", e))
+        })?;
+        fs::write(config_dir.join(REGISTRY_FILE_NAME), json)?;
+        Ok(())
+    }
+
+    // The vault opened last, falling back to the first registered one.
+    pub fn initial_vault(&self) -> Vault {
+        self.last_vault_id
+            .as_deref()
+            .and_then(|id| self.find(id))
+            .unwrap_or(&self.vaults[0])
+            .clone()
+    }
+
+    pub fn find(&self, vault_id: &str) -> Option<&Vault> {
+        self.vaults.iter().find(|v| v.vault_id == vault_id)
+    }
+
+    pub fn add(&mut self, name: &str, vaults_dir: &Path) -> Result<Vault, AppError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::Validation(
+                "Vault name cannot be empty.".to_string(),
+            ));
+        }
+        if self
+            .vaults
+            .iter()
+            .any(|v| v.name.eq_ignore_ascii_case(name))
+        {
+            return Err(AppError::Validation(format!(
+                "A vault named {} already exists.",
+                name
+            )));
+        }
+
+        let vault_id = Uuid::new_v4().to_string();
+        let vault = Vault {
+            database_path: vaults_dir.join(format!("{}.db", vault_id)),
+            vault_id,
+            name: name.to_string(),
+            created_at: Utc::now().timestamp(),
+            last_opened_at: None,
+        };
+        self.vaults.push(vault.clone());
+        Ok(vault)
+    }
+
+    pub fn mark_opened(&mut self, vault_id: &str) {
+        if let Some(vault) = self.vaults.iter_mut().find(|v| v.vault_id == vault_id) {
+            vault.last_opened_at = Some(Utc::now().timestamp());
+        }
+        self.last_vault_id = Some(vault_id.to_string());
+    }
+}
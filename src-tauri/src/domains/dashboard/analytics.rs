@@ -1,35 +1,42 @@
 use crate::app::error::AppError;
 use crate::domains::dashboard::model::DashboardSnapshot;
-use chrono::{Local, Utc};
+use crate::utils::time::LogicalDay;
+use chrono::Utc;
 use sqlx::SqlitePool;
 use uuid::Uuid;
 
-pub async fn compute_dashboard(pool: &SqlitePool) -> Result<DashboardSnapshot, AppError> {
+pub async fn compute_dashboard(
+    pool: &SqlitePool,
+    cache_ttl_secs: i64,
+    active_profile_id: Option<String>,
+    day: &LogicalDay,
+) -> Result<DashboardSnapshot, AppError> {
     let start_time = Utc::now();
-    let today = Local::now().format("%Y-%m-%d").to_string();
-    let now_ts = Utc::now().timestamp();
+    let today = day.today_string();
+    let now_ts = day.now().timestamp();
 
     // 1. Fetch Today's Diary Status
-    let diary_exists: (i32,) =
-        sqlx::query_as("SELECT COUNT(*) FROM diary_entries WHERE entry_date = ?")
-            .bind(&today)
-            .fetch_one(pool)
-            .await?;
+    let diary_exists: (i32,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM diary_entries WHERE entry_date = ? AND is_deleted = 0",
+    )
+    .bind(&today)
+    .fetch_one(pool)
+    .await?;
 
     // 2. Fetch Habits Status
     let habits_stats: (i32, i32) = sqlx::query_as(
-        "SELECT COUNT(*), SUM(CASE WHEN habit_visibility = 'active' THEN 1 ELSE 0 END) FROM habits",
+        "SELECT COUNT(*), SUM(CASE WHEN habit_visibility = 'active' THEN 1 ELSE 0 END) FROM habits WHERE is_deleted = 0",
     )
     .fetch_one(pool)
     .await?;
 
     // 3. Fetch Goals Status
-    let goals_stats: (i32, i32) = sqlx::query_as("SELECT COUNT(*), SUM(CASE WHEN goal_status = 'completed' THEN 1 ELSE 0 END) FROM goals WHERE goal_visibility = 'active'")
+    let goals_stats: (i32, i32) = sqlx::query_as("SELECT COUNT(*), SUM(CASE WHEN goal_status = 'completed' THEN 1 ELSE 0 END) FROM goals WHERE goal_visibility = 'active' AND is_deleted = 0")
         .fetch_one(pool)
         .await?;
 
     // 4. Fetch Jobs Status
-    let jobs_stats: (i32, i32, i32) = sqlx::query_as("SELECT COUNT(*), SUM(CASE WHEN job_status = 'interviewing' THEN 1 ELSE 0 END), SUM(CASE WHEN job_status = 'offer' THEN 1 ELSE 0 END) FROM job_applications WHERE job_visibility = 'active'")
+    let jobs_stats: (i32, i32, i32) = sqlx::query_as("SELECT COUNT(*), SUM(CASE WHEN job_status = 'interviewing' THEN 1 ELSE 0 END), SUM(CASE WHEN job_status = 'offer' THEN 1 ELSE 0 END) FROM job_applications WHERE job_visibility = 'active' AND is_deleted = 0")
         .fetch_one(pool)
         .await?;
 
@@ -40,8 +47,8 @@ pub async fn compute_dashboard(pool: &SqlitePool) -> Result<DashboardSnapshot, A
     let snapshot = DashboardSnapshot {
         dashboard_id: Uuid::new_v4().to_string(),
         dashboard_date: today.clone(),
-        dashboard_timezone: Some(Local::now().offset().to_string()),
-        active_profile_id: None,
+        dashboard_timezone: Some(day.timezone_label()),
+        active_profile_id,
         dashboard_version: 1,
 
         overall_productivity_score: 85.0, // Placeholder
@@ -131,7 +138,7 @@ pub async fn compute_dashboard(pool: &SqlitePool) -> Result<DashboardSnapshot, A
         dashboard_last_refreshed_at: now_ts,
 
         cache_generated_at: now_ts,
-        cache_valid_until: Some(now_ts + 3600), // Valid for 1 hour
+        cache_valid_until: Some(now_ts + cache_ttl_secs),
         data_sources_version: Some("1.0".to_string()),
         analytics_computation_duration_ms: computation_duration,
     };
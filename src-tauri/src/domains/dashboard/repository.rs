@@ -12,6 +12,15 @@ pub async fn get_latest_snapshot(pool: &SqlitePool) -> Result<Option<DashboardSn
     Ok(snapshot)
 }
 
+// Snapshots are derived from the records, so they go whenever the records are swapped
+// out underneath them.
+pub async fn clear_snapshots(pool: &SqlitePool) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM dashboard_snapshots")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 pub async fn save_snapshot(
     pool: &SqlitePool,
     snapshot: &DashboardSnapshot,
@@ -1,9 +1,145 @@
-﻿use crate::app::error::AppError;
+﻿use crate::analytics::cache::save_cache_row;
+use crate::analytics::streaks::{longest_streak, streak_ending};
+use crate::analytics::trends::rolling_average;
+use crate::app::error::AppError;
+use crate::domains::diary::model::DiaryAnalyticsCache;
+use crate::domains::diary::repository::fetch_entry;
+use crate::utils::time::{LogicalDay, DATE_FORMAT};
+use chrono::{Datelike, NaiveDate};
 use sqlx::SqlitePool;
+use std::collections::{BTreeMap, BTreeSet};
 
+pub async fn compute_diary_analytics(
+    pool: &SqlitePool,
+    entry_id: &str,
+    day: &LogicalDay,
+) -> Result<DiaryAnalyticsCache, AppError> {
+    let entry = fetch_entry(pool, entry_id).await?;
+    let words = load_daily_words(pool).await?;
+    diary_analytics_for(entry_id, &entry.entry_date, &words, day)
+}
+
+// Streak and rolling figures of every later day depend on this one: their rows are
+// dropped and recomputed when next read, while this day's pages are recomputed now.
 pub async fn recompute_diary_analytics(
-    _pool: &SqlitePool,
-    _entry_date: &str,
+    pool: &SqlitePool,
+    entry_date: &str,
+    day: &LogicalDay,
 ) -> Result<(), AppError> {
+    sqlx::query(
+        "DELETE FROM diary_analytics_cache WHERE diary_entry_id IN (
+            SELECT diary_entry_id FROM diary_entries WHERE entry_date >= ?
+         )",
+    )
+    .bind(entry_date)
+    .execute(pool)
+    .await?;
+
+    let entry_ids: Vec<String> = sqlx::query_scalar(
+        "SELECT diary_entry_id FROM diary_entries WHERE entry_date = ? AND is_deleted = 0",
+    )
+    .bind(entry_date)
+    .fetch_all(pool)
+    .await?;
+    let words = load_daily_words(pool).await?;
+    for entry_id in entry_ids {
+        let analytics = diary_analytics_for(&entry_id, entry_date, &words, day)?;
+        save_cache_row(pool, "diary_analytics_cache", &analytics).await?;
+    }
     Ok(())
 }
+
+// Recomputes every entry from one pass over the diary.
+pub async fn rebuild_diary_analytics(
+    pool: &SqlitePool,
+    day: &LogicalDay,
+) -> Result<usize, AppError> {
+    let entries: Vec<(String, String)> =
+        sqlx::query_as("SELECT diary_entry_id, entry_date FROM diary_entries WHERE is_deleted = 0")
+            .fetch_all(pool)
+            .await?;
+    let words = load_daily_words(pool).await?;
+    for (entry_id, entry_date) in &entries {
+        let analytics = diary_analytics_for(entry_id, entry_date, &words, day)?;
+        save_cache_row(pool, "diary_analytics_cache", &analytics).await?;
+    }
+    Ok(entries.len())
+}
+
+pub async fn fetch_diary_analytics(
+    pool: &SqlitePool,
+    entry_id: &str,
+) -> Result<Option<DiaryAnalyticsCache>, AppError> {
+    let analytics = sqlx::query_as::<_, DiaryAnalyticsCache>(
+        "SELECT * FROM diary_analytics_cache WHERE diary_entry_id = ?",
+    )
+    .bind(entry_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(analytics)
+}
+
+// Words written per day across all pages. A day counts as filled once it has any.
+async fn load_daily_words(pool: &SqlitePool) -> Result<BTreeMap<NaiveDate, i64>, AppError> {
+    let rows: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT entry_date, SUM(word_count) FROM diary_entries
+         WHERE is_deleted = 0 GROUP BY entry_date",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(date, words)| {
+            Some((NaiveDate::parse_from_str(&date, DATE_FORMAT).ok()?, words))
+        })
+        .collect())
+}
+
+fn diary_analytics_for(
+    entry_id: &str,
+    entry_date: &str,
+    words: &BTreeMap<NaiveDate, i64>,
+    day: &LogicalDay,
+) -> Result<DiaryAnalyticsCache, AppError> {
+    let date = NaiveDate::parse_from_str(entry_date, DATE_FORMAT)
+        .map_err(|_| AppError::Validation(format!("Invalid entry date {}", entry_date)))?;
+    let filled: BTreeSet<NaiveDate> = words
+        .range(..=date)
+        .filter(|(_, words)| **words > 0)
+        .map(|(day, _)| *day)
+        .collect();
+    let is_filled_day = filled.contains(&date);
+    // Only a run that reaches this very day counts, unlike a habit's open "today".
+    let current_streak_length = if is_filled_day {
+        streak_ending(&filled, date).map_or(0, |(_, length)| length)
+    } else {
+        0
+    };
+    let year_start = NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap_or(date);
+    let days_in_year = if date.leap_year() { 366.0 } else { 365.0 };
+    let filled_this_year = filled.range(year_start..=date).count();
+
+    Ok(DiaryAnalyticsCache {
+        diary_entry_id: entry_id.to_string(),
+        computed_for_date: day.today_string(),
+        computed_at: day.now().timestamp(),
+        relation_strength_score: 0.0,
+        is_counted_for_streak: is_filled_day,
+        daily_streak_index: is_filled_day.then_some(current_streak_length),
+        is_streak_breaker: !is_filled_day
+            && date
+                .pred_opt()
+                .is_some_and(|previous| filled.contains(&previous)),
+        yearly_day_index: Some(date.ordinal() as i32),
+        is_filled_day,
+        filled_day_score: if is_filled_day { 1.0 } else { 0.0 },
+        year_completion_percentage: filled_this_year as f64 / days_in_year * 100.0,
+        rolling_7_day_avg_words: rolling_average(words, date, 7),
+        rolling_30_day_avg_words: rolling_average(words, date, 30),
+        longest_streak_so_far: longest_streak(&filled),
+        current_streak_length,
+        streak_last_updated_at: Some(day.now().timestamp()),
+    })
+}
@@ -0,0 +1,72 @@
+use crate::app::context::RequestContext;
+use crate::app::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DiaryRevision {
+    pub history_id: String,
+    pub diary_entry_id: String,
+    pub event_type: String, // created, edited, deleted, restored
+    pub title: Option<String>,
+    pub content_json: String,
+    pub word_count: i32,
+    pub mood_label: Option<String>,
+    pub mood_rating: Option<i32>,
+    pub energy_level: Option<i32>,
+    pub stress_level: Option<i32>,
+    pub importance_level: i32,
+    pub event_date: String,
+    pub recorded_at: i64,
+    pub profile_id: Option<String>,
+    pub device_id: String,
+    pub session_id: String,
+}
+
+// Keeps a copy of the entry as saved, written in the same transaction as the save.
+pub async fn record_diary_revision(
+    conn: &mut SqliteConnection,
+    entry_id: &str,
+    event_type: &str,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO diary_edit_history (
+            history_id, diary_entry_id, event_type, title, content_json, word_count,
+            mood_label, mood_rating, energy_level, stress_level, importance_level,
+            event_date, recorded_at, profile_id, device_id, session_id
+        )
+        SELECT ?, diary_entry_id, ?, title, content_json, word_count,
+            mood_label, mood_rating, energy_level, stress_level, importance_level,
+            ?, ?, ?, ?, ?
+        FROM diary_entries WHERE diary_entry_id = ?",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(event_type)
+    .bind(ctx.day.today_string())
+    .bind(ctx.timestamp())
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(entry_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn list_diary_revisions(
+    pool: &SqlitePool,
+    entry_id: &str,
+) -> Result<Vec<DiaryRevision>, AppError> {
+    let revisions = sqlx::query_as::<_, DiaryRevision>(
+        "SELECT * FROM diary_edit_history WHERE diary_entry_id = ?
+         ORDER BY recorded_at ASC, rowid ASC",
+    )
+    .bind(entry_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(revisions)
+}
@@ -1,4 +1,6 @@
 ﻿pub mod analytics;
+pub mod history;
 pub mod model;
 pub mod repository;
+pub mod text;
 pub mod validation;
@@ -1,4 +1,5 @@
-﻿use serde::{Deserialize, Serialize};
+﻿use crate::utils::hashing::ContentHash;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct DiaryEntry {
@@ -85,28 +86,9 @@ pub struct DiaryEntry {
     pub social_context: Option<String>,
     pub health_context_notes: Option<String>,
     pub gratitude_items: Option<String>,
-    pub linked_habit_ids: Option<String>,
-    pub completed_habit_ids: Option<String>,
-    pub linked_goal_ids: Option<String>,
-    pub progressed_goal_ids: Option<String>,
     pub linked_task_ids: Option<String>,
-    pub linked_job_ids: Option<String>,
-    pub linked_attachment_ids: Option<String>,
     pub backlink_page_ids: Option<String>,
     pub forward_link_page_ids: Option<String>,
-    pub relation_strength_score: f64,
-    pub is_counted_for_streak: bool,
-    pub daily_streak_index: Option<i32>,
-    pub is_streak_breaker: bool,
-    pub yearly_day_index: Option<i32>,
-    pub is_filled_day: bool,
-    pub filled_day_score: f64,
-    pub year_completion_percentage: f64,
-    pub rolling_7_day_avg_words: f64,
-    pub rolling_30_day_avg_words: f64,
-    pub longest_streak_so_far: i32,
-    pub current_streak_length: i32,
-    pub streak_last_updated_at: Option<i64>,
     pub filter_date_bucket: Option<String>,
     pub filter_has_children: bool,
     pub filter_has_tags: bool,
@@ -138,6 +120,96 @@ pub struct DiaryEntry {
     pub experimental_fields: Option<String>,
 }
 
+impl ContentHash for DiaryEntry {
+    const TABLE: &'static str = "diary_entries";
+    const ID_COLUMN: &'static str = "diary_entry_id";
+    const DERIVED_FIELDS: &'static [&'static str] = &[
+        "entry_year",
+        "entry_month",
+        "entry_day",
+        "entry_week_of_year",
+        "entry_day_of_week",
+        "date_last_validated_at",
+        "root_page_id",
+        "page_depth",
+        "page_path",
+        "has_children",
+        "children_count",
+        "descendant_count",
+        "tree_version",
+        "title_plaintext",
+        "content_plaintext",
+        "content_html_cache",
+        "block_count",
+        "paragraph_count",
+        "heading_count",
+        "list_count",
+        "code_block_count",
+        "table_count",
+        "toggle_count",
+        "embed_count",
+        "inline_comment_count",
+        "last_block_edit_at",
+        "word_count",
+        "character_count",
+        "sentence_count",
+        "reading_time_minutes",
+        "writing_time_seconds",
+        "revision_count",
+        "avg_words_per_paragraph",
+        "is_empty_entry",
+        "length_category",
+        "length_score",
+        "last_length_change_at",
+        "tag_names_cache",
+        "tag_count",
+        "backlink_page_ids",
+        "filter_date_bucket",
+        "filter_has_children",
+        "filter_has_tags",
+        "filter_length_bucket",
+        "filter_mood_bucket",
+        "sort_title_normalized",
+        "sort_date_numeric",
+        "sort_last_edited_numeric",
+        "debug_notes",
+    ];
+
+    fn entity_id(&self) -> &str {
+        &self.diary_entry_id
+    }
+
+    fn stored_content_hash(&self) -> Option<&str> {
+        self.content_hash.as_deref()
+    }
+
+    fn set_content_hash(&mut self, hash: String) {
+        self.content_hash = Some(hash);
+    }
+}
+
+// Writing streaks and rolling word counts for the entry's day, derived from every
+// entry up to it.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DiaryAnalyticsCache {
+    pub diary_entry_id: String,
+    pub computed_for_date: String,
+    pub computed_at: i64,
+    pub relation_strength_score: f64,
+    pub is_counted_for_streak: bool,
+    pub daily_streak_index: Option<i32>,
+    pub is_streak_breaker: bool,
+    pub yearly_day_index: Option<i32>,
+    pub is_filled_day: bool,
+    pub filled_day_score: f64,
+    pub year_completion_percentage: f64,
+    pub rolling_7_day_avg_words: f64,
+    pub rolling_30_day_avg_words: f64,
+    pub longest_streak_so_far: i32,
+    pub current_streak_length: i32,
+    pub streak_last_updated_at: Option<i64>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateDiaryInput {
     pub entry_date: String,
@@ -1,12 +1,19 @@
-﻿use crate::app::error::AppError;
+﻿use crate::app::context::RequestContext;
+use crate::app::error::AppError;
+use crate::domains::diary::history::record_diary_revision;
 use crate::domains::diary::model::{CreateDiaryInput, DiaryEntry};
-use chrono::Utc;
+use crate::domains::diary::text::{content_plaintext, title_plaintext};
+use crate::utils::hashing::store_content_hash;
 use sqlx::SqlitePool;
 use uuid::Uuid;
 
-pub async fn insert_entry(pool: &SqlitePool, input: &CreateDiaryInput) -> Result<String, AppError> {
+pub async fn insert_entry(
+    pool: &SqlitePool,
+    input: &CreateDiaryInput,
+    ctx: &RequestContext,
+) -> Result<String, AppError> {
     let id = Uuid::new_v4().to_string();
-    let now = Utc::now().timestamp();
+    let now = ctx.timestamp();
     let date = chrono::NaiveDate::parse_from_str(&input.entry_date, "%Y-%m-%d")
         .map_err(|_| AppError::Validation("Invalid date".to_string()))?;
 
@@ -16,12 +23,15 @@ pub async fn insert_entry(pool: &SqlitePool, input: &CreateDiaryInput) -> Result
     let week = date.format("%V").to_string().parse::<i32>().unwrap_or(0);
     let dow = date.format("%u").to_string().parse::<i32>().unwrap_or(0);
 
+    let mut tx = pool.begin().await?;
     sqlx::query(
         "INSERT INTO diary_entries (
             diary_entry_id, entry_date, entry_year, entry_month, entry_day, 
             entry_week_of_year, entry_day_of_week, content_json, title,
-            created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            title_plaintext, content_plaintext,
+            created_by_profile_id, last_modified_by_profile_id, device_id, session_id,
+            app_version_created, app_version_last_modified, created_at, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
     )
     .bind(&id)
     .bind(&input.entry_date)
@@ -32,21 +42,32 @@ pub async fn insert_entry(pool: &SqlitePool, input: &CreateDiaryInput) -> Result
     .bind(dow)
     .bind(&input.content_json)
     .bind(&input.title)
+    .bind(title_plaintext(input.title.as_deref()))
+    .bind(content_plaintext(&input.content_json))
+    .bind(ctx.profile_id())
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(ctx.app_version)
+    .bind(ctx.app_version)
     .bind(now)
     .bind(now)
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
-
+    record_diary_revision(&mut tx, &id, "created", ctx).await?;
+    store_content_hash::<DiaryEntry>(&mut tx, &id).await?;
+    tx.commit().await?;
     Ok(id)
 }
 
 pub async fn fetch_entry(pool: &SqlitePool, id: &str) -> Result<DiaryEntry, AppError> {
-    let entry =
-        sqlx::query_as::<_, DiaryEntry>("SELECT * FROM diary_entries WHERE diary_entry_id = ?")
-            .bind(id)
-            .fetch_optional(pool)
-            .await?
-            .ok_or_else(|| AppError::NotFound(format!("Diary entry {} not found", id)))?;
+    let entry = sqlx::query_as::<_, DiaryEntry>(
+        "SELECT * FROM diary_entries WHERE diary_entry_id = ? AND is_deleted = 0",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::not_found("Diary entry", id))?;
 
     Ok(entry)
 }
@@ -61,49 +82,64 @@ pub async fn list_entries(pool: &SqlitePool) -> Result<Vec<DiaryEntry>, AppError
     Ok(entries)
 }
 
+// Writes the editable fields of `entry`, which is the stored row with the user's edits
+// applied.
 pub async fn update_entry(
     pool: &SqlitePool,
-    id: &str,
-    title: Option<String>,
-    content_json: &str,
-    word_count: i32,
-    mood_label: Option<String>,
-    mood_rating: Option<i32>,
-    energy_level: Option<i32>,
-    stress_level: Option<i32>,
-    importance_level: i32,
+    entry: &DiaryEntry,
+    ctx: &RequestContext,
 ) -> Result<(), AppError> {
-    let now = Utc::now().timestamp();
+    let id = entry.diary_entry_id.as_str();
+    let now = ctx.timestamp();
+    let mut tx = pool.begin().await?;
     sqlx::query(
         "UPDATE diary_entries SET 
             title = ?, 
             content_json = ?, 
+            title_plaintext = ?,
+            content_plaintext = ?,
             word_count = ?, 
             mood_label = ?, 
             mood_rating = ?, 
             energy_level = ?, 
             stress_level = ?, 
             importance_level = ?,
+            last_modified_by_profile_id = ?,
+            device_id = ?,
+            session_id = ?,
+            app_version_last_modified = ?,
             updated_at = ? 
          WHERE diary_entry_id = ?",
     )
-    .bind(title)
-    .bind(content_json)
-    .bind(word_count)
-    .bind(mood_label)
-    .bind(mood_rating)
-    .bind(energy_level)
-    .bind(stress_level)
-    .bind(importance_level)
+    .bind(&entry.title)
+    .bind(&entry.content_json)
+    .bind(title_plaintext(entry.title.as_deref()))
+    .bind(content_plaintext(&entry.content_json))
+    .bind(entry.word_count)
+    .bind(&entry.mood_label)
+    .bind(entry.mood_rating)
+    .bind(entry.energy_level)
+    .bind(entry.stress_level)
+    .bind(entry.importance_level)
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(ctx.app_version)
     .bind(now)
     .bind(id)
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
-
+    record_diary_revision(&mut tx, id, "edited", ctx).await?;
+    store_content_hash::<DiaryEntry>(&mut tx, id).await?;
+    tx.commit().await?;
     Ok(())
 }
 
-pub async fn ensure_yearly_entries(pool: &SqlitePool, year_val: i32) -> Result<(), AppError> {
+pub async fn ensure_yearly_entries(
+    pool: &SqlitePool,
+    year_val: i32,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
     use chrono::Datelike;
     let start_date = chrono::NaiveDate::from_ymd_opt(year_val, 1, 1).unwrap();
     let is_leap = (year_val % 4 == 0 && year_val % 100 != 0) || (year_val % 400 == 0);
@@ -121,19 +157,22 @@ pub async fn ensure_yearly_entries(pool: &SqlitePool, year_val: i32) -> Result<(
 
         if !exists {
             let id = Uuid::new_v4().to_string();
-            let now = Utc::now().timestamp();
+            let now = ctx.timestamp();
             let year = date.year();
             let month = date.month() as i32;
             let day = date.day() as i32;
             let week = date.iso_week().week() as i32;
             let dow = date.weekday().number_from_monday() as i32;
 
+            let mut tx = pool.begin().await?;
             sqlx::query(
                 "INSERT INTO diary_entries (
                     diary_entry_id, entry_date, entry_year, entry_month, entry_day, 
-                    entry_week_of_year, entry_day_of_week, content_json, title,
-                    is_primary_page, created_at, updated_at
-                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)",
+                    entry_week_of_year, entry_day_of_week, content_json, title, title_plaintext,
+                    is_primary_page, created_by_profile_id, last_modified_by_profile_id,
+                    device_id, session_id, app_version_created, app_version_last_modified,
+                    created_at, updated_at
+                ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?, ?, ?, ?, ?, ?, ?)",
             )
             .bind(&id)
             .bind(&date_str)
@@ -144,10 +183,20 @@ pub async fn ensure_yearly_entries(pool: &SqlitePool, year_val: i32) -> Result<(
             .bind(dow)
             .bind("[{\"type\":\"paragraph\",\"content\":[]}]") // Valid blocknote paragraph
             .bind(format!("Reflection: {}", date_str))
+            .bind(format!("Reflection: {}", date_str))
+            .bind(ctx.profile_id())
+            .bind(ctx.profile_id())
+            .bind(&ctx.device_id)
+            .bind(&ctx.session_id)
+            .bind(ctx.app_version)
+            .bind(ctx.app_version)
             .bind(now)
             .bind(now)
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
+            record_diary_revision(&mut tx, &id, "created", ctx).await?;
+            store_content_hash::<DiaryEntry>(&mut tx, &id).await?;
+            tx.commit().await?;
         }
     }
 
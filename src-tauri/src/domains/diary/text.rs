@@ -0,0 +1,67 @@
+use serde_json::Value;
+
+pub fn title_plaintext(title: Option<&str>) -> Option<String> {
+    title
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+}
+
+// The text of an editor document, one line per block. Content that is not block JSON
+// is taken as plain text.
+pub fn content_plaintext(content_json: &str) -> String {
+    let Ok(doc) = serde_json::from_str::<Value>(content_json) else {
+        return content_json.trim().to_string();
+    };
+    let mut lines = Vec::new();
+    collect_blocks(&doc, &mut lines);
+    lines.join("\n")
+}
+
+fn collect_blocks(value: &Value, lines: &mut Vec<String>) {
+    match value {
+        Value::Array(blocks) => blocks.iter().for_each(|b| collect_blocks(b, lines)),
+        Value::String(text) if !text.trim().is_empty() => lines.push(text.trim().to_string()),
+        Value::Object(block) => {
+            let mut line = String::new();
+            if let Some(content) = block.get("content") {
+                collect_inline(content, &mut line);
+            }
+            if !line.trim().is_empty() {
+                lines.push(line.trim().to_string());
+            }
+            if let Some(children) = block.get("children") {
+                collect_blocks(children, lines);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Inline content is a string, a list of styled text and links, or for tables a set of
+// rows whose cells hold inline content again.
+fn collect_inline(value: &Value, line: &mut String) {
+    match value {
+        Value::String(text) => line.push_str(text),
+        Value::Array(items) => items.iter().for_each(|i| collect_inline(i, line)),
+        Value::Object(item) => {
+            if let Some(Value::String(text)) = item.get("text") {
+                line.push_str(text);
+            }
+            if let Some(inner) = item.get("content") {
+                collect_inline(inner, line);
+            }
+            for key in ["rows", "cells"] {
+                if let Some(Value::Array(parts)) = item.get(key) {
+                    for part in parts {
+                        if !line.is_empty() && !line.ends_with(' ') {
+                            line.push(' ');
+                        }
+                        collect_inline(part, line);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,40 +1,42 @@
-﻿use crate::app::error::AppError;
+﻿use crate::app::error::{AppError, FieldErrors};
 use crate::domains::diary::model::CreateDiaryInput;
 use chrono::NaiveDate;
 
-pub fn validate_create(input: &CreateDiaryInput) -> Result<(), AppError> {
-    let date = NaiveDate::parse_from_str(&input.entry_date, "%Y-%m-%d").map_err(|_| {
-        AppError::Validation("Invalid date format. Expected YYYY-MM-DD.".to_string())
-    })?;
+pub fn validate_create(
+    input: &CreateDiaryInput,
+    start_year: i32,
+    today: NaiveDate,
+) -> Result<(), AppError> {
+    let mut errors = FieldErrors::new();
 
-    if date.format("%Y").to_string().parse::<i32>().unwrap_or(0) < 2026 {
-        return Err(AppError::Validation(
-            "Diary entries must start from 2026 onwards.".to_string(),
-        ));
-    }
-
-    let today = chrono::Utc::now().naive_utc().date();
-    if date > today {
-        return Err(AppError::Validation(
-            "Future dates are currently locked for new entries.".to_string(),
-        ));
+    match NaiveDate::parse_from_str(&input.entry_date, "%Y-%m-%d") {
+        Err(_) => errors.add("entry_date", "Invalid date format. Expected YYYY-MM-DD."),
+        Ok(date) if date.format("%Y").to_string().parse::<i32>().unwrap_or(0) < start_year => {
+            errors.add(
+                "entry_date",
+                format!("Diary entries must start from {} onwards.", start_year),
+            )
+        }
+        Ok(date) if date > today => errors.add(
+            "entry_date",
+            "Future dates are currently locked for new entries.",
+        ),
+        Ok(_) => {}
     }
 
     if input.content_json.is_empty() {
-        return Err(AppError::Validation("Content cannot be empty.".to_string()));
+        errors.add("content_json", "Content cannot be empty.");
     }
-    Ok(())
+    errors.into_result()
 }
 
-pub fn validate_update(entry_date: &str) -> Result<(), AppError> {
-    let date = NaiveDate::parse_from_str(entry_date, "%Y-%m-%d")
-        .map_err(|_| AppError::Validation("Invalid entry date format.".to_string()))?;
+pub fn validate_update(entry_date: &str, today: NaiveDate) -> Result<(), AppError> {
+    let mut errors = FieldErrors::new();
 
-    let today = chrono::Utc::now().naive_utc().date();
-    if date > today {
-        return Err(AppError::Validation(
-            "Future entries cannot be modified.".to_string(),
-        ));
+    match NaiveDate::parse_from_str(entry_date, "%Y-%m-%d") {
+        Err(_) => errors.add("entry_date", "Invalid entry date format."),
+        Ok(date) if date > today => errors.add("entry_date", "Future entries cannot be modified."),
+        Ok(_) => {}
     }
-    Ok(())
+    errors.into_result()
 }
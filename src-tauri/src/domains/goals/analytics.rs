@@ -1,7 +1,115 @@
+use crate::analytics::cache::save_cache_row;
 use crate::app::error::AppError;
+use crate::domains::goals::events::list_goal_events;
+use crate::domains::goals::model::GoalAnalyticsCache;
+use crate::domains::goals::repository::fetch_goal;
+use crate::utils::time::{LogicalDay, DATE_FORMAT};
+use chrono::{Duration, NaiveDate};
 use sqlx::SqlitePool;
 
-pub async fn recompute_goal_analytics(_pool: &SqlitePool, _goal_id: &str) -> Result<(), AppError> {
-    // Logic for progress metrics, health scores, etc.
-    Ok(())
+// Built from the goal's dates and its progress events.
+pub async fn compute_goal_analytics(
+    pool: &SqlitePool,
+    goal_id: &str,
+    day: &LogicalDay,
+) -> Result<GoalAnalyticsCache, AppError> {
+    let goal = fetch_goal(pool, goal_id).await?;
+    let events = list_goal_events(pool, goal_id).await?;
+    let today = day.today();
+    let parse = |date: &str| NaiveDate::parse_from_str(date, DATE_FORMAT).ok();
+
+    let start = goal
+        .goal_start_date
+        .as_deref()
+        .and_then(parse)
+        .or_else(|| parse(&goal.goal_created_date));
+    let target = goal.goal_target_date.as_deref().and_then(parse);
+    let finished = goal.progress_percentage >= 100.0 || goal.goal_status == "completed";
+
+    let progress_delta = match events.as_slice() {
+        [.., previous, last] => last.progress_percentage - previous.progress_percentage,
+        _ => 0.0,
+    };
+    // Percentage points per day since the first event.
+    let progress_velocity = events
+        .first()
+        .and_then(|first| Some((first, parse(&first.event_date)?)))
+        .map_or(0.0, |(first, since)| {
+            let days = (today - since).num_days().max(1);
+            (goal.progress_percentage - first.progress_percentage) / days as f64
+        });
+    let expected_completion = if finished || progress_velocity <= 0.0 {
+        None
+    } else {
+        let days_left = ((100.0 - goal.progress_percentage) / progress_velocity).ceil();
+        today.checked_add_signed(Duration::days(days_left as i64))
+    };
+    let progress_is_on_track = finished
+        || match (target, expected_completion) {
+            (Some(target), Some(expected)) => expected <= target,
+            (Some(target), None) => today <= target,
+            (None, _) => true,
+        };
+
+    Ok(GoalAnalyticsCache {
+        goal_id: goal_id.to_string(),
+        computed_for_date: day.today_string(),
+        computed_at: day.now().timestamp(),
+        goal_days_remaining: target.map(|target| (target - today).num_days().max(0) as i32),
+        goal_days_elapsed: start.map(|start| (today - start).num_days().max(0) as i32),
+        goal_overdue_days: target.map(|target| {
+            if finished {
+                0
+            } else {
+                (today - target).num_days().max(0) as i32
+            }
+        }),
+        progress_delta,
+        progress_velocity,
+        progress_acceleration: 0.0,
+        progress_is_on_track,
+        progress_confidence_interval: None,
+        dependency_strength_score: 0.0,
+        habit_progress_aggregate: 0.0,
+        task_progress_aggregate: 0.0,
+        completion_probability: 0.0,
+        failure_probability: 0.0,
+        burnout_risk_score: 0.0,
+        confidence_trend: 0.0,
+        consistency_index: 0.0,
+        momentum_score: 0.0,
+        volatility_score: 0.0,
+        effort_to_reward_ratio: 0.0,
+        expected_completion_date: expected_completion
+            .map(|date| date.format(DATE_FORMAT).to_string()),
+        deviation_from_plan: 0.0,
+        goal_health_status: None,
+        risk_level: None,
+        risk_factors: None,
+        reflection_sentiment_score: 0.0,
+    })
+}
+
+pub async fn recompute_goal_analytics(
+    pool: &SqlitePool,
+    goal_id: &str,
+    day: &LogicalDay,
+) -> Result<GoalAnalyticsCache, AppError> {
+    let analytics = compute_goal_analytics(pool, goal_id, day).await?;
+    save_cache_row(pool, "goal_analytics_cache", &analytics).await?;
+    Ok(analytics)
+}
+
+pub async fn fetch_goal_analytics(
+    pool: &SqlitePool,
+    goal_id: &str,
+) -> Result<Option<GoalAnalyticsCache>, AppError> {
+    let analytics = sqlx::query_as::<_, GoalAnalyticsCache>(
+        "SELECT * FROM goal_analytics_cache WHERE goal_id = ?",
+    )
+    .bind(goal_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(analytics)
 }
@@ -0,0 +1,66 @@
+use crate::app::context::RequestContext;
+use crate::app::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct GoalProgressEvent {
+    pub event_id: String,
+    pub goal_id: String,
+    pub event_type: String, // created, renamed, deleted, restored
+    pub goal_title: String,
+    pub goal_status: String,
+    pub current_value: f64,
+    pub progress_percentage: f64,
+    pub event_date: String,
+    pub recorded_at: i64,
+    pub profile_id: Option<String>,
+    pub device_id: String,
+    pub session_id: String,
+}
+
+// Snapshots the goal as it now stands. Call it on the connection that made the change,
+// before committing, so the event and the change land together.
+pub async fn record_goal_event(
+    conn: &mut SqliteConnection,
+    goal_id: &str,
+    event_type: &str,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO goal_progress_events (
+            event_id, goal_id, event_type, goal_title, goal_status, current_value,
+            progress_percentage, event_date, recorded_at, profile_id, device_id, session_id
+        )
+        SELECT ?, goal_id, ?, goal_title, goal_status, current_value,
+            progress_percentage, ?, ?, ?, ?, ?
+        FROM goals WHERE goal_id = ?",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(event_type)
+    .bind(ctx.day.today_string())
+    .bind(ctx.timestamp())
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(goal_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn list_goal_events(
+    pool: &SqlitePool,
+    goal_id: &str,
+) -> Result<Vec<GoalProgressEvent>, AppError> {
+    let events = sqlx::query_as::<_, GoalProgressEvent>(
+        "SELECT * FROM goal_progress_events WHERE goal_id = ? ORDER BY recorded_at ASC, rowid ASC",
+    )
+    .bind(goal_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(events)
+}
@@ -1,4 +1,5 @@
 pub mod analytics;
+pub mod events;
 pub mod model;
 pub mod repository;
 pub mod validation;
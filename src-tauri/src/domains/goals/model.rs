@@ -1,3 +1,4 @@
+use crate::utils::hashing::ContentHash;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -16,6 +17,9 @@ pub struct Goal {
     pub goal_icon_emoji: Option<String>,
     pub goal_color: Option<String>,
     pub goal_visibility: String, // active, paused, archived
+    pub is_deleted: bool,
+    pub deleted_at: Option<i64>,
+    pub deleted_reason: Option<String>,
     pub goal_status: String,     // not_started, in_progress, completed, failed, abandoned
     pub goal_priority_level: i32,
     pub goal_importance_weight: f64,
@@ -35,9 +39,6 @@ pub struct Goal {
     pub goal_next_review_at: Option<i64>,
     pub goal_time_zone: Option<String>,
     pub goal_is_time_bound: bool,
-    pub goal_days_remaining: Option<i32>,
-    pub goal_days_elapsed: Option<i32>,
-    pub goal_overdue_days: Option<i32>,
 
     // 3. MEASUREMENT, METRICS & SUCCESS CRITERIA
     pub success_metric_type: String, // numeric, boolean, milestone, composite
@@ -50,12 +51,7 @@ pub struct Goal {
     pub max_allowed_value: Option<f64>,
     pub measurement_operator: String, // >=, <=, ==
     pub progress_percentage: f64,
-    pub progress_delta: f64,
-    pub progress_velocity: f64,
-    pub progress_acceleration: f64,
     pub progress_last_updated_at: Option<i64>,
-    pub progress_is_on_track: bool,
-    pub progress_confidence_interval: Option<f64>,
 
     // 4. MILESTONES, SUBGOALS & PHASES
     pub milestone_ids: Option<String>,
@@ -71,12 +67,7 @@ pub struct Goal {
     pub milestone_weight_distribution: Option<String>,
 
     // 5. DEPENDENCIES, BLOCKERS & UNLOCK LOGIC
-    pub prerequisite_goal_ids: Option<String>,
-    pub dependent_goal_ids: Option<String>,
-    pub blocked_by_goal_ids: Option<String>,
-    pub unlocks_goal_ids: Option<String>,
     pub dependency_type: String, // hard, soft
-    pub dependency_strength_score: f64,
     pub unlock_condition_type: Option<String>,
     pub unlock_threshold_value: Option<f64>,
     pub unlock_evaluation_frequency: Option<String>,
@@ -85,35 +76,15 @@ pub struct Goal {
     pub unlock_auto_activate: bool,
 
     // 6. HABIT, TASK, DIARY & JOB INTEGRATION
-    pub linked_habit_ids: Option<String>,
     pub habit_contribution_weights: Option<String>,
     pub habit_completion_required: bool,
-    pub habit_progress_aggregate: f64,
     pub habit_dependency_mode: String,
     pub linked_task_ids: Option<String>,
     pub task_completion_required: bool,
-    pub task_progress_aggregate: f64,
     pub task_dependency_mode: String,
-    pub linked_diary_page_ids: Option<String>,
     pub diary_reflection_required: bool,
-    pub linked_job_ids: Option<String>,
     pub job_progress_dependency: Option<String>,
 
-    // 7. ANALYTICS, HEALTH & RISK (DERIVED — READ ONLY)
-    pub completion_probability: f64,
-    pub failure_probability: f64,
-    pub burnout_risk_score: f64,
-    pub confidence_trend: f64,
-    pub consistency_index: f64,
-    pub momentum_score: f64,
-    pub volatility_score: f64,
-    pub effort_to_reward_ratio: f64,
-    pub expected_completion_date: Option<String>,
-    pub deviation_from_plan: f64,
-    pub goal_health_status: Option<String>,
-    pub risk_level: Option<String>,
-    pub risk_factors: Option<String>,
-
     // 8. MOTIVATION, PSYCHOLOGY & IDENTITY
     pub motivation_type: Option<String>, // intrinsic, extrinsic
     pub motivation_reason: Option<String>,
@@ -165,7 +136,6 @@ pub struct Goal {
     // 12. REVIEW, REFLECTION & LEARNING
     pub last_reflection_text: Option<String>,
     pub reflection_history: Option<String>,
-    pub reflection_sentiment_score: f64,
     pub lessons_learned: Option<String>,
     pub obstacles_encountered: Option<String>,
     pub strategy_adjustments: Option<String>,
@@ -206,6 +176,70 @@ pub struct Goal {
     pub experimental_fields: Option<String>,
 }
 
+impl ContentHash for Goal {
+    const TABLE: &'static str = "goals";
+    const ID_COLUMN: &'static str = "goal_id";
+    const DERIVED_FIELDS: &'static [&'static str] = &[
+        "goal_slug",
+        "progress_percentage",
+        "progress_last_updated_at",
+        "milestones_completed_count",
+        "milestone_completion_percent",
+        "next_milestone_id",
+        "last_completed_milestone_id",
+        "unlock_last_evaluated_at",
+        "unlock_is_ready",
+        "review_count",
+        "escalation_last_triggered_at",
+    ];
+
+    fn entity_id(&self) -> &str {
+        &self.goal_id
+    }
+
+    fn stored_content_hash(&self) -> Option<&str> {
+        self.content_hash.as_deref()
+    }
+
+    fn set_content_hash(&mut self, hash: String) {
+        self.content_hash = Some(hash);
+    }
+}
+
+// Progress trend and schedule figures for a goal, derived from its progress events
+// and dates.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct GoalAnalyticsCache {
+    pub goal_id: String,
+    pub computed_for_date: String,
+    pub computed_at: i64,
+    pub goal_days_remaining: Option<i32>,
+    pub goal_days_elapsed: Option<i32>,
+    pub goal_overdue_days: Option<i32>,
+    pub progress_delta: f64,
+    pub progress_velocity: f64,
+    pub progress_acceleration: f64,
+    pub progress_is_on_track: bool,
+    pub progress_confidence_interval: Option<f64>,
+    pub dependency_strength_score: f64,
+    pub habit_progress_aggregate: f64,
+    pub task_progress_aggregate: f64,
+    pub completion_probability: f64,
+    pub failure_probability: f64,
+    pub burnout_risk_score: f64,
+    pub confidence_trend: f64,
+    pub consistency_index: f64,
+    pub momentum_score: f64,
+    pub volatility_score: f64,
+    pub effort_to_reward_ratio: f64,
+    pub expected_completion_date: Option<String>,
+    pub deviation_from_plan: f64,
+    pub goal_health_status: Option<String>,
+    pub risk_level: Option<String>,
+    pub risk_factors: Option<String>,
+    pub reflection_sentiment_score: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateGoalInput {
     pub goal_title: String,
@@ -214,3 +248,8 @@ pub struct CreateGoalInput {
     pub goal_description: Option<String>,
     pub goal_target_date: Option<String>,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenameGoalInput {
+    pub goal_title: String,
+}
@@ -1,25 +1,30 @@
+use crate::app::context::RequestContext;
 use crate::app::error::AppError;
-use crate::domains::goals::model::{CreateGoalInput, Goal};
-use chrono::{Local, Utc};
+use crate::db::slugs::{resolve_slug, retire_slug, unique_slug, GOAL_SLUGS};
+use crate::domains::goals::events::record_goal_event;
+use crate::domains::goals::model::{CreateGoalInput, Goal, RenameGoalInput};
+use crate::utils::hashing::store_content_hash;
 use sqlx::SqlitePool;
 use uuid::Uuid;
 
-pub async fn insert_goal(pool: &SqlitePool, input: &CreateGoalInput) -> Result<String, AppError> {
+pub async fn insert_goal(
+    pool: &SqlitePool,
+    input: &CreateGoalInput,
+    ctx: &RequestContext,
+) -> Result<String, AppError> {
     let id = Uuid::new_v4().to_string();
-    let slug = format!(
-        "{}-{}",
-        input.goal_title.to_lowercase().replace(" ", "-"),
-        Uuid::new_v4().to_string()[..8].to_string()
-    );
-    let now_ts = Utc::now().timestamp();
-    let today = Local::now().format("%Y-%m-%d").to_string();
+    let now_ts = ctx.timestamp();
+    let today = ctx.day.today_string();
 
+    let mut tx = pool.begin().await?;
+    let slug = unique_slug(&mut tx, &GOAL_SLUGS, &input.goal_title, &id).await?;
     sqlx::query(
         "INSERT INTO goals (
             goal_id, goal_slug, goal_title, goal_type, goal_category, 
             goal_description, goal_target_date, goal_created_date,
-            created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            created_by_profile_id, last_modified_by_profile_id, device_id, session_id,
+            app_version_created, app_version_last_modified, created_at, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
     )
     .bind(&id)
     .bind(&slug)
@@ -29,27 +34,79 @@ pub async fn insert_goal(pool: &SqlitePool, input: &CreateGoalInput) -> Result<S
     .bind(&input.goal_description)
     .bind(&input.goal_target_date)
     .bind(today)
+    .bind(ctx.profile_id())
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(ctx.app_version)
+    .bind(ctx.app_version)
     .bind(now_ts)
     .bind(now_ts)
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
-
+    record_goal_event(&mut tx, &id, "created", ctx).await?;
+    store_content_hash::<Goal>(&mut tx, &id).await?;
+    tx.commit().await?;
     Ok(id)
 }
 
+// Renaming moves the goal to a slug for the new title; the old slug stays behind as
+// an alias so existing links still find it.
+pub async fn rename_goal(
+    pool: &SqlitePool,
+    id: &str,
+    input: &RenameGoalInput,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    let goal = fetch_goal(pool, id).await?;
+
+    let mut tx = pool.begin().await?;
+    let slug = unique_slug(&mut tx, &GOAL_SLUGS, &input.goal_title, id).await?;
+    sqlx::query(
+        "UPDATE goals SET
+            goal_title = ?, goal_slug = ?, last_modified_by_profile_id = ?, device_id = ?,
+            session_id = ?, app_version_last_modified = ?, updated_at = ?
+        WHERE goal_id = ?",
+    )
+    .bind(&input.goal_title)
+    .bind(&slug)
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(ctx.app_version)
+    .bind(ctx.timestamp())
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    retire_slug(&mut tx, &GOAL_SLUGS, id, ctx, &goal.goal_slug, &slug).await?;
+    record_goal_event(&mut tx, id, "renamed", ctx).await?;
+    store_content_hash::<Goal>(&mut tx, id).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
 pub async fn fetch_goal(pool: &SqlitePool, id: &str) -> Result<Goal, AppError> {
-    let goal = sqlx::query_as::<_, Goal>("SELECT * FROM goals WHERE goal_id = ?")
-        .bind(id)
-        .fetch_optional(pool)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Goal {} not found", id)))?;
+    let goal =
+        sqlx::query_as::<_, Goal>("SELECT * FROM goals WHERE goal_id = ? AND is_deleted = 0")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found("Goal", id))?;
 
     Ok(goal)
 }
 
+pub async fn fetch_goal_by_slug(pool: &SqlitePool, slug: &str) -> Result<Goal, AppError> {
+    let id = resolve_slug(pool, &GOAL_SLUGS, slug)
+        .await?
+        .ok_or_else(|| AppError::not_found("Goal", slug))?;
+
+    fetch_goal(pool, &id).await
+}
+
 pub async fn list_goals(pool: &SqlitePool) -> Result<Vec<Goal>, AppError> {
     let goals = sqlx::query_as::<_, Goal>(
-        "SELECT * FROM goals WHERE goal_status != 'completed' AND goal_visibility = 'active' ORDER BY created_at DESC"
+        "SELECT * FROM goals WHERE goal_status != 'completed' AND goal_visibility = 'active' AND is_deleted = 0 ORDER BY created_at DESC"
     )
     .fetch_all(pool)
     .await?;
@@ -1,20 +1,33 @@
-use crate::app::error::AppError;
-use crate::domains::goals::model::CreateGoalInput;
+use crate::app::error::{AppError, FieldErrors};
+use crate::domains::goals::model::{CreateGoalInput, RenameGoalInput};
 
 pub fn validate_create_goal(input: &CreateGoalInput) -> Result<(), AppError> {
+    let mut errors = FieldErrors::new();
+
     if input.goal_title.trim().is_empty() {
-        return Err(AppError::Validation(
-            "Goal title cannot be empty.".to_string(),
-        ));
+        errors.add("goal_title", "Goal title cannot be empty.");
     }
 
     let valid_types = vec!["outcome", "process", "performance", "learning"];
     if !valid_types.contains(&input.goal_type.as_str()) {
-        return Err(AppError::Validation(format!(
-            "Invalid goal type: {}. Must be one of {:?}",
-            input.goal_type, valid_types
-        )));
+        errors.add(
+            "goal_type",
+            format!(
+                "Invalid goal type: {}. Must be one of {:?}",
+                input.goal_type, valid_types
+            ),
+        );
+    }
+
+    errors.into_result()
+}
+
+pub fn validate_rename_goal(input: &RenameGoalInput) -> Result<(), AppError> {
+    let mut errors = FieldErrors::new();
+
+    if input.goal_title.trim().is_empty() {
+        errors.add("goal_title", "Goal title cannot be empty.");
     }
 
-    Ok(())
+    errors.into_result()
 }
@@ -1,10 +1,123 @@
+use crate::analytics::cache::save_cache_row;
+use crate::analytics::streaks::{longest_streak, streak_ending};
+use crate::analytics::trends::ratio;
 use crate::app::error::AppError;
+use crate::domains::habits::habit_log::HabitLog;
+use crate::domains::habits::model::HabitAnalyticsCache;
+use crate::domains::habits::repository::{fetch_habit, get_habit_logs_for_date_range};
+use crate::utils::time::{LogicalDay, DATE_FORMAT};
+use chrono::{Duration, NaiveDate};
 use sqlx::SqlitePool;
+use std::collections::BTreeSet;
+
+// Everything here comes from the habit's logs, so the row can be rebuilt at any time.
+pub async fn compute_habit_analytics(
+    pool: &SqlitePool,
+    habit_id: &str,
+    day: &LogicalDay,
+) -> Result<HabitAnalyticsCache, AppError> {
+    fetch_habit(pool, habit_id).await?;
+    let today = day.today();
+    let logs =
+        get_habit_logs_for_date_range(pool, habit_id, "0000-01-01", &day.today_string()).await?;
+
+    let completed = completed_days(&logs);
+    let streak = streak_ending(&completed, today);
+    let count = |status: &str| logs.iter().filter(|log| log.status == status).count();
+    let last_at = |status: &str| {
+        logs.iter()
+            .filter(|log| log.status == status)
+            .map(|log| log.logged_at)
+            .max()
+    };
+    let rate_since = |days: i64| {
+        let start = (today - Duration::days(days - 1))
+            .format(DATE_FORMAT)
+            .to_string();
+        let window: Vec<&HabitLog> = logs.iter().filter(|log| log.log_date >= start).collect();
+        let done = window
+            .iter()
+            .filter(|log| log.status == "completed")
+            .count();
+        ratio(done, window.len())
+    };
+    let total_completions = count("completed");
+
+    Ok(HabitAnalyticsCache {
+        habit_id: habit_id.to_string(),
+        computed_for_date: day.today_string(),
+        computed_at: day.now().timestamp(),
+        streak_current: streak.map_or(0, |(_, length)| length),
+        streak_longest: longest_streak(&completed),
+        streak_best_window: 0,
+        streak_start_date: streak.map(|(start, _)| start.format(DATE_FORMAT).to_string()),
+        streak_last_updated_at: Some(day.now().timestamp()),
+        streak_break_date: None,
+        checkin_last_value: logs.last().and_then(|log| log.value),
+        total_completions: total_completions as i32,
+        total_failures: count("failed") as i32,
+        total_skips: count("skipped") as i32,
+        completion_rate_lifetime: ratio(total_completions, logs.len()),
+        completion_rate_30d: rate_since(30),
+        completion_rate_90d: rate_since(90),
+        completion_rate_365d: rate_since(365),
+        consistency_index: 0.0,
+        variance_score: 0.0,
+        momentum_score: 0.0,
+        habit_strength_score: 0.0,
+        habit_entropy_score: 0.0,
+        habit_predictability_score: 0.0,
+        gamification_points: 0,
+        gamification_level: 1,
+        gamification_xp: 0,
+        gamification_badges: None,
+        dependency_strength_score: 0.0,
+        is_on_track: true,
+        projected_completion_rate: 0.0,
+        projected_streak_length: 0,
+        habit_health_status: None,
+        burnout_risk_score: 0.0,
+        relapse_risk_score: 0.0,
+        success_probability: 0.0,
+        last_completed_at: last_at("completed"),
+        last_failed_at: last_at("failed"),
+        last_skipped_at: last_at("skipped"),
+    })
+}
 
 pub async fn recompute_habit_analytics(
-    _pool: &SqlitePool,
-    _habit_id: &str,
-) -> Result<(), AppError> {
-    // Logic for streaks, completion rates, etc.
-    Ok(())
+    pool: &SqlitePool,
+    habit_id: &str,
+    day: &LogicalDay,
+) -> Result<HabitAnalyticsCache, AppError> {
+    let analytics = compute_habit_analytics(pool, habit_id, day).await?;
+    save_cache_row(pool, "habit_analytics_cache", &analytics).await?;
+    Ok(analytics)
+}
+
+pub async fn fetch_habit_analytics(
+    pool: &SqlitePool,
+    habit_id: &str,
+) -> Result<Option<HabitAnalyticsCache>, AppError> {
+    let analytics = sqlx::query_as::<_, HabitAnalyticsCache>(
+        "SELECT * FROM habit_analytics_cache WHERE habit_id = ?",
+    )
+    .bind(habit_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(analytics)
+}
+
+// Consecutive completed days ending on `today`, or ending yesterday while today has
+// not been logged yet, so an open day doesn't break the streak.
+pub fn current_streak(logs: &[HabitLog], today: NaiveDate) -> i32 {
+    streak_ending(&completed_days(logs), today).map_or(0, |(_, length)| length)
+}
+
+fn completed_days(logs: &[HabitLog]) -> BTreeSet<NaiveDate> {
+    logs.iter()
+        .filter(|log| log.status == "completed")
+        .filter_map(|log| NaiveDate::parse_from_str(&log.log_date, DATE_FORMAT).ok())
+        .collect()
 }
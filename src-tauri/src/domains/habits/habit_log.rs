@@ -21,7 +21,7 @@ pub struct HabitLog {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateHabitLogInput {
     pub habit_id: String,
-    pub log_date: String,
+    pub log_date: Option<String>, // defaults to the current logical day
     pub value: Option<f64>,
     pub status: String,
     pub note: Option<String>,
@@ -29,3 +29,8 @@ pub struct CreateHabitLogInput {
     pub energy_level: Option<i32>,
 }
 
+// A habit has one log per day, so the day names the log: devices that log the same day
+// write the same record.
+pub fn habit_log_id(habit_id: &str, log_date: &str) -> String {
+    format!("{}:{}", habit_id, log_date)
+}
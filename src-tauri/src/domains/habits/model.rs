@@ -1,3 +1,4 @@
+use crate::utils::hashing::ContentHash;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -15,6 +16,9 @@ pub struct Habit {
     pub habit_icon_emoji: Option<String>,
     pub habit_color: Option<String>,
     pub habit_visibility: String, // active, paused, archived
+    pub is_deleted: bool,
+    pub deleted_at: Option<i64>,
+    pub deleted_reason: Option<String>,
     pub habit_priority_level: i32,
     pub habit_difficulty_level: i32,
     pub habit_motivation_score: f64,
@@ -65,7 +69,6 @@ pub struct Habit {
     pub checkin_mode: String, // manual, auto, mixed
     pub checkin_input_type: Option<String>,
     pub checkin_default_value: Option<f64>,
-    pub checkin_last_value: Option<f64>,
     pub checkin_notes: Option<String>,
     pub checkin_media_allowed: bool,
     pub checkin_voice_allowed: bool,
@@ -81,36 +84,15 @@ pub struct Habit {
     pub checkin_retry_limit: i32,
 
     // 5. STREAK LOGIC
-    pub streak_current: i32,
-    pub streak_longest: i32,
-    pub streak_best_window: i32,
-    pub streak_start_date: Option<String>,
-    pub streak_last_updated_at: Option<i64>,
     pub streak_freeze_available: i32,
     pub streak_freeze_used: i32,
     pub streak_freeze_remaining: i32,
     pub streak_break_reason: Option<String>,
-    pub streak_break_date: Option<String>,
     pub streak_repair_allowed: bool,
     pub streak_repair_used: i32,
     pub streak_decay_model: Option<String>,
     pub streak_weight: f64,
 
-    // 6. PERFORMANCE METRICS (DERIVED)
-    pub total_completions: i32,
-    pub total_failures: i32,
-    pub total_skips: i32,
-    pub completion_rate_lifetime: f64,
-    pub completion_rate_30d: f64,
-    pub completion_rate_90d: f64,
-    pub completion_rate_365d: f64,
-    pub consistency_index: f64,
-    pub variance_score: f64,
-    pub momentum_score: f64,
-    pub habit_strength_score: f64,
-    pub habit_entropy_score: f64,
-    pub habit_predictability_score: f64,
-
     // 7. REWARDS & GAMIFICATION
     pub reward_type: Option<String>,
     pub reward_value: Option<f64>,
@@ -121,10 +103,6 @@ pub struct Habit {
     pub punishment_value: Option<f64>,
     pub punishment_trigger: Option<String>,
     pub loss_aversion_enabled: bool,
-    pub gamification_points: i32,
-    pub gamification_level: i32,
-    pub gamification_xp: i32,
-    pub gamification_badges: Option<String>,
 
     // 8. MOTIVATION & PSYCHOLOGY
     pub motivation_type: Option<String>, // intrinsic, extrinsic
@@ -140,32 +118,13 @@ pub struct Habit {
     pub temptation_bundling_enabled: bool,
 
     // 9. DEPENDENCIES & RELATIONS
-    pub prerequisite_habit_ids: Option<String>,
-    pub dependent_habit_ids: Option<String>,
-    pub blocked_by_habit_ids: Option<String>,
-    pub unlocks_habit_ids: Option<String>,
-    pub linked_goal_ids: Option<String>,
     pub contributing_goal_weight: f64,
     pub linked_task_ids: Option<String>,
-    pub linked_diary_page_ids: Option<String>,
-    pub linked_job_ids: Option<String>,
-    pub dependency_strength_score: f64,
-
-    // 10. ANALYTICS (READ-ONLY)
-    pub is_on_track: bool,
-    pub projected_completion_rate: f64,
-    pub projected_streak_length: i32,
-    pub habit_health_status: Option<String>,
-    pub burnout_risk_score: f64,
-    pub relapse_risk_score: f64,
-    pub success_probability: f64,
 
     // 11. AUDIT & SYSTEM
+    pub habit_created_date: Option<String>, // logical day it was added
     pub created_at: i64,
     pub updated_at: i64,
-    pub last_completed_at: Option<i64>,
-    pub last_failed_at: Option<i64>,
-    pub last_skipped_at: Option<i64>,
     pub created_by_profile_id: Option<String>,
     pub last_modified_by_profile_id: Option<String>,
     pub device_id: Option<String>,
@@ -181,6 +140,73 @@ pub struct Habit {
     pub experimental_fields: Option<String>,
 }
 
+impl ContentHash for Habit {
+    const TABLE: &'static str = "habits";
+    const ID_COLUMN: &'static str = "habit_id";
+    const DERIVED_FIELDS: &'static [&'static str] = &[
+        "habit_slug",
+        "streak_freeze_used",
+        "streak_freeze_remaining",
+        "streak_break_reason",
+        "streak_repair_used",
+    ];
+
+    fn entity_id(&self) -> &str {
+        &self.habit_id
+    }
+
+    fn stored_content_hash(&self) -> Option<&str> {
+        self.content_hash.as_deref()
+    }
+
+    fn set_content_hash(&mut self, hash: String) {
+        self.content_hash = Some(hash);
+    }
+}
+
+// Streaks, totals and completion rates derived from the habit's logs.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct HabitAnalyticsCache {
+    pub habit_id: String,
+    pub computed_for_date: String,
+    pub computed_at: i64,
+    pub streak_current: i32,
+    pub streak_longest: i32,
+    pub streak_best_window: i32,
+    pub streak_start_date: Option<String>,
+    pub streak_last_updated_at: Option<i64>,
+    pub streak_break_date: Option<String>,
+    pub checkin_last_value: Option<f64>,
+    pub total_completions: i32,
+    pub total_failures: i32,
+    pub total_skips: i32,
+    pub completion_rate_lifetime: f64,
+    pub completion_rate_30d: f64,
+    pub completion_rate_90d: f64,
+    pub completion_rate_365d: f64,
+    pub consistency_index: f64,
+    pub variance_score: f64,
+    pub momentum_score: f64,
+    pub habit_strength_score: f64,
+    pub habit_entropy_score: f64,
+    pub habit_predictability_score: f64,
+    pub gamification_points: i32,
+    pub gamification_level: i32,
+    pub gamification_xp: i32,
+    pub gamification_badges: Option<String>,
+    pub dependency_strength_score: f64,
+    pub is_on_track: bool,
+    pub projected_completion_rate: f64,
+    pub projected_streak_length: i32,
+    pub habit_health_status: Option<String>,
+    pub burnout_risk_score: f64,
+    pub relapse_risk_score: f64,
+    pub success_probability: f64,
+    pub last_completed_at: Option<i64>,
+    pub last_failed_at: Option<i64>,
+    pub last_skipped_at: Option<i64>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateHabitInput {
     pub habit_name: String,
@@ -1,20 +1,30 @@
+use crate::app::context::RequestContext;
 use crate::app::error::AppError;
-use crate::domains::habits::habit_log::{CreateHabitLogInput, HabitLog};
+use crate::domains::habits::habit_log::{habit_log_id, CreateHabitLogInput, HabitLog};
 use crate::domains::habits::model::{CreateHabitInput, Habit};
-use chrono::Utc;
+use crate::utils::hashing::store_content_hash;
+use crate::utils::time::DATE_FORMAT;
+use chrono::DateTime;
 use sqlx::{Row, SqlitePool};
 use uuid::Uuid;
 
-pub async fn insert_habit(pool: &SqlitePool, input: &CreateHabitInput) -> Result<String, AppError> {
+pub async fn insert_habit(
+    pool: &SqlitePool,
+    input: &CreateHabitInput,
+    ctx: &RequestContext,
+) -> Result<String, AppError> {
     let id = Uuid::new_v4().to_string();
-    let now = Utc::now().timestamp();
+    let now = ctx.timestamp();
+    let today = ctx.day.today_string();
 
+    let mut tx = pool.begin().await?;
     sqlx::query(
         "INSERT INTO habits (
             habit_id, habit_name, habit_type, habit_description, 
-            habit_icon_emoji, habit_color, schedule_type,
-            created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            habit_icon_emoji, habit_color, schedule_type, habit_created_date,
+            created_by_profile_id, last_modified_by_profile_id, device_id, session_id,
+            app_version_created, app_version_last_modified, created_at, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
     )
     .bind(&id)
     .bind(&input.habit_name)
@@ -23,27 +33,66 @@ pub async fn insert_habit(pool: &SqlitePool, input: &CreateHabitInput) -> Result
     .bind(&input.habit_icon_emoji)
     .bind(&input.habit_color)
     .bind(&input.schedule_type)
+    .bind(today)
+    .bind(ctx.profile_id())
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(ctx.app_version)
+    .bind(ctx.app_version)
     .bind(now)
     .bind(now)
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
-
+    store_content_hash::<Habit>(&mut tx, &id).await?;
+    tx.commit().await?;
     Ok(id)
 }
 
+// Habits from before habit_created_date, or synced from a device that predates it. Run
+// on unlock, since the day depends on the configured timezone and day-start hour.
+pub async fn backfill_created_dates(
+    pool: &SqlitePool,
+    ctx: &RequestContext,
+) -> Result<usize, AppError> {
+    let rows: Vec<(String, i64)> =
+        sqlx::query_as("SELECT habit_id, created_at FROM habits WHERE habit_created_date IS NULL")
+            .fetch_all(pool)
+            .await?;
+    if rows.is_empty() {
+        return Ok(0);
+    }
+
+    let mut tx = pool.begin().await?;
+    for (id, created_at) in &rows {
+        let date = DateTime::from_timestamp(*created_at, 0)
+            .map(|at| ctx.day.date_at(at).format(DATE_FORMAT).to_string());
+        sqlx::query("UPDATE habits SET habit_created_date = ? WHERE habit_id = ?")
+            .bind(date)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        store_content_hash::<Habit>(&mut tx, id).await?;
+    }
+    tx.commit().await?;
+    Ok(rows.len())
+}
+
 pub async fn fetch_habit(pool: &SqlitePool, id: &str) -> Result<Habit, AppError> {
-    let habit = sqlx::query_as::<_, Habit>("SELECT * FROM habits WHERE habit_id = ?")
-        .bind(id)
-        .fetch_optional(pool)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Habit {} not found", id)))?;
+    let habit =
+        sqlx::query_as::<_, Habit>("SELECT * FROM habits WHERE habit_id = ? AND is_deleted = 0")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| AppError::not_found("Habit", id))?;
 
     Ok(habit)
 }
 
 pub async fn list_habits(pool: &SqlitePool) -> Result<Vec<Habit>, AppError> {
     let habits = sqlx::query_as::<_, Habit>(
-        "SELECT * FROM habits WHERE habit_visibility != 'archived' ORDER BY created_at DESC",
+        "SELECT * FROM habits WHERE habit_visibility != 'archived' AND is_deleted = 0
+         ORDER BY created_at DESC",
     )
     .fetch_all(pool)
     .await?;
@@ -54,7 +103,7 @@ pub async fn list_habits(pool: &SqlitePool) -> Result<Vec<Habit>, AppError> {
 pub async fn get_today_habits(pool: &SqlitePool, date: &str) -> Result<Vec<Habit>, AppError> {
     let habits = sqlx::query_as::<_, Habit>(
         "SELECT * FROM habits 
-         WHERE habit_visibility = 'active' 
+         WHERE habit_visibility = 'active' AND is_deleted = 0
          AND (schedule_start_date IS NULL OR schedule_start_date <= ?)
          AND (schedule_end_date IS NULL OR schedule_end_date >= ?)
          ORDER BY habit_priority_level DESC, created_at ASC",
@@ -70,19 +119,32 @@ pub async fn get_today_habits(pool: &SqlitePool, date: &str) -> Result<Vec<Habit
 pub async fn insert_habit_log(
     pool: &SqlitePool,
     input: &CreateHabitLogInput,
+    log_date: &str,
+    ctx: &RequestContext,
 ) -> Result<String, AppError> {
-    let id = Uuid::new_v4().to_string();
-    let now = Utc::now().timestamp();
+    let now = ctx.timestamp();
 
-    sqlx::query(
-        "INSERT OR REPLACE INTO habit_logs (
+    // Logging a day again updates that day's log in place, keeping its ID and
+    // created_at.
+    let id: String = sqlx::query_scalar(
+        "INSERT INTO habit_logs (
             log_id, habit_id, log_date, logged_at, value, status, 
             note, mood, energy_level, is_manual, created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)",
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)
+        ON CONFLICT(habit_id, log_date) DO UPDATE SET
+            logged_at = excluded.logged_at,
+            value = excluded.value,
+            status = excluded.status,
+            note = excluded.note,
+            mood = excluded.mood,
+            energy_level = excluded.energy_level,
+            is_manual = excluded.is_manual,
+            updated_at = excluded.updated_at
+        RETURNING log_id",
     )
-    .bind(&id)
+    .bind(habit_log_id(&input.habit_id, log_date))
     .bind(&input.habit_id)
-    .bind(&input.log_date)
+    .bind(log_date)
     .bind(now)
     .bind(&input.value)
     .bind(&input.status)
@@ -91,7 +153,7 @@ pub async fn insert_habit_log(
     .bind(&input.energy_level)
     .bind(now)
     .bind(now)
-    .execute(pool)
+    .fetch_one(pool)
     .await?;
 
     Ok(id)
@@ -1,20 +1,23 @@
-use crate::app::error::AppError;
+use crate::app::error::{AppError, FieldErrors};
 use crate::domains::habits::model::CreateHabitInput;
 
 pub fn validate_create_habit(input: &CreateHabitInput) -> Result<(), AppError> {
+    let mut errors = FieldErrors::new();
+
     if input.habit_name.trim().is_empty() {
-        return Err(AppError::Validation(
-            "Habit name cannot be empty.".to_string(),
-        ));
+        errors.add("habit_name", "Habit name cannot be empty.");
     }
 
     let valid_types = vec!["boolean", "quantitative", "duration", "checklist"];
     if !valid_types.contains(&input.habit_type.as_str()) {
-        return Err(AppError::Validation(format!(
-            "Invalid habit type: {}. Must be one of {:?}",
-            input.habit_type, valid_types
-        )));
+        errors.add(
+            "habit_type",
+            format!(
+                "Invalid habit type: {}. Must be one of {:?}",
+                input.habit_type, valid_types
+            ),
+        );
     }
 
-    Ok(())
+    errors.into_result()
 }
@@ -1,7 +1,80 @@
+use crate::analytics::cache::save_cache_row;
 use crate::app::error::AppError;
+use crate::domains::jobs::events::list_job_events;
+use crate::domains::jobs::model::JobAnalyticsCache;
+use crate::domains::jobs::repository::fetch_job_application;
+use crate::utils::time::{LogicalDay, DATE_FORMAT};
+use chrono::NaiveDate;
 use sqlx::SqlitePool;
 
-pub async fn recompute_job_analytics(_pool: &SqlitePool, _job_id: &str) -> Result<(), AppError> {
-    // Logic for pipeline health, conversion rates, etc.
-    Ok(())
+// Statuses that mean the employer answered.
+const RESPONSE_STATUSES: &[&str] = &["interviewing", "offer", "rejected"];
+
+// Built from the application's status history.
+pub async fn compute_job_analytics(
+    pool: &SqlitePool,
+    job_id: &str,
+    day: &LogicalDay,
+) -> Result<JobAnalyticsCache, AppError> {
+    let job = fetch_job_application(pool, job_id).await?;
+    let events = list_job_events(pool, job_id).await?;
+    let parse = |date: &str| NaiveDate::parse_from_str(date, DATE_FORMAT).ok();
+
+    let applied = events
+        .iter()
+        .find(|event| event.job_status == "applied")
+        .and_then(|event| parse(&event.event_date))
+        .or_else(|| job.application_submitted_date.as_deref().and_then(parse));
+    let first_response = applied.and_then(|applied| {
+        events
+            .iter()
+            .filter(|event| RESPONSE_STATUSES.contains(&event.job_status.as_str()))
+            .filter_map(|event| parse(&event.event_date))
+            .find(|date| *date >= applied)
+    });
+
+    Ok(JobAnalyticsCache {
+        job_application_id: job_id.to_string(),
+        computed_for_date: day.today_string(),
+        computed_at: day.now().timestamp(),
+        days_since_applied: applied.map(|applied| (day.today() - applied).num_days() as i32),
+        days_to_first_response: applied
+            .zip(first_response)
+            .map(|(applied, response)| (response - applied).num_days() as i32),
+        response_received_flag: first_response.is_some(),
+        interview_conversion_rate: 0.0,
+        offer_conversion_rate: 0.0,
+        rejection_rate: 0.0,
+        ghost_rate: 0.0,
+        avg_response_time: 0.0,
+        pipeline_velocity: 0.0,
+        application_success_probability: 0.0,
+        job_pipeline_health_status: None,
+        job_related_habits_completed: 0,
+        opportunity_score: 0.0,
+    })
+}
+
+pub async fn recompute_job_analytics(
+    pool: &SqlitePool,
+    job_id: &str,
+    day: &LogicalDay,
+) -> Result<JobAnalyticsCache, AppError> {
+    let analytics = compute_job_analytics(pool, job_id, day).await?;
+    save_cache_row(pool, "job_analytics_cache", &analytics).await?;
+    Ok(analytics)
+}
+
+pub async fn fetch_job_analytics(
+    pool: &SqlitePool,
+    job_id: &str,
+) -> Result<Option<JobAnalyticsCache>, AppError> {
+    let analytics = sqlx::query_as::<_, JobAnalyticsCache>(
+        "SELECT * FROM job_analytics_cache WHERE job_application_id = ?",
+    )
+    .bind(job_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(analytics)
 }
@@ -0,0 +1,65 @@
+use crate::app::context::RequestContext;
+use crate::app::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::{SqliteConnection, SqlitePool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct JobStatusEvent {
+    pub event_id: String,
+    pub job_application_id: String,
+    pub event_type: String, // created, renamed, deleted, restored
+    pub job_status: String,
+    pub job_title: String,
+    pub company_name: String,
+    pub event_date: String,
+    pub recorded_at: i64,
+    pub profile_id: Option<String>,
+    pub device_id: String,
+    pub session_id: String,
+}
+
+// Same contract as goal events: snapshot the row inside the transaction that changed it.
+pub async fn record_job_event(
+    conn: &mut SqliteConnection,
+    job_id: &str,
+    event_type: &str,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO job_status_events (
+            event_id, job_application_id, event_type, job_status, job_title, company_name,
+            event_date, recorded_at, profile_id, device_id, session_id
+        )
+        SELECT ?, job_application_id, ?, job_status, job_title, company_name,
+            ?, ?, ?, ?, ?
+        FROM job_applications WHERE job_application_id = ?",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(event_type)
+    .bind(ctx.day.today_string())
+    .bind(ctx.timestamp())
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(job_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn list_job_events(
+    pool: &SqlitePool,
+    job_id: &str,
+) -> Result<Vec<JobStatusEvent>, AppError> {
+    let events = sqlx::query_as::<_, JobStatusEvent>(
+        "SELECT * FROM job_status_events WHERE job_application_id = ?
+         ORDER BY recorded_at ASC, rowid ASC",
+    )
+    .bind(job_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(events)
+}
@@ -1,3 +1,4 @@
+use crate::utils::hashing::ContentHash;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -18,6 +19,9 @@ pub struct JobApplication {
     pub job_interest_level: i32,
     pub job_fit_score: f64,
     pub job_visibility: String, // active, archived
+    pub is_deleted: bool,
+    pub deleted_at: Option<i64>,
+    pub deleted_reason: Option<String>,
     pub job_status: String,     // draft, applied, interviewing, offer, rejected, ghosted, withdrawn
     pub job_stage: Option<String>,
     pub job_stage_order: i32,
@@ -112,13 +116,11 @@ pub struct JobApplication {
     pub ghosted_detected_at: Option<i64>,
 
     // 6. RESUME, DOCUMENTS & MATERIALS
-    pub resume_id: Option<String>,
     pub resume_filename: Option<String>,
     pub resume_version: Option<String>,
     pub resume_customized_for_job: bool,
     pub resume_keywords_targeted: Option<String>,
     pub resume_strength_score: Option<f64>,
-    pub cover_letter_id: Option<String>,
     pub cover_letter_filename: Option<String>,
     pub cover_letter_customized: bool,
     pub portfolio_url: Option<String>,
@@ -211,24 +213,7 @@ pub struct JobApplication {
     pub emotional_load_score: f64,
     pub burnout_contribution_score: f64,
 
-    // 13. ANALYTICS & PIPELINE HEALTH (DERIVED — READ ONLY)
-    pub days_since_applied: Option<i32>,
-    pub days_to_first_response: Option<i32>,
-    pub response_received_flag: bool,
-    pub interview_conversion_rate: f64,
-    pub offer_conversion_rate: f64,
-    pub rejection_rate: f64,
-    pub ghost_rate: f64,
-    pub avg_response_time: f64,
-    pub pipeline_velocity: f64,
-    pub application_success_probability: f64,
-    pub job_pipeline_health_status: Option<String>,
-
     // 14. GOAL, HABIT & DIARY INTEGRATION
-    pub linked_goal_ids: Option<String>,
-    pub linked_habit_ids: Option<String>,
-    pub job_related_habits_completed: i32,
-    pub linked_diary_entry_ids: Option<String>,
     pub reflection_required: bool,
     pub reflection_completed: bool,
     pub job_search_phase: Option<String>,
@@ -238,7 +223,6 @@ pub struct JobApplication {
     pub tag_names_cache: Option<String>,
     pub priority_bucket: Option<String>,
     pub risk_bucket: Option<String>,
-    pub opportunity_score: f64,
     pub confidence_bucket: Option<String>,
     pub job_search_batch_id: Option<String>,
 
@@ -284,6 +268,51 @@ pub struct JobApplication {
     pub experimental_fields: Option<String>,
 }
 
+impl ContentHash for JobApplication {
+    const TABLE: &'static str = "job_applications";
+    const ID_COLUMN: &'static str = "job_application_id";
+    const DERIVED_FIELDS: &'static [&'static str] = &[
+        "job_application_slug",
+        "application_stage_entered_at",
+        "ghosted_detected_at",
+        "tag_names_cache",
+        "escalation_last_triggered_at",
+    ];
+
+    fn entity_id(&self) -> &str {
+        &self.job_application_id
+    }
+
+    fn stored_content_hash(&self) -> Option<&str> {
+        self.content_hash.as_deref()
+    }
+
+    fn set_content_hash(&mut self, hash: String) {
+        self.content_hash = Some(hash);
+    }
+}
+
+// Response times and pipeline figures derived from the application's status events.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct JobAnalyticsCache {
+    pub job_application_id: String,
+    pub computed_for_date: String,
+    pub computed_at: i64,
+    pub days_since_applied: Option<i32>,
+    pub days_to_first_response: Option<i32>,
+    pub response_received_flag: bool,
+    pub interview_conversion_rate: f64,
+    pub offer_conversion_rate: f64,
+    pub rejection_rate: f64,
+    pub ghost_rate: f64,
+    pub avg_response_time: f64,
+    pub pipeline_velocity: f64,
+    pub application_success_probability: f64,
+    pub job_pipeline_health_status: Option<String>,
+    pub job_related_habits_completed: i32,
+    pub opportunity_score: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateJobInput {
     pub job_title: String,
@@ -293,3 +322,9 @@ pub struct CreateJobInput {
     pub job_work_mode: Option<String>,
     pub job_posting_url: Option<String>,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RenameJobInput {
+    pub job_title: String,
+    pub company_name: String,
+}
@@ -1,29 +1,32 @@
+use crate::app::context::RequestContext;
 use crate::app::error::AppError;
-use crate::domains::jobs::model::{CreateJobInput, JobApplication};
-use chrono::{Local, Utc};
+use crate::db::slugs::{resolve_slug, retire_slug, unique_slug, JOB_SLUGS};
+use crate::domains::jobs::events::record_job_event;
+use crate::domains::jobs::model::{CreateJobInput, JobApplication, RenameJobInput};
+use crate::utils::hashing::store_content_hash;
 use sqlx::SqlitePool;
 use uuid::Uuid;
 
 pub async fn insert_job_application(
     pool: &SqlitePool,
     input: &CreateJobInput,
+    ctx: &RequestContext,
 ) -> Result<String, AppError> {
     let id = Uuid::new_v4().to_string();
-    let slug = format!(
-        "{}-{}-{}",
-        input.company_name.to_lowercase().replace(" ", "-"),
-        input.job_title.to_lowercase().replace(" ", "-"),
-        Uuid::new_v4().to_string()[..8].to_string()
-    );
-    let now_ts = Utc::now().timestamp();
-    let today = Local::now().format("%Y-%m-%d").to_string();
+    let now_ts = ctx.timestamp();
+    let today = ctx.day.today_string();
 
+    let mut tx = pool.begin().await?;
+    let slug_text = format!("{} {}", input.company_name, input.job_title);
+    let slug = unique_slug(&mut tx, &JOB_SLUGS, &slug_text, &id).await?;
     sqlx::query(
         "INSERT INTO job_applications (
             job_application_id, job_application_slug, job_title, company_name,
             job_level, job_employment_type, job_work_mode, job_posting_url,
-            application_created_date, created_at, updated_at
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            application_created_date, created_by_profile_id, last_modified_by_profile_id,
+            device_id, session_id, app_version_created, app_version_last_modified,
+            created_at, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
     )
     .bind(&id)
     .bind(&slug)
@@ -34,32 +37,94 @@ pub async fn insert_job_application(
     .bind(&input.job_work_mode)
     .bind(&input.job_posting_url)
     .bind(today)
+    .bind(ctx.profile_id())
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(ctx.app_version)
+    .bind(ctx.app_version)
     .bind(now_ts)
     .bind(now_ts)
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
-
+    record_job_event(&mut tx, &id, "created", ctx).await?;
+    store_content_hash::<JobApplication>(&mut tx, &id).await?;
+    tx.commit().await?;
     Ok(id)
 }
 
+// Same slug handling as goals: the old slug is kept as an alias.
+pub async fn rename_job_application(
+    pool: &SqlitePool,
+    id: &str,
+    input: &RenameJobInput,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    let job = fetch_job_application(pool, id).await?;
+
+    let mut tx = pool.begin().await?;
+    let slug_text = format!("{} {}", input.company_name, input.job_title);
+    let slug = unique_slug(&mut tx, &JOB_SLUGS, &slug_text, id).await?;
+    sqlx::query(
+        "UPDATE job_applications SET
+            job_title = ?, company_name = ?, job_application_slug = ?,
+            last_modified_by_profile_id = ?, device_id = ?, session_id = ?,
+            app_version_last_modified = ?, updated_at = ?
+        WHERE job_application_id = ?",
+    )
+    .bind(&input.job_title)
+    .bind(&input.company_name)
+    .bind(&slug)
+    .bind(ctx.profile_id())
+    .bind(&ctx.device_id)
+    .bind(&ctx.session_id)
+    .bind(ctx.app_version)
+    .bind(ctx.timestamp())
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+    retire_slug(
+        &mut tx,
+        &JOB_SLUGS,
+        id,
+        ctx,
+        &job.job_application_slug,
+        &slug,
+    )
+    .await?;
+    record_job_event(&mut tx, id, "renamed", ctx).await?;
+    store_content_hash::<JobApplication>(&mut tx, id).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
 pub async fn fetch_job_application(
     pool: &SqlitePool,
     id: &str,
 ) -> Result<JobApplication, AppError> {
     let job = sqlx::query_as::<_, JobApplication>(
-        "SELECT * FROM job_applications WHERE job_application_id = ?",
+        "SELECT * FROM job_applications WHERE job_application_id = ? AND is_deleted = 0",
     )
     .bind(id)
     .fetch_optional(pool)
     .await?
-    .ok_or_else(|| AppError::NotFound(format!("Job application {} not found", id)))?;
+    .ok_or_else(|| AppError::not_found("Job application", id))?;
 
     Ok(job)
 }
 
+pub async fn fetch_job_by_slug(pool: &SqlitePool, slug: &str) -> Result<JobApplication, AppError> {
+    let id = resolve_slug(pool, &JOB_SLUGS, slug)
+        .await?
+        .ok_or_else(|| AppError::not_found("Job application", slug))?;
+
+    fetch_job_application(pool, &id).await
+}
+
 pub async fn list_job_applications(pool: &SqlitePool) -> Result<Vec<JobApplication>, AppError> {
     let jobs = sqlx::query_as::<_, JobApplication>(
-        "SELECT * FROM job_applications WHERE job_visibility = 'active' ORDER BY created_at DESC",
+        "SELECT * FROM job_applications WHERE job_visibility = 'active' AND is_deleted = 0
+         ORDER BY created_at DESC",
     )
     .fetch_all(pool)
     .await?;
@@ -1,16 +1,26 @@
-use crate::app::error::AppError;
-use crate::domains::jobs::model::CreateJobInput;
+use crate::app::error::{AppError, FieldErrors};
+use crate::domains::jobs::model::{CreateJobInput, RenameJobInput};
 
 pub fn validate_create_job(input: &CreateJobInput) -> Result<(), AppError> {
+    let mut errors = FieldErrors::new();
+
+    if input.job_title.trim().is_empty() {
+        errors.add("job_title", "Job title cannot be empty.");
+    }
+    if input.company_name.trim().is_empty() {
+        errors.add("company_name", "Company name cannot be empty.");
+    }
+    errors.into_result()
+}
+
+pub fn validate_rename_job(input: &RenameJobInput) -> Result<(), AppError> {
+    let mut errors = FieldErrors::new();
+
     if input.job_title.trim().is_empty() {
-        return Err(AppError::Validation(
-            "Job title cannot be empty.".to_string(),
-        ));
+        errors.add("job_title", "Job title cannot be empty.");
     }
     if input.company_name.trim().is_empty() {
-        return Err(AppError::Validation(
-            "Company name cannot be empty.".to_string(),
-        ));
+        errors.add("company_name", "Company name cannot be empty.");
     }
-    Ok(())
+    errors.into_result()
 }
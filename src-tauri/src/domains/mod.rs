@@ -3,4 +3,4 @@ pub mod diary;
 pub mod goals;
 pub mod habits;
 pub mod jobs;
-// pub mod profile;
+pub mod profile;
@@ -0,0 +1,27 @@
+use crate::app::error::AppError;
+use crate::domains::profile::model::ProfileActivity;
+use sqlx::SqlitePool;
+
+pub async fn compute_profile_activity(
+    pool: &SqlitePool,
+    profile_id: &str,
+) -> Result<ProfileActivity, AppError> {
+    let counts: (i64, i64, i64, i64) = sqlx::query_as(
+        "SELECT
+            (SELECT COUNT(*) FROM diary_entries WHERE created_by_profile_id = ?1 AND is_deleted = 0),
+            (SELECT COUNT(*) FROM habits WHERE created_by_profile_id = ?1 AND is_deleted = 0),
+            (SELECT COUNT(*) FROM goals WHERE created_by_profile_id = ?1 AND is_deleted = 0),
+            (SELECT COUNT(*) FROM job_applications WHERE created_by_profile_id = ?1 AND is_deleted = 0)",
+    )
+    .bind(profile_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(ProfileActivity {
+        profile_id: profile_id.to_string(),
+        diary_entries_created: counts.0,
+        habits_created: counts.1,
+        goals_created: counts.2,
+        job_applications_created: counts.3,
+    })
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Profile {
+    pub profile_id: String,
+    pub display_name: String,
+    pub avatar: Option<String>,
+    pub timezone: Option<String>,
+    pub preferences_json: String,
+    pub last_selected_at: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateProfileInput {
+    pub display_name: String,
+    pub avatar: Option<String>,
+    pub timezone: Option<String>,
+    pub preferences_json: Option<String>,
+}
+
+// Fields left as None keep their current value.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateProfileInput {
+    pub display_name: Option<String>,
+    pub avatar: Option<String>,
+    pub timezone: Option<String>,
+    pub preferences_json: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileActivity {
+    pub profile_id: String,
+    pub diary_entries_created: i64,
+    pub habits_created: i64,
+    pub goals_created: i64,
+    pub job_applications_created: i64,
+}
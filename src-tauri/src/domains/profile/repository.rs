@@ -0,0 +1,111 @@
+use crate::app::context::RequestContext;
+use crate::app::error::AppError;
+use crate::domains::profile::model::{CreateProfileInput, Profile, UpdateProfileInput};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+pub async fn insert_profile(
+    pool: &SqlitePool,
+    input: &CreateProfileInput,
+    ctx: &RequestContext,
+) -> Result<String, AppError> {
+    let id = Uuid::new_v4().to_string();
+    let now = ctx.timestamp();
+
+    sqlx::query(
+        "INSERT INTO profiles (
+            profile_id, display_name, avatar, timezone, preferences_json,
+            created_at, updated_at
+        ) VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&id)
+    .bind(input.display_name.trim())
+    .bind(&input.avatar)
+    .bind(&input.timezone)
+    .bind(input.preferences_json.as_deref().unwrap_or("{}"))
+    .bind(now)
+    .bind(now)
+    .execute(pool)
+    .await?;
+
+    Ok(id)
+}
+
+pub async fn fetch_profile(pool: &SqlitePool, id: &str) -> Result<Profile, AppError> {
+    let profile = sqlx::query_as::<_, Profile>("SELECT * FROM profiles WHERE profile_id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::not_found("Profile", id))?;
+
+    Ok(profile)
+}
+
+pub async fn list_profiles(pool: &SqlitePool) -> Result<Vec<Profile>, AppError> {
+    let profiles =
+        sqlx::query_as::<_, Profile>("SELECT * FROM profiles ORDER BY display_name COLLATE NOCASE")
+            .fetch_all(pool)
+            .await?;
+
+    Ok(profiles)
+}
+
+pub async fn update_profile(
+    pool: &SqlitePool,
+    id: &str,
+    input: &UpdateProfileInput,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    let now = ctx.timestamp();
+    let result = sqlx::query(
+        "UPDATE profiles SET
+            display_name = COALESCE(?, display_name),
+            avatar = COALESCE(?, avatar),
+            timezone = COALESCE(?, timezone),
+            preferences_json = COALESCE(?, preferences_json),
+            updated_at = ?
+         WHERE profile_id = ?",
+    )
+    .bind(input.display_name.as_deref().map(str::trim))
+    .bind(&input.avatar)
+    .bind(&input.timezone)
+    .bind(&input.preferences_json)
+    .bind(now)
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Profile", id));
+    }
+    Ok(())
+}
+
+pub async fn mark_profile_selected(
+    pool: &SqlitePool,
+    id: &str,
+    ctx: &RequestContext,
+) -> Result<(), AppError> {
+    let result = sqlx::query("UPDATE profiles SET last_selected_at = ? WHERE profile_id = ?")
+        .bind(ctx.timestamp())
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::not_found("Profile", id));
+    }
+    Ok(())
+}
+
+// The profile that was active when the vault was last used.
+pub async fn fetch_last_selected_profile(pool: &SqlitePool) -> Result<Option<Profile>, AppError> {
+    let profile = sqlx::query_as::<_, Profile>(
+        "SELECT * FROM profiles WHERE last_selected_at IS NOT NULL
+         ORDER BY last_selected_at DESC LIMIT 1",
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(profile)
+}
@@ -0,0 +1,60 @@
+use crate::app::error::{AppError, FieldErrors};
+use crate::domains::profile::model::{CreateProfileInput, UpdateProfileInput};
+use crate::utils::time::is_valid_timezone;
+
+pub fn validate_create_profile(input: &CreateProfileInput) -> Result<(), AppError> {
+    let mut errors = FieldErrors::new();
+
+    if input.display_name.trim().is_empty() {
+        errors.add("display_name", "Display name cannot be empty.");
+    }
+    validate_timezone(&mut errors, input.timezone.as_deref());
+    validate_preferences(&mut errors, input.preferences_json.as_deref());
+
+    errors.into_result()
+}
+
+pub fn validate_update_profile(input: &UpdateProfileInput) -> Result<(), AppError> {
+    let mut errors = FieldErrors::new();
+
+    if let Some(name) = &input.display_name {
+        if name.trim().is_empty() {
+            errors.add("display_name", "Display name cannot be empty.");
+        }
+    }
+    validate_timezone(&mut errors, input.timezone.as_deref());
+    validate_preferences(&mut errors, input.preferences_json.as_deref());
+
+    errors.into_result()
+}
+
+fn validate_preferences(errors: &mut FieldErrors, preferences_json: Option<&str>) {
+    let Some(preferences_json) = preferences_json else {
+        return;
+    };
+    match serde_json::from_str::<serde_json::Value>(preferences_json) {
+        Ok(value) if value.is_object() => match value.get("day_start_hour") {
+            None => {}
+            Some(hour) if hour.as_u64().is_some_and(|h| h <= 23) => {}
+            Some(_) => errors.add(
+                "preferences_json",
+                "day_start_hour must be a whole hour between 0 and 23.",
+            ),
+        },
+        _ => errors.add("preferences_json", "Preferences must be a JSON object."),
+    }
+}
+
+fn validate_timezone(errors: &mut FieldErrors, timezone: Option<&str>) {
+    if let Some(timezone) = timezone {
+        if !is_valid_timezone(timezone) {
+            errors.add(
+                "timezone",
+                format!(
+                    "Unknown timezone: {}. Expected an IANA name like Europe/Berlin.",
+                    timezone
+                ),
+            );
+        }
+    }
+}
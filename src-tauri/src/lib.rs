@@ -6,67 +6,136 @@ pub mod domains;
 pub mod migrations;
 pub mod utils;
 
-use crate::app::state::{AppConfig, AppState, SharedState};
-use crate::db::connection::establish_connection;
-use crate::migrations::runner::run_migrations;
+use crate::app::backup_schedule::spawn_backup_schedule;
+use crate::app::lock::spawn_idle_lock;
+use crate::app::config::Config;
+use crate::app::context::load_or_create_device_id;
+use crate::app::state::{AppState, EncryptionStatus, SharedState};
+use crate::db::cipher::probe_cipher;
+use crate::db::vault::VaultRegistry;
 use std::sync::Arc;
 use tauri::Manager;
-use tokio::sync::Mutex;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
+            let config_dir = app.path().app_config_dir()?;
+            let data_dir = app.path().app_data_dir()?;
+            std::fs::create_dir_all(&config_dir)?;
+            std::fs::create_dir_all(&data_dir)?;
+            let config = Config::load(&config_dir, &data_dir)?;
+
             if cfg!(debug_assertions) {
                 app.handle().plugin(
                     tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
+                        .level(config.log_level_filter())
                         .build(),
                 )?;
             }
+            log::info!("Using database at {}", config.database_path.display());
 
-            // Initialize database
-            let database_url = "sqlite:nocturne.db";
-
-            let pool = tauri::async_runtime::block_on(async {
-                let pool = establish_connection(database_url)
-                    .await
-                    .expect("Failed to connect to database");
-
-                run_migrations(&pool)
-                    .await
-                    .expect("Failed to run migrations");
+            let cipher_version = tauri::async_runtime::block_on(probe_cipher())?;
+            match &cipher_version {
+                Some(version) => log::info!("SQLCipher {} available", version),
+                None => log::warn!("SQLCipher not available; database encryption is disabled"),
+            }
+            let encryption_status = EncryptionStatus::from_probe(
+                cipher_version.as_deref(),
+                config.allow_plaintext_database,
+            );
 
-                pool
-            });
+            let vaults = VaultRegistry::load(&config_dir, &config.database_path)?;
+            let device_id = load_or_create_device_id(&config_dir)?;
 
-            let state: SharedState = Arc::new(Mutex::new(AppState {
-                db: pool,
-                config: AppConfig::default(),
-            }));
+            // The database stays closed until the vault is unlocked with the passphrase.
+            let state: SharedState = Arc::new(AppState::new(
+                config,
+                config_dir,
+                device_id,
+                encryption_status,
+                vaults,
+            ));
 
+            spawn_idle_lock(state.clone());
+            spawn_backup_schedule(state.clone());
             app.manage(state);
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            crate::commands::security::get_vault_status,
+            crate::commands::security::unlock_vault,
+            crate::commands::security::lock_vault,
+            crate::commands::security::set_auto_lock_minutes,
+            crate::commands::security::change_passphrase,
+            crate::commands::security::rekey_database,
+            crate::commands::vaults::list_vaults,
+            crate::commands::vaults::create_vault,
+            crate::commands::vaults::switch_vault,
+            crate::commands::backups::list_backups,
+            crate::commands::backups::create_backup,
+            crate::commands::backups::restore_backup,
+            crate::commands::archive::export_vault,
+            crate::commands::archive::import_vault,
+            crate::commands::maintenance::get_schema_status,
+            crate::commands::maintenance::run_integrity_check,
+            crate::commands::maintenance::repair_integrity_issues,
+            crate::commands::maintenance::rebuild_analytics_caches,
+            crate::commands::sync::get_sync_status,
+            crate::commands::sync::set_sync_folder,
+            crate::commands::sync::sync_now,
+            crate::commands::sync::list_conflicts,
+            crate::commands::sync::resolve_conflict,
+            crate::commands::debug::get_clock_status,
+            #[cfg(debug_assertions)]
+            crate::commands::debug::set_simulated_time,
+            crate::commands::profiles::get_profiles,
+            crate::commands::profiles::get_active_profile,
+            crate::commands::profiles::create_profile,
+            crate::commands::profiles::update_profile_details,
+            crate::commands::profiles::select_active_profile,
+            crate::commands::profiles::get_profile_activity,
             crate::commands::diary::create_diary_entry,
             crate::commands::diary::get_diary_entries,
             crate::commands::diary::get_diary_entry,
             crate::commands::diary::setup_diary,
             crate::commands::diary::update_diary_entry,
             crate::commands::diary::get_diary_sub_pages,
+            crate::commands::diary::get_diary_entry_history,
+            crate::commands::diary::get_diary_analytics_cache,
             crate::commands::habits::create_habit,
             crate::commands::habits::get_habits,
             crate::commands::habits::get_today_habits,
             crate::commands::habits::log_habit_completion,
             crate::commands::habits::get_habit_analytics,
+            crate::commands::habits::get_habit_analytics_cache,
             crate::commands::goals::create_goal,
             crate::commands::goals::get_goals,
             crate::commands::goals::get_goal,
+            crate::commands::goals::get_goal_by_slug,
+            crate::commands::goals::rename_goal,
+            crate::commands::goals::get_goal_timeline,
+            crate::commands::goals::get_goal_analytics_cache,
             crate::commands::jobs::create_job_application,
             crate::commands::jobs::get_job_applications,
             crate::commands::jobs::get_job_application,
+            crate::commands::jobs::get_job_by_slug,
+            crate::commands::jobs::rename_job_application,
+            crate::commands::jobs::get_job_timeline,
+            crate::commands::jobs::get_job_analytics_cache,
+            crate::commands::links::link_entities,
+            crate::commands::links::unlink_entities,
+            crate::commands::links::get_links,
+            crate::commands::trash::delete_entity,
+            crate::commands::trash::restore_entity,
+            crate::commands::trash::purge_entity,
+            crate::commands::trash::list_trash,
+            crate::commands::attachments::add_attachment,
+            crate::commands::attachments::fetch_attachment,
+            crate::commands::attachments::list_attachments,
+            crate::commands::attachments::unlink_attachment,
+            crate::commands::search::search,
             crate::commands::habits::get_habit,
             crate::commands::dashboard::get_dashboard,
         ])
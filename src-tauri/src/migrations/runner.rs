@@ -1,7 +1,72 @@
 ﻿use crate::app::error::AppError;
+use serde::{Deserialize, Serialize};
+use sqlx::migrate::Migrator;
 use sqlx::SqlitePool;
 
+static MIGRATOR: Migrator = sqlx::migrate!("./src/db/migrations");
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationInfo {
+    pub version: i64,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaStatus {
+    pub current_version: i64,
+    pub latest_version: i64,
+    pub applied: Vec<MigrationInfo>,
+    pub pending: Vec<MigrationInfo>,
+    // Set when the last upgrade failed and the vault was rolled back and opened read-only.
+    pub recovery_reason: Option<String>,
+}
+
 pub async fn run_migrations(pool: &SqlitePool) -> Result<(), AppError> {
-    sqlx::migrate!("./src/db/migrations").run(pool).await?;
+    MIGRATOR.run(pool).await?;
     Ok(())
 }
+
+pub async fn applied_versions(pool: &SqlitePool) -> Result<Vec<i64>, AppError> {
+    let has_table: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(pool)
+    .await?;
+    if !has_table {
+        return Ok(Vec::new());
+    }
+
+    let versions = sqlx::query_scalar(
+        "SELECT version FROM _sqlx_migrations WHERE success = 1 ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(versions)
+}
+
+pub async fn current_version(pool: &SqlitePool) -> Result<i64, AppError> {
+    Ok(applied_versions(pool).await?.last().copied().unwrap_or(0))
+}
+
+pub async fn schema_status(
+    pool: &SqlitePool,
+    recovery_reason: Option<String>,
+) -> Result<SchemaStatus, AppError> {
+    let applied_versions = applied_versions(pool).await?;
+    let (applied, pending): (Vec<MigrationInfo>, Vec<MigrationInfo>) = MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| MigrationInfo {
+            version: m.version,
+            description: m.description.to_string(),
+        })
+        .partition(|m| applied_versions.contains(&m.version));
+
+    Ok(SchemaStatus {
+        current_version: applied_versions.last().copied().unwrap_or(0),
+        latest_version: MIGRATOR.iter().map(|m| m.version).max().unwrap_or(0),
+        applied,
+        pending,
+        recovery_reason,
+    })
+}
@@ -1 +1,145 @@
-﻿// Placeholder
+﻿use crate::app::error::AppError;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{SqliteConnection, SqlitePool};
+
+// Bookkeeping columns every entity table shares. They change on writes that leave
+// the content itself alone, so they never count towards the hash.
+const SYSTEM_FIELDS: &[&str] = &[
+    "created_at",
+    "updated_at",
+    "last_viewed_at",
+    "created_by_profile_id",
+    "last_modified_by_profile_id",
+    "device_id",
+    "session_id",
+    "app_version_created",
+    "app_version_last_modified",
+    "encryption_key_id",
+    "content_hash",
+    "sync_state",
+    "conflict_state",
+    "conflict_resolved_at",
+    "data_migration_version",
+    "is_system_generated",
+    "internal_flags",
+    "experimental_fields",
+];
+
+// A SHA-256 over what the user actually wrote. Two rows with the same hash hold the
+// same content, whatever their IDs, audit columns or cached analytics say.
+pub trait ContentHash: Serialize {
+    const TABLE: &'static str;
+    const ID_COLUMN: &'static str;
+    // Fields computed from other data, which are recomputed rather than edited.
+    const DERIVED_FIELDS: &'static [&'static str];
+
+    fn entity_id(&self) -> &str;
+    fn stored_content_hash(&self) -> Option<&str>;
+    fn set_content_hash(&mut self, hash: String);
+
+    fn content_hash(&self) -> String {
+        let mut value = serde_json::to_value(self).unwrap_or(Value::Null);
+        if let Value::Object(fields) = &mut value {
+            for field in SYSTEM_FIELDS.iter().chain(Self::DERIVED_FIELDS) {
+                fields.remove(*field);
+            }
+            fields.remove(Self::ID_COLUMN);
+        }
+        hash_value(&value)
+    }
+}
+
+// Keys are sorted at every level, so the hash doesn't depend on serde_json's map order.
+pub fn hash_value(value: &Value) -> String {
+    hash_bytes(canonicalize(value).to_string().as_bytes())
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut keys: Vec<&String> = fields.keys().collect();
+            keys.sort();
+            let mut sorted = Map::new();
+            for key in keys {
+                sorted.insert(key.clone(), canonicalize(&fields[key]));
+            }
+            Value::Object(sorted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+// Recomputes and saves the hash of one row, returning the row. Run it on the
+// transaction that wrote the row, so the row is never committed with a stale hash.
+pub async fn store_content_hash<T>(conn: &mut SqliteConnection, id: &str) -> Result<T, AppError>
+where
+    T: ContentHash + for<'r> sqlx::FromRow<'r, SqliteRow> + Send + Unpin,
+{
+    let mut entity = sqlx::query_as::<_, T>(&format!(
+        "SELECT * FROM {} WHERE {} = ?",
+        T::TABLE,
+        T::ID_COLUMN
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::not_found(T::TABLE, id))?;
+
+    let hash = entity.content_hash();
+    sqlx::query(&format!(
+        "UPDATE {} SET content_hash = ? WHERE {} = ?",
+        T::TABLE,
+        T::ID_COLUMN
+    ))
+    .bind(&hash)
+    .bind(id)
+    .execute(&mut *conn)
+    .await?;
+
+    entity.set_content_hash(hash);
+    Ok(entity)
+}
+
+// IDs of rows whose stored hash is missing or no longer matches their content.
+pub async fn find_stale_hashes<T>(pool: &SqlitePool) -> Result<Vec<String>, AppError>
+where
+    T: ContentHash + for<'r> sqlx::FromRow<'r, SqliteRow> + Send + Unpin,
+{
+    let rows = sqlx::query_as::<_, T>(&format!("SELECT * FROM {}", T::TABLE))
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows
+        .iter()
+        .filter(|row| row.stored_content_hash() != Some(row.content_hash().as_str()))
+        .map(|row| row.entity_id().to_string())
+        .collect())
+}
+
+// Hashes rows written before hashing existed. Returns how many were filled in.
+pub async fn fill_missing_hashes<T>(pool: &SqlitePool) -> Result<usize, AppError>
+where
+    T: ContentHash + for<'r> sqlx::FromRow<'r, SqliteRow> + Send + Unpin,
+{
+    let ids: Vec<String> = sqlx::query_scalar(&format!(
+        "SELECT {} FROM {} WHERE content_hash IS NULL",
+        T::ID_COLUMN,
+        T::TABLE
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    let mut conn = pool.acquire().await?;
+    for id in &ids {
+        store_content_hash::<T>(&mut conn, id).await?;
+    }
+    Ok(ids.len())
+}
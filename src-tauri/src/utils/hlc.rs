@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+// Hybrid logical clock timestamp: wall-clock milliseconds, a counter for events in
+// the same millisecond, and the device that made it as a tie-breaker. The string
+// form sorts the same way the values do, so stored HLCs can be compared in SQL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hlc {
+    pub wall_ms: i64,
+    pub counter: u32,
+    pub device_id: String,
+}
+
+impl Hlc {
+    // The timestamp for a local event, strictly after `last` even if the wall clock
+    // went backwards.
+    pub fn tick(last: Option<&Hlc>, now: DateTime<Utc>, device_id: &str) -> Hlc {
+        let now_ms = now.timestamp_millis();
+        let (wall_ms, counter) = match last {
+            Some(last) if last.wall_ms >= now_ms => (last.wall_ms, last.counter + 1),
+            _ => (now_ms, 0),
+        };
+        Hlc {
+            wall_ms,
+            counter,
+            device_id: device_id.to_string(),
+        }
+    }
+
+    // Moves the local clock past a timestamp received from another device, so the
+    // next local event orders after everything already seen.
+    pub fn observe(last: Option<Hlc>, seen: &Hlc) -> Hlc {
+        match last {
+            Some(last) if last >= *seen => last,
+            _ => seen.clone(),
+        }
+    }
+}
+
+impl Ord for Hlc {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.wall_ms, self.counter, &self.device_id).cmp(&(
+            other.wall_ms,
+            other.counter,
+            &other.device_id,
+        ))
+    }
+}
+
+impl PartialOrd for Hlc {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Hlc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:015}-{:06}-{}",
+            self.wall_ms, self.counter, self.device_id
+        )
+    }
+}
+
+impl FromStr for Hlc {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.splitn(3, '-');
+        let (Some(wall_ms), Some(counter), Some(device_id)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(format!("Malformed HLC: {}", value));
+        };
+        Ok(Hlc {
+            wall_ms: wall_ms
+                .parse()
+                .map_err(|_| format!("Malformed HLC: {}", value))?,
+            counter: counter
+                .parse()
+                .map_err(|_| format!("Malformed HLC: {}", value))?,
+            device_id: device_id.to_string(),
+        })
+    }
+}
@@ -1 +1,70 @@
-﻿// Placeholder
+﻿use deunicode::deunicode;
+
+const MAX_SLUG_LEN: usize = 60;
+
+// "Café Crème — 2026 Plan!" becomes "cafe-creme-2026-plan". Non-Latin scripts are
+// transliterated, so a title never turns into an empty slug unless it has no letters
+// or digits at all.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in deunicode(text).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    // Cut long slugs at a word boundary where there is one.
+    if slug.len() > MAX_SLUG_LEN {
+        slug.truncate(MAX_SLUG_LEN);
+        if let Some(boundary) = slug.rfind('-') {
+            slug.truncate(boundary);
+        }
+    }
+
+    let slug = slug.trim_matches('-');
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+// The n-th candidate for a slug whose plain form is taken: `plan`, `plan-2`, `plan-3`...
+pub fn numbered_slug(base: &str, n: u32) -> String {
+    if n <= 1 {
+        base.to_string()
+    } else {
+        format!("{}-{}", base, n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_transliterates_and_drops_punctuation() {
+        assert_eq!(slugify("Café Crème — 2026 Plan!"), "cafe-creme-2026-plan");
+        assert_eq!(slugify("Привет мир"), "privet-mir");
+        assert_eq!(slugify("  a/b\\c  "), "a-b-c");
+        assert_eq!(slugify("!!!"), "untitled");
+    }
+
+    #[test]
+    fn slugify_cuts_long_titles_at_a_word_boundary() {
+        let slug = slugify(&"abcdefghij ".repeat(10));
+        assert_eq!(slug, ["abcdefghij"; 5].join("-"));
+        assert!(slug.len() <= MAX_SLUG_LEN);
+
+        // With no boundary to cut at, the slug is cut at the limit.
+        assert_eq!(slugify(&"x".repeat(80)), "x".repeat(MAX_SLUG_LEN));
+    }
+
+    #[test]
+    fn numbered_slug_leaves_the_first_one_bare() {
+        assert_eq!(numbered_slug("plan", 1), "plan");
+        assert_eq!(numbered_slug("plan", 2), "plan-2");
+    }
+}
@@ -1,3 +1,4 @@
 ﻿pub mod hashing;
+pub mod hlc;
 pub mod ids;
 pub mod time;
@@ -1 +1,207 @@
-﻿// Placeholder
+﻿use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+// Source of "now" for everything that depends on the user's calendar: date locking,
+// streaks, dashboard cache expiry and the timestamps written with records. Security
+// and housekeeping timers (auto-lock, scheduled backups, trash expiry) stay on real time.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+// Always the same instant, for reproducing date-dependent behavior exactly.
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+// Real time shifted by an offset. Time keeps flowing after a jump, so crossing
+// midnight at the simulated date behaves like it would for real.
+#[derive(Default)]
+pub struct SimulatedClock {
+    offset_secs: AtomicI64,
+}
+
+impl SimulatedClock {
+    pub fn travel_to(&self, at: DateTime<Utc>) {
+        let offset = at.timestamp() - Utc::now().timestamp();
+        self.offset_secs.store(offset, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.offset_secs.store(0, Ordering::Relaxed);
+    }
+
+    pub fn is_simulated(&self) -> bool {
+        self.offset_secs.load(Ordering::Relaxed) != 0
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now() + Duration::seconds(self.offset_secs.load(Ordering::Relaxed))
+    }
+}
+
+// The calendar day the user is living in, which is not always the UTC date or even
+// the wall-clock date: with a day-start hour of 4, 2am on Tuesday still counts as
+// Monday. Every `entry_date`, `log_date` and "today" in the app comes from here.
+// It is taken at one instant, so a command sees the same day from start to finish.
+#[derive(Debug, Clone, Copy)]
+pub struct LogicalDay {
+    timezone: Option<Tz>, // None follows the system timezone
+    day_start_hour: u32,
+    now: DateTime<Utc>,
+}
+
+impl LogicalDay {
+    // Unknown timezone names fall back to the system timezone rather than failing,
+    // since a stale profile setting shouldn't stop the app from working.
+    pub fn new(timezone: Option<&str>, day_start_hour: u32, clock: &dyn Clock) -> Self {
+        let timezone = timezone.and_then(|name| match name.parse::<Tz>() {
+            Ok(tz) => Some(tz),
+            Err(_) => {
+                log::warn!("Unknown timezone {}; using the system timezone", name);
+                None
+            }
+        });
+        Self {
+            timezone,
+            day_start_hour: day_start_hour.min(23),
+            now: clock.now(),
+        }
+    }
+
+    pub fn date_at(&self, at: DateTime<Utc>) -> NaiveDate {
+        let wall_clock = match self.timezone {
+            Some(tz) => at.with_timezone(&tz).naive_local(),
+            None => at.with_timezone(&Local).naive_local(),
+        };
+        (wall_clock - Duration::hours(self.day_start_hour as i64)).date()
+    }
+
+    // The instant `date` begins: its day-start hour in the day's timezone. When that
+    // hour is skipped by a DST change, the day begins once the clocks have gone forward.
+    pub fn start_of(&self, date: NaiveDate) -> DateTime<Utc> {
+        let start = date.and_time(NaiveTime::MIN) + Duration::hours(self.day_start_hour as i64);
+        (0..=2)
+            .map(|hours| start + Duration::hours(hours))
+            .find_map(|wall_clock| self.instant_at_wall_clock(wall_clock))
+            .unwrap_or_else(|| start.and_utc())
+    }
+
+    fn instant_at_wall_clock(&self, wall_clock: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self.timezone {
+            Some(tz) => tz
+                .from_local_datetime(&wall_clock)
+                .earliest()
+                .map(|at| at.to_utc()),
+            None => Local
+                .from_local_datetime(&wall_clock)
+                .earliest()
+                .map(|at| at.to_utc()),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.date_at(self.now)
+    }
+
+    pub fn today_string(&self) -> String {
+        self.today().format(DATE_FORMAT).to_string()
+    }
+
+    // IANA name when one is configured, otherwise the current system UTC offset.
+    pub fn timezone_label(&self) -> String {
+        match self.timezone {
+            Some(tz) => tz.name().to_string(),
+            None => self.now.with_timezone(&Local).offset().to_string(),
+        }
+    }
+}
+
+pub fn is_valid_timezone(name: &str) -> bool {
+    name.parse::<Tz>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day_at(timezone: &str, day_start_hour: u32, at: &str) -> LogicalDay {
+        let at = DateTime::parse_from_rfc3339(at).unwrap().to_utc();
+        LogicalDay::new(Some(timezone), day_start_hour, &FixedClock(at))
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, DATE_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn small_hours_belong_to_the_previous_day_until_the_day_start_hour() {
+        // 02:00 and 04:00 in Berlin.
+        assert_eq!(
+            day_at("Europe/Berlin", 4, "2026-03-10T01:00:00Z").today(),
+            date("2026-03-09")
+        );
+        assert_eq!(
+            day_at("Europe/Berlin", 4, "2026-03-10T03:00:00Z").today(),
+            date("2026-03-10")
+        );
+        assert_eq!(
+            day_at("Europe/Berlin", 0, "2026-03-10T01:00:00Z").today(),
+            date("2026-03-10")
+        );
+    }
+
+    #[test]
+    fn today_follows_the_timezone_not_utc() {
+        // 23:00 the evening before in New York.
+        let day = day_at("America/New_York", 0, "2026-03-10T03:00:00Z");
+        assert_eq!(day.today_string(), "2026-03-09");
+        assert_eq!(day.timezone_label(), "America/New_York");
+    }
+
+    #[test]
+    fn start_of_is_the_first_instant_of_the_logical_day() {
+        for (timezone, day_start_hour) in [("America/New_York", 0), ("Asia/Tokyo", 4), ("UTC", 23)]
+        {
+            let day = day_at(timezone, day_start_hour, "2026-06-01T12:00:00Z");
+            let start = day.start_of(date("2026-06-15"));
+            assert_eq!(day.date_at(start), date("2026-06-15"));
+            assert_eq!(
+                day.date_at(start - Duration::seconds(1)),
+                date("2026-06-14")
+            );
+        }
+    }
+
+    #[test]
+    fn start_of_skips_an_hour_lost_to_dst() {
+        // Clocks in New York jump from 02:00 to 03:00 on 8 March 2026.
+        let day = day_at("America/New_York", 2, "2026-03-01T12:00:00Z");
+        let start = day.start_of(date("2026-03-08"));
+        assert_eq!(
+            start,
+            DateTime::parse_from_rfc3339("2026-03-08T07:00:00Z").unwrap()
+        );
+        assert_eq!(day.date_at(start), date("2026-03-08"));
+    }
+}
@@ -0,0 +1,207 @@
+use app_lib::app::context::RequestContext;
+use app_lib::db::archive::{
+    export_archive, import_archive, Archive, DashboardSettings, ImportMode,
+};
+use app_lib::db::vault::{open_vault, OpenedVault};
+use app_lib::domains::diary::model::CreateDiaryInput;
+use app_lib::domains::diary::repository::insert_entry;
+use app_lib::domains::goals::model::CreateGoalInput;
+use app_lib::domains::goals::repository::insert_goal;
+use app_lib::domains::habits::model::CreateHabitInput;
+use app_lib::domains::habits::repository::insert_habit;
+use app_lib::utils::time::{LogicalDay, SystemClock};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+const RELATION_TABLES: &[&str] = &[
+    "diary_habits",
+    "diary_goals",
+    "diary_jobs",
+    "habit_goals",
+    "job_goals",
+    "job_habits",
+    "goal_dependencies",
+    "habit_dependencies",
+];
+
+async fn vault(dir: &Path) -> OpenedVault {
+    fs::create_dir_all(dir).unwrap();
+    open_vault(
+        &dir.join("nocturne.db"),
+        &dir.join("backups"),
+        "correct horse battery",
+        false,
+    )
+    .await
+    .unwrap()
+}
+
+fn goal(title: &str) -> CreateGoalInput {
+    CreateGoalInput {
+        goal_title: title.to_string(),
+        goal_type: "outcome".to_string(),
+        goal_category: None,
+        goal_description: None,
+        goal_target_date: None,
+    }
+}
+
+// Sets `column` on the row of `table` whose `id_column` is `id`.
+fn set_column(
+    archive: &mut Archive,
+    table: &str,
+    id_column: &str,
+    id: &str,
+    column: &str,
+    value: Value,
+) {
+    let rows = archive.tables[table].as_array_mut().unwrap();
+    let row = rows.iter_mut().find(|row| row[id_column] == id).unwrap();
+    row.as_object_mut()
+        .unwrap()
+        .insert(column.to_string(), value);
+}
+
+fn reseal_manifest(archive: &mut Archive) {
+    archive
+        .manifest
+        .retain(|entry| entry.table == "settings" || archive.tables.contains_key(&entry.table));
+    for entry in &mut archive.manifest {
+        if let Some(Value::Array(rows)) = archive.tables.get(&entry.table) {
+            entry.row_count = rows.len();
+            entry.sha256 = hex::encode(Sha256::digest(serde_json::to_vec(rows).unwrap()));
+        }
+    }
+}
+
+#[tokio::test]
+async fn archive_from_before_the_relation_tables_imports() {
+    let dir = std::env::temp_dir().join(format!("nocturne-archive-{}", uuid::Uuid::new_v4()));
+    let source = vault(&dir.join("source")).await;
+    let pool = &source.pools.writer;
+    let ctx = RequestContext {
+        profile_id: None,
+        device_id: "device".to_string(),
+        session_id: "session".to_string(),
+        app_version: env!("CARGO_PKG_VERSION"),
+        day: LogicalDay::new(None, 0, &SystemClock),
+    };
+
+    let first = insert_goal(pool, &goal("Run"), &ctx).await.unwrap();
+    let second = insert_goal(pool, &goal("Stretch first"), &ctx)
+        .await
+        .unwrap();
+    let habit = CreateHabitInput {
+        habit_name: "Jog".to_string(),
+        habit_type: "boolean".to_string(),
+        habit_description: None,
+        habit_icon_emoji: None,
+        habit_color: None,
+        schedule_type: "daily".to_string(),
+    };
+    let habit = insert_habit(pool, &habit, &ctx).await.unwrap();
+    let page = CreateDiaryInput {
+        entry_date: "2026-05-01".to_string(),
+        title: None,
+        content_json: "[]".to_string(),
+        parent_page_id: None,
+    };
+    let page = insert_entry(pool, &page, &ctx).await.unwrap();
+
+    let settings = DashboardSettings {
+        diary_start_year: 2026,
+        day_start_hour: 0,
+    };
+    let mut archive = export_archive(pool, settings).await.unwrap();
+    source.pools.close().await;
+
+    // As exported before 0012: ID lists instead of relation tables, no attachments, and
+    // the derived columns 0013 dropped.
+    let current_version = archive.schema_version;
+    archive.schema_version = 11;
+    archive.format_version = 1;
+    for table in RELATION_TABLES
+        .iter()
+        .chain(&["attachments", "attachment_links"])
+    {
+        archive.tables.remove(*table);
+    }
+    set_column(
+        &mut archive,
+        "goals",
+        "goal_id",
+        &first,
+        "prerequisite_goal_ids",
+        json!(format!("[\"{}\"]", second)),
+    );
+    set_column(
+        &mut archive,
+        "goals",
+        "goal_id",
+        &first,
+        "linked_habit_ids",
+        json!(format!("{}, missing", habit)),
+    );
+    set_column(
+        &mut archive,
+        "habits",
+        "habit_id",
+        &habit,
+        "total_completions",
+        json!(3),
+    );
+    set_column(
+        &mut archive,
+        "diary_entries",
+        "diary_entry_id",
+        &page,
+        "linked_goal_ids",
+        json!(format!("[\"{}\"]", first)),
+    );
+    set_column(
+        &mut archive,
+        "diary_entries",
+        "diary_entry_id",
+        &page,
+        "linked_attachment_ids",
+        Value::Null,
+    );
+    reseal_manifest(&mut archive);
+
+    let target = vault(&dir.join("target")).await;
+    let pool = &target.pools.writer;
+    import_archive(pool, &archive, ImportMode::Replace)
+        .await
+        .unwrap();
+
+    let links = |sql: &'static str| async move {
+        sqlx::query_as::<_, (String, String, String)>(sql)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    };
+    assert_eq!(
+        links("SELECT goal_id, depends_on_goal_id, relation_type FROM goal_dependencies").await,
+        vec![(first.clone(), second.clone(), "prerequisite".to_string())]
+    );
+    assert_eq!(
+        links("SELECT habit_id, goal_id, relation_type FROM habit_goals").await,
+        vec![(habit.clone(), first.clone(), "linked".to_string())]
+    );
+    assert_eq!(
+        links("SELECT diary_entry_id, goal_id, relation_type FROM diary_goals").await,
+        vec![(page.clone(), first.clone(), "linked".to_string())]
+    );
+
+    // An archive from the current schema still has to match it exactly.
+    archive.schema_version = current_version;
+    let error = import_archive(pool, &archive, ImportMode::Replace)
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("Unknown column"), "{}", error);
+
+    target.pools.close().await;
+    fs::remove_dir_all(&dir).unwrap();
+}
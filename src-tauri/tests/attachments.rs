@@ -0,0 +1,77 @@
+use app_lib::app::context::RequestContext;
+use app_lib::db::attachments::{add_attachment, attachments_dir, read_attachment};
+use app_lib::db::relations::{EntityKind, EntityRef};
+use app_lib::db::vault::{open_vault, rekey_vault};
+use app_lib::domains::diary::model::CreateDiaryInput;
+use app_lib::domains::diary::repository::insert_entry;
+use app_lib::utils::time::{LogicalDay, SystemClock};
+use std::fs;
+
+const PASSPHRASE: &str = "correct horse battery";
+
+#[tokio::test]
+async fn attachments_open_after_a_rekey() {
+    let dir = std::env::temp_dir().join(format!("nocturne-attachments-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let database_path = dir.join("nocturne.db");
+    let backup_dir = dir.join("backups");
+    let store = attachments_dir(&database_path);
+    let ctx = RequestContext {
+        profile_id: None,
+        device_id: "device".to_string(),
+        session_id: "session".to_string(),
+        app_version: env!("CARGO_PKG_VERSION"),
+        day: LogicalDay::new(None, 0, &SystemClock),
+    };
+
+    let opened = open_vault(&database_path, &backup_dir, PASSPHRASE, false)
+        .await
+        .unwrap();
+    let page = CreateDiaryInput {
+        entry_date: "2026-05-01".to_string(),
+        title: None,
+        content_json: "[]".to_string(),
+        parent_page_id: None,
+    };
+    let entity = EntityRef {
+        kind: EntityKind::Diary,
+        id: insert_entry(&opened.pools.writer, &page, &ctx)
+            .await
+            .unwrap(),
+    };
+    let source = dir.join("notes.txt");
+    fs::write(&source, "remember the milk").unwrap();
+    let link = add_attachment(
+        &opened.pools.writer,
+        &store,
+        &opened.key,
+        &entity,
+        &source,
+        None,
+        &ctx,
+    )
+    .await
+    .unwrap();
+    opened.pools.close().await;
+
+    let new_key = rekey_vault(&database_path, &opened.key, PASSPHRASE)
+        .await
+        .unwrap();
+    let reopened = open_vault(&database_path, &backup_dir, PASSPHRASE, false)
+        .await
+        .unwrap();
+    let bytes = read_attachment(
+        &reopened.pools.reader,
+        &store,
+        &new_key,
+        &link.attachment_id,
+    )
+    .await
+    .unwrap();
+    assert_eq!(bytes, b"remember the milk");
+    let shard = store.join(&link.attachment_id[..2]);
+    assert_eq!(fs::read_dir(shard).unwrap().count(), 1);
+
+    reopened.pools.close().await;
+    fs::remove_dir_all(&dir).unwrap();
+}
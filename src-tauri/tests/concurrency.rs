@@ -0,0 +1,83 @@
+use app_lib::app::config::Config;
+use app_lib::app::state::{AppState, EncryptionStatus};
+use app_lib::db::vault::{open_vault, VaultRegistry};
+use app_lib::domains::habits::repository::list_habits;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn slow_command_does_not_block_other_commands() {
+    let dir = std::env::temp_dir().join(format!("nocturne-concurrency-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let config = Config {
+        database_path: dir.join("nocturne.db"),
+        ..Config::default()
+    };
+    let vaults = VaultRegistry::load(&dir, &config.database_path).unwrap();
+    let state = Arc::new(AppState::new(
+        config,
+        dir.clone(),
+        uuid::Uuid::new_v4().to_string(),
+        EncryptionStatus::PlaintextOptIn,
+        vaults,
+    ));
+
+    {
+        let mut session = state.session.lock().await;
+        let vault = session.active_vault.clone();
+        let backup_dir = dir.join("backups");
+        let opened = open_vault(
+            &vault.database_path,
+            &backup_dir,
+            "correct horse battery",
+            false,
+        )
+        .await
+        .unwrap();
+        state
+            .activate_vault(&mut session, vault, opened)
+            .await
+            .unwrap();
+    }
+
+    // Stand-in for an expensive dashboard refresh: a command that keeps one connection
+    // busy inside a read transaction until the test lets it go.
+    let (started_tx, started_rx) = oneshot::channel();
+    let (release_tx, release_rx) = oneshot::channel::<()>();
+    let slow_state = state.clone();
+    let slow = tokio::spawn(async move {
+        let pool = slow_state.reader().unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        let count: i64 = sqlx::query_scalar("SELECT count(*) FROM habits")
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+        started_tx.send(()).unwrap();
+        release_rx.await.unwrap();
+        tx.commit().await.unwrap();
+        count
+    });
+    started_rx.await.unwrap();
+
+    // A second command gets the pool and finishes its query while the first is still running.
+    let pool = state.reader().unwrap();
+    let habits = tokio::time::timeout(Duration::from_secs(5), list_habits(&pool))
+        .await
+        .expect("habit query was blocked by the slow command")
+        .unwrap();
+    assert!(habits.is_empty());
+    assert!(!slow.is_finished());
+
+    // Holding the session lock (as unlock and rekey do) doesn't block ordinary commands either.
+    let session = state.session.lock().await;
+    assert!(state.writer().is_ok());
+    assert_eq!(state.config().auto_lock_minutes, 15);
+    drop(session);
+
+    release_tx.send(()).unwrap();
+    assert_eq!(slow.await.unwrap(), 0);
+    state.lock_vault().await;
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,107 @@
+use app_lib::app::context::RequestContext;
+use app_lib::db::sync::sync_with_folder;
+use app_lib::db::vault::open_vault;
+use app_lib::domains::habits::habit_log::{habit_log_id, CreateHabitLogInput};
+use app_lib::domains::habits::model::CreateHabitInput;
+use app_lib::domains::habits::repository::{
+    get_habit_log_for_date, insert_habit, insert_habit_log,
+};
+use app_lib::utils::time::{LogicalDay, SystemClock};
+use std::fs;
+
+fn ctx(device: &str) -> RequestContext {
+    RequestContext {
+        profile_id: None,
+        device_id: device.to_string(),
+        session_id: "session".to_string(),
+        app_version: env!("CARGO_PKG_VERSION"),
+        day: LogicalDay::new(None, 0, &SystemClock),
+    }
+}
+
+fn log(habit_id: &str, status: &str) -> CreateHabitLogInput {
+    CreateHabitLogInput {
+        habit_id: habit_id.to_string(),
+        log_date: None,
+        value: None,
+        status: status.to_string(),
+        note: None,
+        mood: None,
+        energy_level: None,
+    }
+}
+
+#[tokio::test]
+async fn same_day_logs_from_two_devices_merge() {
+    let dir = std::env::temp_dir().join(format!("nocturne-habit-sync-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(dir.join("a")).unwrap();
+    fs::create_dir_all(dir.join("b")).unwrap();
+    let folder = dir.join("shared");
+    let a = open_vault(
+        &dir.join("a/n.db"),
+        &dir.join("a/bk"),
+        "correct horse",
+        false,
+    )
+    .await
+    .unwrap();
+    fs::copy(dir.join("a/n.key"), dir.join("b/n.key")).unwrap();
+    let b = open_vault(
+        &dir.join("b/n.db"),
+        &dir.join("b/bk"),
+        "correct horse",
+        false,
+    )
+    .await
+    .unwrap();
+    let (ca, cb) = (ctx("device-a"), ctx("device-b"));
+    let (pa, pb) = (&a.pools.writer, &b.pools.writer);
+    let day = ca.day.today_string();
+
+    let habit = CreateHabitInput {
+        habit_name: "Stretch".to_string(),
+        habit_type: "boolean".to_string(),
+        habit_description: None,
+        habit_icon_emoji: None,
+        habit_color: None,
+        schedule_type: "daily".to_string(),
+    };
+    let habit_id = insert_habit(pa, &habit, &ca).await.unwrap();
+    sync_with_folder(pa, &a.key, &folder, &ca).await.unwrap();
+    sync_with_folder(pb, &b.key, &folder, &cb).await.unwrap();
+
+    let first = insert_habit_log(pa, &log(&habit_id, "skipped"), &day, &ca)
+        .await
+        .unwrap();
+    let again = insert_habit_log(pa, &log(&habit_id, "partial"), &day, &ca)
+        .await
+        .unwrap();
+    assert_eq!(first, habit_log_id(&habit_id, &day));
+    assert_eq!(again, first);
+
+    // B's log stands in for one made before log IDs came from the day.
+    insert_habit_log(pb, &log(&habit_id, "completed"), &day, &cb)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE habit_logs SET log_id = 'legacy-log' WHERE habit_id = ?")
+        .bind(&habit_id)
+        .execute(pb)
+        .await
+        .unwrap();
+
+    sync_with_folder(pb, &b.key, &folder, &cb).await.unwrap();
+    sync_with_folder(pa, &a.key, &folder, &ca).await.unwrap();
+    sync_with_folder(pb, &b.key, &folder, &cb).await.unwrap();
+
+    let on_a = get_habit_log_for_date(pa, &habit_id, &day).await.unwrap();
+    let on_b = get_habit_log_for_date(pb, &habit_id, &day).await.unwrap();
+    assert_eq!(
+        on_a.map(|l| (l.log_id, l.status)),
+        Some((first, "completed".to_string()))
+    );
+    assert_eq!(on_b.map(|l| l.status), Some("completed".to_string()));
+
+    a.pools.close().await;
+    b.pools.close().await;
+    fs::remove_dir_all(&dir).unwrap();
+}
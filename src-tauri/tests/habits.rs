@@ -0,0 +1,75 @@
+use app_lib::app::context::RequestContext;
+use app_lib::db::relations::EntityKind;
+use app_lib::db::search::{search, SearchQuery};
+use app_lib::db::vault::open_vault;
+use app_lib::domains::habits::model::CreateHabitInput;
+use app_lib::domains::habits::repository::{backfill_created_dates, fetch_habit, insert_habit};
+use app_lib::utils::time::{FixedClock, LogicalDay};
+use chrono::DateTime;
+use std::fs;
+
+#[tokio::test]
+async fn habits_are_dated_by_the_logical_day_they_were_added() {
+    let dir = std::env::temp_dir().join(format!("nocturne-habits-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let opened = open_vault(
+        &dir.join("nocturne.db"),
+        &dir.join("backups"),
+        "correct horse battery",
+        false,
+    )
+    .await
+    .unwrap();
+    let pool = &opened.pools.writer;
+    // 02:00 in Berlin, before a 04:00 day start.
+    let at = DateTime::parse_from_rfc3339("2026-03-10T01:00:00Z")
+        .unwrap()
+        .to_utc();
+    let ctx = RequestContext {
+        profile_id: None,
+        device_id: "device".to_string(),
+        session_id: "session".to_string(),
+        app_version: env!("CARGO_PKG_VERSION"),
+        day: LogicalDay::new(Some("Europe/Berlin"), 4, &FixedClock(at)),
+    };
+
+    let habit = CreateHabitInput {
+        habit_name: "Stretch".to_string(),
+        habit_type: "boolean".to_string(),
+        habit_description: None,
+        habit_icon_emoji: None,
+        habit_color: None,
+        schedule_type: "daily".to_string(),
+    };
+    let added = insert_habit(pool, &habit, &ctx).await.unwrap();
+    let legacy = insert_habit(pool, &habit, &ctx).await.unwrap();
+    sqlx::query("UPDATE habits SET habit_created_date = NULL, created_at = ? WHERE habit_id = ?")
+        .bind(at.timestamp())
+        .bind(&legacy)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    assert_eq!(backfill_created_dates(pool, &ctx).await.unwrap(), 1);
+    assert_eq!(backfill_created_dates(pool, &ctx).await.unwrap(), 0);
+    for id in [&added, &legacy] {
+        let habit = fetch_habit(pool, id).await.unwrap();
+        assert_eq!(habit.habit_created_date.as_deref(), Some("2026-03-09"));
+    }
+
+    let query = SearchQuery {
+        query: "stret".to_string(),
+        kinds: Some(vec![EntityKind::Habit]),
+        from_date: Some("2026-03-09".to_string()),
+        to_date: Some("2026-03-09".to_string()),
+        limit: None,
+    };
+    let hits = search(pool, &query).await.unwrap();
+    assert_eq!(hits.len(), 2);
+    assert!(hits
+        .iter()
+        .all(|hit| hit.date.as_deref() == Some("2026-03-09")));
+
+    opened.pools.close().await;
+    fs::remove_dir_all(&dir).unwrap();
+}
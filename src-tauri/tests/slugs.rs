@@ -0,0 +1,72 @@
+use app_lib::app::context::RequestContext;
+use app_lib::db::slugs::{backfill_slugs, resolve_slug, GOAL_SLUGS};
+use app_lib::db::vault::open_vault;
+use app_lib::domains::goals::model::CreateGoalInput;
+use app_lib::domains::goals::repository::{fetch_goal, insert_goal};
+use app_lib::utils::time::{LogicalDay, SystemClock};
+use std::fs;
+
+fn goal(title: &str) -> CreateGoalInput {
+    CreateGoalInput {
+        goal_title: title.to_string(),
+        goal_type: "outcome".to_string(),
+        goal_category: None,
+        goal_description: None,
+        goal_target_date: None,
+    }
+}
+
+#[tokio::test]
+async fn backfill_regenerates_slugs_that_are_not_the_records_own() {
+    let dir = std::env::temp_dir().join(format!("nocturne-slugs-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let opened = open_vault(
+        &dir.join("nocturne.db"),
+        &dir.join("backups"),
+        "correct horse battery",
+        false,
+    )
+    .await
+    .unwrap();
+    let pool = &opened.pools.writer;
+    let ctx = RequestContext {
+        profile_id: None,
+        device_id: "device".to_string(),
+        session_id: "session".to_string(),
+        app_version: env!("CARGO_PKG_VERSION"),
+        day: LogicalDay::new(None, 0, &SystemClock),
+    };
+
+    let first = insert_goal(pool, &goal("Plan"), &ctx).await.unwrap();
+    let second = insert_goal(pool, &goal("Plan"), &ctx).await.unwrap();
+    let legacy = insert_goal(pool, &goal("Plan"), &ctx).await.unwrap();
+    let year = insert_goal(pool, &goal("Plan"), &ctx).await.unwrap();
+    for (id, slug) in [(&legacy, "plan-1a2b3c4d"), (&year, "plan-2024")] {
+        sqlx::query("UPDATE goals SET goal_slug = ? WHERE goal_id = ?")
+            .bind(slug)
+            .bind(id)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(backfill_slugs(pool, &ctx).await.unwrap(), 2);
+    assert_eq!(backfill_slugs(pool, &ctx).await.unwrap(), 0);
+
+    let slug = |id: String| async move { fetch_goal(pool, &id).await.unwrap().goal_slug };
+    assert_eq!(slug(first).await, "plan");
+    assert_eq!(slug(second).await, "plan-2");
+    // "plan-2024" looks like a numbered slug, but not one this goal would be given.
+    assert_eq!(slug(legacy.clone()).await, "plan-3");
+    assert_eq!(slug(year.clone()).await, "plan-4");
+
+    let resolved = resolve_slug(pool, &GOAL_SLUGS, "plan-2024").await.unwrap();
+    assert_eq!(resolved, Some(year));
+    let resolved = resolve_slug(pool, &GOAL_SLUGS, "plan-1a2b3c4d")
+        .await
+        .unwrap();
+    assert_eq!(resolved, Some(legacy));
+
+    opened.pools.close().await;
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,90 @@
+use app_lib::app::context::RequestContext;
+use app_lib::db::integrity::recount_tree;
+use app_lib::db::relations::{EntityKind, EntityRef};
+use app_lib::db::trash::{delete_entity, purge_entity};
+use app_lib::db::vault::open_vault;
+use app_lib::domains::diary::model::CreateDiaryInput;
+use app_lib::domains::diary::repository::insert_entry;
+use app_lib::utils::time::{LogicalDay, SystemClock};
+use std::fs;
+
+fn page(title: &str, parent: Option<&str>) -> CreateDiaryInput {
+    CreateDiaryInput {
+        entry_date: "2026-05-01".to_string(),
+        title: Some(title.to_string()),
+        content_json: "[]".to_string(),
+        parent_page_id: parent.map(str::to_string),
+    }
+}
+
+#[tokio::test]
+async fn purging_a_page_purges_its_sub_pages() {
+    let dir = std::env::temp_dir().join(format!("nocturne-trash-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    let opened = open_vault(
+        &dir.join("nocturne.db"),
+        &dir.join("backups"),
+        "correct horse battery",
+        false,
+    )
+    .await
+    .unwrap();
+    let pool = &opened.pools.writer;
+    let ctx = RequestContext {
+        profile_id: None,
+        device_id: "device".to_string(),
+        session_id: "session".to_string(),
+        app_version: env!("CARGO_PKG_VERSION"),
+        day: LogicalDay::new(None, 0, &SystemClock),
+    };
+
+    let root = insert_entry(pool, &page("Day", None), &ctx).await.unwrap();
+    let child = insert_entry(pool, &page("Notes", Some(&root)), &ctx)
+        .await
+        .unwrap();
+    let grandchild = insert_entry(pool, &page("More", Some(&child)), &ctx)
+        .await
+        .unwrap();
+    for (id, parent) in [(&child, &root), (&grandchild, &child)] {
+        sqlx::query(
+            "UPDATE diary_entries
+             SET parent_page_id = ?1, root_page_id = ?3, primary_page_id = ?3
+             WHERE diary_entry_id = ?2",
+        )
+        .bind(parent)
+        .bind(id)
+        .bind(&root)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+    let mut tx = pool.begin().await.unwrap();
+    recount_tree(&mut tx).await.unwrap();
+    tx.commit().await.unwrap();
+
+    let entity = EntityRef {
+        kind: EntityKind::Diary,
+        id: root.clone(),
+    };
+    delete_entity(pool, &entity, None, &ctx).await.unwrap();
+    purge_entity(pool, &entity, &ctx).await.unwrap();
+
+    let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM diary_entries")
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(left, 0);
+    let mut purged: Vec<String> = sqlx::query_scalar(
+        "SELECT record_id FROM purged_records WHERE table_name = 'diary_entries'",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap();
+    purged.sort();
+    let mut expected = vec![root, child, grandchild];
+    expected.sort();
+    assert_eq!(purged, expected);
+
+    opened.pools.close().await;
+    fs::remove_dir_all(&dir).unwrap();
+}